@@ -0,0 +1,250 @@
+use crate::config::write_file_atomic;
+use crate::status::InjectionStatus;
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::Path;
+
+/// Oldest entries are dropped beyond this many
+pub const MAX_ACTIVITY_ENTRIES: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Success,
+    Error,
+}
+
+impl Severity {
+    pub fn of(status: &InjectionStatus) -> Self {
+        match status {
+            InjectionStatus::Idle | InjectionStatus::InProgress(_) => Severity::Info,
+            InjectionStatus::Success(_, _)
+            | InjectionStatus::ConfigLoaded(_, _, _)
+            | InjectionStatus::ConfigSaved(_) => Severity::Success,
+            InjectionStatus::Error(_) => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Severity::Info => "INFO",
+            Severity::Success => "OK",
+            Severity::Error => "ERROR",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// What reported a status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Manual, // Injections and config actions started from the window
+    Watch,
+    Api,
+    Queue,
+    Host,
+    Profile,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            Source::Manual => "manual",
+            Source::Watch => "watch",
+            Source::Api => "api",
+            Source::Queue => "queue",
+            Source::Host => "host",
+            Source::Profile => "profile",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+/// One status transition
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActivityEntry {
+    pub time: DateTime<Local>,
+    pub severity: Severity,
+    pub source: Source,
+    pub message: String,
+}
+
+impl ActivityEntry {
+    /// The entry as a line of text, e.g.
+    /// `2024-05-01 18:03:27.412 ERROR [queue] Error: Connection refused`
+    pub fn line(&self) -> String {
+        format!(
+            "{} {} [{}] {}",
+            self.time.format("%Y-%m-%d %H:%M:%S%.3f"),
+            self.severity,
+            self.source,
+            self.message
+        )
+    }
+}
+
+/// Every status reported while the app runs, newest last. Kept in memory only.
+#[derive(Debug, Clone, Default)]
+pub struct ActivityLog {
+    entries: VecDeque<ActivityEntry>,
+}
+
+impl ActivityLog {
+    /// Record a status; Idle is not an event
+    pub fn record(&mut self, source: Source, status: &InjectionStatus) {
+        if matches!(status, InjectionStatus::Idle) {
+            return;
+        }
+
+        let message = status.message();
+        self.entries.push_back(ActivityEntry {
+            time: Local::now(),
+            severity: Severity::of(status),
+            source,
+            message,
+        });
+        if self.entries.len() > MAX_ACTIVITY_ENTRIES {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &ActivityEntry> {
+        self.entries.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Entries of `severity`, or of any severity, whose line contains `text`
+    /// ignoring case
+    pub fn filtered<'a>(
+        &'a self,
+        severity: Option<Severity>,
+        text: &str,
+    ) -> impl Iterator<Item = &'a ActivityEntry> {
+        let text = text.trim().to_lowercase();
+        self.entries.iter().filter(move |entry| {
+            (severity.is_none() || severity == Some(entry.severity))
+                && (text.is_empty() || entry.line().to_lowercase().contains(&text))
+        })
+    }
+}
+
+/// Entries as text, one line each
+pub fn to_text<'a>(entries: impl IntoIterator<Item = &'a ActivityEntry>) -> String {
+    entries
+        .into_iter()
+        .map(|entry| entry.line() + "\n")
+        .collect()
+}
+
+/// Write entries to a text file
+pub fn export<'a, P: AsRef<Path>>(
+    path: P,
+    entries: impl IntoIterator<Item = &'a ActivityEntry>,
+) -> Result<(), String> {
+    write_file_atomic(path.as_ref(), to_text(entries).as_bytes(), "activity log")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum::Checksums;
+
+    #[test]
+    fn test_record_skips_idle() {
+        let mut log = ActivityLog::default();
+        let sending = InjectionStatus::InProgress("Sending 'a.elf'...".to_string());
+
+        log.record(Source::Manual, &InjectionStatus::Idle);
+        assert!(log.is_empty());
+
+        log.record(Source::Manual, &sending);
+        log.record(Source::Queue, &sending);
+        log.record(
+            Source::Manual,
+            &InjectionStatus::Success(
+                3,
+                Some(Checksums {
+                    sha256: "ab12".to_string(),
+                    crc32: None,
+                }),
+            ),
+        );
+
+        // Results are recorded every time
+        let refused = InjectionStatus::Error("Connection refused".to_string());
+        log.record(Source::Manual, &refused);
+        log.record(Source::Manual, &refused);
+
+        let sources: Vec<(Source, Severity)> = log
+            .entries()
+            .map(|entry| (entry.source, entry.severity))
+            .collect();
+        assert_eq!(
+            sources,
+            [
+                (Source::Manual, Severity::Info),
+                (Source::Queue, Severity::Info),
+                (Source::Manual, Severity::Success),
+                (Source::Manual, Severity::Error),
+                (Source::Manual, Severity::Error),
+            ]
+        );
+        assert_eq!(
+            log.entries().nth(2).unwrap().message,
+            "Success! Sent 3 bytes (SHA-256 ab12)"
+        );
+    }
+
+    #[test]
+    fn test_oldest_entries_dropped() {
+        let mut log = ActivityLog::default();
+        for i in 0..MAX_ACTIVITY_ENTRIES + 5 {
+            log.record(Source::Watch, &InjectionStatus::InProgress(i.to_string()));
+        }
+        assert_eq!(log.entries().count(), MAX_ACTIVITY_ENTRIES);
+        assert_eq!(log.entries().next().unwrap().message, "5");
+    }
+
+    #[test]
+    fn test_filter_and_export() {
+        let mut log = ActivityLog::default();
+        log.record(
+            Source::Manual,
+            &InjectionStatus::InProgress("Connecting to 10.0.0.2:9021...".to_string()),
+        );
+        log.record(
+            Source::Manual,
+            &InjectionStatus::Error("Connection refused".to_string()),
+        );
+        log.record(
+            Source::Api,
+            &InjectionStatus::Error("File does not exist".to_string()),
+        );
+
+        assert_eq!(log.filtered(Some(Severity::Error), "").count(), 2);
+        assert_eq!(log.filtered(None, "REFUSED").count(), 1);
+        assert_eq!(log.filtered(Some(Severity::Info), "refused").count(), 0);
+        assert_eq!(log.filtered(None, "[api]").count(), 1);
+
+        let line = log.entries().next().unwrap().line();
+        assert!(line.ends_with(" INFO [manual] Connecting to 10.0.0.2:9021..."));
+
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("activity.log");
+        export(&path, log.filtered(Some(Severity::Error), "")).expect("Failed to export");
+        let text = std::fs::read_to_string(&path).expect("Failed to read export");
+        assert_eq!(text.lines().count(), 2);
+        assert!(text.contains("ERROR [manual] Error: Connection refused"));
+        assert!(text.contains("ERROR [api] Error: File does not exist"));
+    }
+}
@@ -0,0 +1,911 @@
+use crate::archive;
+use crate::checksum::Checksums;
+use crate::config::DataFile;
+use crate::handlers::create_inject_fn;
+use crate::history::{History, HistoryEntry, Trigger};
+use crate::network::http::{self, Request, Response};
+use crate::network::{self, ServerHandle, STDIN_PATH};
+use crate::payload::{Manifest, PayloadCheck};
+use crate::profiles::Profiles;
+use crate::settings::Settings;
+use crate::status::InjectionStatus;
+use serde::{Deserialize, Serialize};
+use std::net::ToSocketAddrs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, oneshot};
+
+pub const DEFAULT_API_BIND: &str = "127.0.0.1:8787";
+
+/// Comment sent on idle event streams so dead clients are noticed
+const EVENT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Larger files are not hashed to see whether they are a manifest build
+const MAX_CHECKED_PAYLOAD_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiOptions {
+    pub bind: String,
+    /// When set, every request must carry `Authorization: Bearer <token>`
+    /// or a `token=<token>` query parameter
+    pub token: Option<String>,
+    /// Payloads under this directory may be named in `POST /inject`, besides
+    /// the configured one, those of profiles and builds in the manifest
+    pub payload_root: Option<PathBuf>,
+}
+
+impl Default for ApiOptions {
+    fn default() -> Self {
+        Self {
+            bind: DEFAULT_API_BIND.to_string(),
+            token: None,
+            payload_root: None,
+        }
+    }
+}
+
+/// State of the latest injection started through the API
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StatusEvent {
+    pub job: u64, // 0 until the first injection
+    pub state: String,
+    pub message: String,
+    pub bytes_sent: Option<usize>,
+    pub sha256: Option<String>, // Of the bytes sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crc32: Option<String>, // Of the bytes sent, lowercase hex
+}
+
+impl StatusEvent {
+    pub fn from_status(job: u64, status: &InjectionStatus) -> Self {
+        let (state, message) = match status {
+            InjectionStatus::Idle => ("idle", "Idle".to_string()),
+            InjectionStatus::InProgress(msg) => ("in_progress", msg.clone()),
+            InjectionStatus::Success(bytes, _) => {
+                ("success", format!("Success! Sent {} bytes", bytes))
+            }
+            InjectionStatus::Error(msg) => ("error", msg.clone()),
+            InjectionStatus::ConfigLoaded(_, _, _) => ("idle", "Config loaded".to_string()),
+            InjectionStatus::ConfigSaved(msg) => ("idle", msg.clone()),
+        };
+        let (bytes_sent, checksums) = match status {
+            InjectionStatus::Success(bytes, checksums) => (Some(*bytes), checksums.as_ref()),
+            _ => (None, None),
+        };
+
+        Self {
+            job,
+            state: state.to_string(),
+            message,
+            bytes_sent,
+            sha256: checksums.map(|checksums| checksums.sha256.clone()),
+            crc32: checksums.and_then(Checksums::crc32_text),
+        }
+    }
+
+    pub fn is_in_progress(&self) -> bool {
+        self.state == "in_progress"
+    }
+}
+
+/// Body of `POST /inject`. Unset fields come from the profile if one is named,
+/// otherwise from the saved config.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct InjectRequest {
+    pub profile: Option<String>,
+    pub ip: Option<String>,
+    pub port: Option<String>,
+    pub file_path: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PayloadInfo {
+    pub name: String,
+    pub path: String,
+    pub exists: bool,
+}
+
+struct ApiState {
+    token: Option<String>,
+    payload_root: Option<PathBuf>,
+    current: Mutex<StatusEvent>,
+    next_job: AtomicU64,
+    events: broadcast::Sender<StatusEvent>,
+    observer: Option<mpsc::Sender<InjectionStatus>>,
+}
+
+impl ApiState {
+    fn current(&self) -> StatusEvent {
+        self.current
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn publish(&self, job: u64, status: &InjectionStatus) {
+        let event = StatusEvent::from_status(job, status);
+        *self.current.lock().unwrap_or_else(|e| e.into_inner()) = event.clone();
+        let _ = self.events.send(event);
+        if let Some(observer) = &self.observer {
+            let _ = observer.send(status.clone());
+        }
+    }
+
+    fn is_authorized(&self, request: &Request) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+
+        let matches = |given: Option<&str>| {
+            given.is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()))
+        };
+        let bearer = request
+            .header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+        // Check both so the time taken does not tell which one was tried
+        matches(bearer) | matches(request.query_param("token"))
+    }
+
+    fn listed_payloads(&self) -> ListedPayloads {
+        let mut paths = vec![Settings::load().file_path.value];
+        paths.extend(
+            Profiles::load_or_default()
+                .profiles
+                .into_iter()
+                .map(|profile| profile.file_path),
+        );
+        ListedPayloads {
+            root: self
+                .payload_root
+                .as_deref()
+                .and_then(|root| root.canonicalize().ok()),
+            known: paths.iter().filter_map(|path| payload_key(path)).collect(),
+        }
+    }
+
+    /// Whether a client may name `file_path` in `POST /inject`. Only payloads
+    /// the app already knows can be sent: the configured one, those of
+    /// profiles, files under the payload root and builds listed in the manifest.
+    async fn may_send(&self, file_path: &str) -> bool {
+        if self.listed_payloads().contains(file_path) {
+            return true;
+        }
+
+        let manifest = Manifest::load_or_default();
+        if manifest.payloads.is_empty() {
+            return false;
+        }
+        // Hashing reads the whole file, so keep it off the runtime's threads
+        let file_path = file_path.to_string();
+        tokio::task::spawn_blocking(move || {
+            is_checkable(&file_path)
+                && matches!(
+                    manifest.check_file(&file_path),
+                    Ok(PayloadCheck::Recognised(_))
+                )
+        })
+        .await
+        .unwrap_or(false)
+    }
+}
+
+/// Payloads clients may name without a manifest check: the configured one,
+/// those of profiles and files under the payload root
+struct ListedPayloads {
+    root: Option<PathBuf>,
+    known: Vec<(PathBuf, Option<String>)>,
+}
+
+impl ListedPayloads {
+    fn contains(&self, file_path: &str) -> bool {
+        let Some(requested) = payload_key(file_path) else {
+            return false;
+        };
+        self.root
+            .as_ref()
+            .is_some_and(|root| requested.0.starts_with(root))
+            || self.known.contains(&requested)
+    }
+}
+
+/// Whether `file_path` is a regular file, or an entry of one, small enough to
+/// hash. Devices such as `/dev/zero` would never finish.
+fn is_checkable(file_path: &str) -> bool {
+    let is_small_file = std::fs::metadata(archive::backing_file(file_path))
+        .is_ok_and(|metadata| metadata.is_file() && metadata.len() <= MAX_CHECKED_PAYLOAD_SIZE);
+    match archive::ArchivePath::parse(file_path) {
+        Some(entry) if is_small_file => archive::with_entry(&entry, |_, size| Ok(size))
+            .is_ok_and(|size| size <= MAX_CHECKED_PAYLOAD_SIZE),
+        _ => is_small_file,
+    }
+}
+
+/// The canonical file behind a payload path and the archive entry, if any,
+/// so different spellings of the same payload compare equal
+fn payload_key(path: &str) -> Option<(PathBuf, Option<String>)> {
+    if path.trim().is_empty() || path == STDIN_PATH {
+        return None;
+    }
+    let file = archive::backing_file(path).canonicalize().ok()?;
+    let entry = archive::ArchivePath::parse(path).map(|archive_path| archive_path.entry);
+    Some((file, entry))
+}
+
+/// Compare secrets in time that only depends on their lengths
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Whether every address `bind` resolves to is a loopback address. Addresses
+/// that do not resolve count as loopback; binding them reports the error.
+fn is_loopback_bind(bind: &str) -> bool {
+    bind.to_socket_addrs()
+        .map(|mut addrs| addrs.all(|addr| addr.ip().is_loopback()))
+        .unwrap_or(true)
+}
+
+/// Start the control API on a background thread. Statuses of injections it
+/// starts are also sent to `observer`, so the GUI can show them.
+pub fn start(
+    options: ApiOptions,
+    observer: Option<mpsc::Sender<InjectionStatus>>,
+) -> Result<ServerHandle, String> {
+    let token = options.token.filter(|token| !token.is_empty());
+    if token.is_none() && !is_loopback_bind(&options.bind) {
+        return Err(format!(
+            "The control API needs --api-token to listen on {}, which is reachable from the network",
+            options.bind
+        ));
+    }
+
+    let (events, _) = broadcast::channel(64);
+    let state = Arc::new(ApiState {
+        token,
+        payload_root: options.payload_root,
+        current: Mutex::new(StatusEvent::from_status(0, &InjectionStatus::Idle)),
+        next_job: AtomicU64::new(1),
+        events,
+        observer,
+    });
+
+    network::spawn_server(&options.bind, "control API", move |stream, _| {
+        handle_connection(stream, state.clone())
+    })
+}
+
+async fn handle_connection(mut stream: TcpStream, state: Arc<ApiState>) {
+    let response = match http::read_request(&mut stream).await {
+        Ok(request) if !state.is_authorized(&request) => {
+            Response::error(401, "Missing or invalid token")
+                .with_header("WWW-Authenticate", "Bearer")
+        }
+        Ok(request) if request.method == "GET" && request.path == "/events" => {
+            stream_events(&mut stream, &state).await;
+            return;
+        }
+        Ok(request) => route(&request, &state).await,
+        Err(e) => Response::error(400, &e),
+    };
+
+    let _ = http::write_response(&mut stream, &response).await;
+}
+
+async fn route(request: &Request, state: &Arc<ApiState>) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => Response::json(
+            200,
+            &serde_json::json!({
+                "endpoints": [
+                    "GET /status",
+                    "GET /profiles",
+                    "GET /payloads",
+                    "POST /inject",
+                    "GET /events",
+                ]
+            }),
+        ),
+        ("GET", "/status") => Response::json(200, &state.current()),
+        ("GET", "/profiles") => Response::json(200, &Profiles::load_or_default().profiles),
+        ("GET", "/payloads") => Response::json(200, &known_payloads(state)),
+        ("POST", "/inject") => inject(request, state).await,
+        (_, "/" | "/status" | "/profiles" | "/payloads" | "/inject" | "/events") => {
+            Response::error(405, &format!("{} not allowed here", request.method))
+        }
+        _ => Response::error(404, &format!("No endpoint at {}", request.path)),
+    }
+}
+
+async fn inject(request: &Request, state: &Arc<ApiState>) -> Response {
+    let inject_request: InjectRequest = if request.body.iter().all(u8::is_ascii_whitespace) {
+        InjectRequest::default()
+    } else {
+        match serde_json::from_slice(&request.body) {
+            Ok(inject_request) => inject_request,
+            Err(e) => return Response::error(400, &format!("Invalid request body: {}", e)),
+        }
+    };
+
+    if let Some(file_path) = &inject_request.file_path {
+        if !state.may_send(file_path).await {
+            return Response::error(
+                403,
+                &format!(
+                    "'{}' is not the configured payload, a profile's payload, a manifest build or under the payload root",
+                    file_path
+                ),
+            );
+        }
+    }
+
+    let (ip, port, file_path) = match resolve_target(&inject_request) {
+        Ok(target) => target,
+        Err(e) => return Response::error(400, &e),
+    };
+
+    // Claim the job slot under the lock so two requests cannot both start
+    let job = {
+        let mut current = state.current.lock().unwrap_or_else(|e| e.into_inner());
+        if current.is_in_progress() {
+            return Response::error(
+                409,
+                &format!("Injection job {} is still in progress", current.job),
+            );
+        }
+        let job = state.next_job.fetch_add(1, Ordering::SeqCst);
+        *current = StatusEvent::from_status(
+            job,
+            &InjectionStatus::InProgress("Preparing injection...".to_string()),
+        );
+        job
+    };
+
+    run_injection(
+        state.clone(),
+        job,
+        ip.clone(),
+        port.clone(),
+        file_path.clone(),
+    );
+
+    Response::json(
+        202,
+        &serde_json::json!({
+            "job": job,
+            "ip": ip,
+            "port": port,
+            "file_path": file_path,
+        }),
+    )
+}
+
+/// Work out the target of an inject request and validate it
+fn resolve_target(request: &InjectRequest) -> Result<(String, String, String), String> {
+    let settings = Settings::load();
+    let (mut ip, mut port, mut file_path) = (
+        settings.ip.value,
+        settings.port.value,
+        settings.file_path.value,
+    );
+
+    if let Some(name) = &request.profile {
+        let profiles = Profiles::load_or_default();
+        let profile = profiles
+            .find(name)
+            .ok_or_else(|| format!("No profile named '{}'", name))?;
+        ip = profile.ip.clone();
+        port = profile.port.clone();
+        file_path = profile.file_path.clone();
+    }
+
+    ip = request.ip.clone().unwrap_or(ip);
+    port = request.port.clone().unwrap_or(port);
+    file_path = request.file_path.clone().unwrap_or(file_path);
+
+    if ip.trim().is_empty() {
+        return Err("IP address is required".to_string());
+    }
+    if port.parse::<u16>().is_err() {
+        return Err(format!("Invalid port number: {}", port));
+    }
+    if file_path.trim().is_empty() {
+        return Err("No file selected".to_string());
+    }
+    if !archive::payload_exists(&file_path) {
+        return Err(format!("File does not exist: {}", file_path));
+    }
+
+    Ok((ip, port, file_path))
+}
+
+/// Run an injection through the same handler the GUI uses and publish its statuses
+fn run_injection(state: Arc<ApiState>, job: u64, ip: String, port: String, file_path: String) {
+    let (sender, receiver) = mpsc::channel();
+    // API injections are never cancelled
+    let (_, cancel) = oneshot::channel();
+    create_inject_fn()(&ip, &port, &file_path, sender, cancel);
+
+    std::thread::spawn(move || {
+        let entry = HistoryEntry::new(Trigger::Api, &ip, &port, &file_path);
+
+        // Ends when the injection thread drops its sender
+        for status in receiver {
+            let finished = match &status {
+                InjectionStatus::Success(bytes_sent, _) => {
+                    Some(entry.clone().succeeded(*bytes_sent, status.sha256()))
+                }
+                InjectionStatus::Error(e) => Some(entry.clone().failed(e)),
+                _ => None,
+            };
+            if let Some(finished) = finished {
+                // Only the app hears of it; clients would take it for the
+                // job's result
+                if let (Err(e), Some(observer)) = (History::record(finished), &state.observer) {
+                    let _ = observer.send(InjectionStatus::Error(e));
+                }
+            }
+            state.publish(job, &status);
+        }
+    });
+}
+
+/// Payload files clients may send: the configured one, those in the history
+/// that are under the payload root, most recently used first, and those of
+/// profiles. Manifest builds are only known by their hash, so they are not listed.
+fn known_payloads(state: &ApiState) -> Vec<PayloadInfo> {
+    let listed = state.listed_payloads();
+    let mut paths = vec![Settings::load().file_path.value];
+    paths.extend(
+        History::load_or_default()
+            .entries
+            .into_iter()
+            .rev()
+            .map(|entry| entry.file_path),
+    );
+    paths.extend(
+        Profiles::load_or_default()
+            .profiles
+            .into_iter()
+            .map(|profile| profile.file_path),
+    );
+
+    let mut payloads: Vec<PayloadInfo> = Vec::new();
+    for path in paths {
+        if !listed.contains(&path) || payloads.iter().any(|p| p.path == path) {
+            continue;
+        }
+        payloads.push(PayloadInfo {
+            name: Path::new(&path)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or(&path)
+                .to_string(),
+            exists: archive::payload_exists(&path),
+            path,
+        });
+    }
+    payloads
+}
+
+/// Stream status changes as server-sent events, starting with the current status
+async fn stream_events(stream: &mut TcpStream, state: &ApiState) {
+    let headers = [
+        ("Content-Type".to_string(), "text/event-stream".to_string()),
+        ("Cache-Control".to_string(), "no-cache".to_string()),
+    ];
+    let mut events = state.events.subscribe();
+    if http::write_head(stream, 200, &headers).await.is_err() {
+        return;
+    }
+
+    let mut next = Some(state.current());
+    loop {
+        let message = match next.take() {
+            Some(event) => format!(
+                "data: {}\n\n",
+                serde_json::to_string(&event).unwrap_or_default()
+            ),
+            None => match tokio::time::timeout(EVENT_KEEP_ALIVE, events.recv()).await {
+                Ok(Ok(event)) => {
+                    next = Some(event);
+                    continue;
+                }
+                Ok(Err(broadcast::error::RecvError::Lagged(_))) => continue,
+                Ok(Err(broadcast::error::RecvError::Closed)) => break,
+                Err(_) => ": keep-alive\n\n".to_string(),
+            },
+        };
+
+        if stream.write_all(message.as_bytes()).await.is_err() || stream.flush().await.is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::HISTORY_FILE_NAME;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+    use tokio::net::TcpListener;
+
+    async fn http_request(addr: SocketAddr, request: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).await.expect("Failed to connect");
+        stream
+            .write_all(request.as_bytes())
+            .await
+            .expect("Failed to send request");
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .await
+            .expect("Failed to read response");
+
+        let status = response[9..12].parse().expect("Missing status code");
+        let body = response
+            .split_once("\r\n\r\n")
+            .map(|(_, body)| body.to_string())
+            .unwrap_or_default();
+        (status, body)
+    }
+
+    fn test_server(token: Option<&str>, payload_root: Option<&Path>) -> ServerHandle {
+        start(
+            ApiOptions {
+                bind: "127.0.0.1:0".to_string(),
+                token: token.map(str::to_string),
+                payload_root: payload_root.map(Path::to_path_buf),
+            },
+            None,
+        )
+        .expect("Failed to start API")
+    }
+
+    #[tokio::test]
+    async fn test_status_and_routing() {
+        let server = test_server(None, None);
+
+        let (status, body) = http_request(server.local_addr, "GET /status HTTP/1.1\r\n\r\n").await;
+        assert_eq!(status, 200);
+        let event: StatusEvent = serde_json::from_str(&body).expect("Invalid status JSON");
+        assert_eq!(event.state, "idle");
+        assert_eq!(event.job, 0);
+
+        let (status, _) = http_request(server.local_addr, "GET /nope HTTP/1.1\r\n\r\n").await;
+        assert_eq!(status, 404);
+
+        let (status, _) = http_request(server.local_addr, "DELETE /status HTTP/1.1\r\n\r\n").await;
+        assert_eq!(status, 405);
+
+        let (status, _) = http_request(server.local_addr, "garbage\r\n\r\n").await;
+        assert_eq!(status, 400);
+    }
+
+    #[tokio::test]
+    async fn test_token_auth() {
+        let server = test_server(Some("s3cret"), None);
+
+        let (status, _) = http_request(server.local_addr, "GET /status HTTP/1.1\r\n\r\n").await;
+        assert_eq!(status, 401);
+
+        let (status, _) = http_request(
+            server.local_addr,
+            "GET /status HTTP/1.1\r\nAuthorization: Bearer wrong\r\n\r\n",
+        )
+        .await;
+        assert_eq!(status, 401);
+
+        let (status, _) = http_request(
+            server.local_addr,
+            "GET /status HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n",
+        )
+        .await;
+        assert_eq!(status, 200);
+
+        let (status, _) = http_request(
+            server.local_addr,
+            "GET /status?token=s3cret HTTP/1.1\r\n\r\n",
+        )
+        .await;
+        assert_eq!(status, 200);
+
+        let (status, _) = http_request(
+            server.local_addr,
+            "GET /status?token=s3cre HTTP/1.1\r\n\r\n",
+        )
+        .await;
+        assert_eq!(status, 401);
+    }
+
+    #[test]
+    fn test_network_bind_needs_token() {
+        let options = ApiOptions {
+            bind: "0.0.0.0:0".to_string(),
+            ..ApiOptions::default()
+        };
+        let error = start(options.clone(), None).unwrap_err();
+        assert!(error.contains("needs --api-token"), "{}", error);
+
+        let error = start(
+            ApiOptions {
+                token: Some(String::new()),
+                ..options.clone()
+            },
+            None,
+        )
+        .unwrap_err();
+        assert!(error.contains("needs --api-token"), "{}", error);
+
+        let server = start(
+            ApiOptions {
+                token: Some("s3cret".to_string()),
+                ..options
+            },
+            None,
+        );
+        assert!(server.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_inject_rejects_bad_requests() {
+        let payload_root = tempfile::tempdir().expect("Failed to create temp dir");
+        let server = test_server(None, Some(payload_root.path()));
+
+        // Files the app does not know about are refused, whether they exist or not
+        let elsewhere = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+        let escaped = payload_root
+            .path()
+            .join("..")
+            .join(elsewhere.path().file_name().unwrap());
+        for path in [elsewhere.path(), &escaped, Path::new("/does/not/exist")] {
+            let body = serde_json::json!({"ip": "127.0.0.1", "port": "9020", "file_path": path})
+                .to_string();
+            let request = format!(
+                "POST /inject HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let (status, response) = http_request(server.local_addr, &request).await;
+            assert_eq!(status, 403, "{}", response);
+            assert!(response.contains("is not the configured payload"));
+        }
+
+        let body = r#"{"profile": "no such profile"}"#;
+        let request = format!(
+            "POST /inject HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let (status, response) = http_request(server.local_addr, &request).await;
+        assert_eq!(status, 400);
+        assert!(response.contains("No profile named"));
+
+        let request = "POST /inject HTTP/1.1\r\nContent-Length: 5\r\n\r\n{nope";
+        let (status, response) = http_request(server.local_addr, request).await;
+        assert_eq!(status, 400);
+        assert!(response.contains("Invalid request body"));
+    }
+
+    #[tokio::test]
+    async fn test_inject_and_stream_events() {
+        let payload_root = tempfile::tempdir().expect("Failed to create temp dir");
+        let server = test_server(None, Some(payload_root.path()));
+
+        // Target loader
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind listener");
+        let target_addr = listener.local_addr().expect("Failed to get local address");
+        let target = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.expect("Failed to accept");
+            let mut buffer = Vec::new();
+            socket
+                .read_to_end(&mut buffer)
+                .await
+                .expect("Failed to read");
+            buffer
+        });
+
+        // Subscribe to events before injecting
+        let mut events = TcpStream::connect(server.local_addr)
+            .await
+            .expect("Failed to connect");
+        events
+            .write_all(b"GET /events HTTP/1.1\r\n\r\n")
+            .await
+            .expect("Failed to send request");
+        let mut events = BufReader::new(events);
+        let mut line = String::new();
+        events.read_line(&mut line).await.expect("Failed to read");
+        assert!(line.starts_with("HTTP/1.1 200"));
+
+        let temp_file =
+            tempfile::NamedTempFile::new_in(&payload_root).expect("Failed to create temp file");
+        std::fs::write(&temp_file, b"remote payload").expect("Failed to write test data");
+        let body = serde_json::json!({
+            "ip": target_addr.ip().to_string(),
+            "port": target_addr.port().to_string(),
+            "file_path": temp_file.path().to_str().unwrap(),
+        })
+        .to_string();
+        let request = format!(
+            "POST /inject HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let (status, response) = http_request(server.local_addr, &request).await;
+        assert_eq!(status, 202);
+        assert!(response.contains("\"job\": 1"));
+
+        // Follow the event stream until the job finishes
+        let mut final_event = None;
+        while final_event.is_none() {
+            line.clear();
+            let read = tokio::time::timeout(Duration::from_secs(5), events.read_line(&mut line))
+                .await
+                .expect("Timed out waiting for events")
+                .expect("Failed to read event");
+            assert!(read > 0, "Event stream closed");
+            if let Some(data) = line.trim().strip_prefix("data: ") {
+                let event: StatusEvent = serde_json::from_str(data).expect("Invalid event");
+                if event.job == 1 && !event.is_in_progress() {
+                    final_event = Some(event);
+                }
+            }
+        }
+
+        let final_event = final_event.unwrap();
+        assert_eq!(final_event.state, "success");
+        assert_eq!(final_event.bytes_sent, Some(14));
+        assert_eq!(
+            final_event.sha256,
+            Some(crate::checksum::sha256_hex(b"remote payload"))
+        );
+        assert_eq!(target.await.expect("Target task failed"), b"remote payload");
+
+        let (_, body) = http_request(server.local_addr, "GET /status HTTP/1.1\r\n\r\n").await;
+        let event: StatusEvent = serde_json::from_str(&body).expect("Invalid status JSON");
+        assert_eq!(event, final_event);
+
+        let (status, body) =
+            http_request(server.local_addr, "GET /payloads HTTP/1.1\r\n\r\n").await;
+        assert_eq!(status, 200);
+        let payloads: Result<Vec<serde_json::Value>, _> = serde_json::from_str(&body);
+        assert!(payloads.is_ok());
+
+        // The injection was recorded in the worker thread's history file; remove it
+        let payload = temp_file.path().to_str().unwrap().to_string();
+        let mut recorded = false;
+        for entry in std::fs::read_dir(".")
+            .expect("Failed to list dir")
+            .flatten()
+        {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with("test-app_config") || !name.ends_with(HISTORY_FILE_NAME) {
+                continue;
+            }
+            if let Ok(history) = History::load_from_file(entry.path()) {
+                if let Some(last) = history.entries.iter().find(|e| e.file_path == payload) {
+                    assert_eq!(last.trigger, Trigger::Api);
+                    assert_eq!(last.bytes_sent, Some(14));
+                    recorded = true;
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+        assert!(recorded);
+    }
+
+    fn test_state(payload_root: Option<PathBuf>) -> ApiState {
+        ApiState {
+            token: None,
+            payload_root,
+            current: Mutex::new(StatusEvent::from_status(0, &InjectionStatus::Idle)),
+            next_job: AtomicU64::new(1),
+            events: broadcast::channel(1).0,
+            observer: None,
+        }
+    }
+
+    fn save_test_manifest(contents: &[u8]) {
+        let manifest: Manifest = serde_json::from_value(serde_json::json!({
+            "payloads": [{
+                "name": "Vetted",
+                "version": "1.0",
+                "sha256": crate::checksum::sha256_hex(contents),
+            }]
+        }))
+        .expect("Invalid manifest");
+        manifest
+            .save_to_file(Manifest::default_path())
+            .expect("Failed to save manifest");
+    }
+
+    #[tokio::test]
+    async fn test_manifest_builds_may_be_sent() {
+        let state = test_state(None);
+        let temp_file = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+        std::fs::write(&temp_file, b"vetted build").expect("Failed to write test data");
+        let path = temp_file.path().to_str().unwrap();
+        assert!(!state.may_send(path).await);
+
+        save_test_manifest(b"vetted build");
+        let allowed = state.may_send(path).await;
+        let _ = std::fs::remove_file(Manifest::default_path());
+        assert!(allowed);
+    }
+
+    #[tokio::test]
+    async fn test_empty_manifest_allows_nothing() {
+        let _ = std::fs::remove_file(Manifest::default_path());
+        let state = test_state(None);
+
+        // Not even read: a device like this would never finish hashing
+        let result =
+            tokio::time::timeout(Duration::from_secs(5), state.may_send("/dev/zero")).await;
+        assert_eq!(result, Ok(false));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_only_regular_files_are_hashed() {
+        let state = test_state(None);
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        save_test_manifest(b"vetted build");
+
+        let result =
+            tokio::time::timeout(Duration::from_secs(5), state.may_send("/dev/zero")).await;
+        let directory_allowed = state.may_send(temp_dir.path().to_str().unwrap()).await;
+        let _ = std::fs::remove_file(Manifest::default_path());
+        assert_eq!(result, Ok(false));
+        assert!(!directory_allowed);
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"s3cret", b"s3cret"));
+        assert!(!constant_time_eq(b"s3cret", b"s3creT"));
+        assert!(!constant_time_eq(b"s3cret", b"s3cre"));
+        assert!(constant_time_eq(b"", b""));
+    }
+
+    #[tokio::test]
+    async fn test_known_payloads_may_be_sent() {
+        let root = tempfile::tempdir().expect("Failed to create temp dir");
+        let (first, second) = (root.path().join("a.bin"), root.path().join("b.bin"));
+        std::fs::write(&first, b"a").expect("Failed to write test data");
+        std::fs::write(&second, b"b").expect("Failed to write test data");
+        let outside = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+        let (first, second) = (first.to_str().unwrap(), second.to_str().unwrap());
+        let outside = outside.path().to_str().unwrap();
+
+        let mut history = History::default();
+        for path in [first, outside, second, first, "/gone.elf"] {
+            history.push(HistoryEntry::new(Trigger::Manual, "10.0.0.2", "9021", path));
+        }
+        history
+            .save_to_file(History::default_path())
+            .expect("Failed to save history");
+
+        let state = test_state(Some(root.path().to_path_buf()));
+        let payloads = known_payloads(&state);
+        let _ = std::fs::remove_file(History::default_path());
+
+        // Only what POST /inject accepts, most recent first, each path once
+        let ours: Vec<&str> = payloads
+            .iter()
+            .map(|p| p.path.as_str())
+            .filter(|path| [first, second, outside, "/gone.elf"].contains(path))
+            .collect();
+        assert_eq!(ours, [first, second]);
+        for payload in &payloads {
+            assert!(state.may_send(&payload.path).await, "{}", payload.path);
+        }
+        assert!(!state.may_send(outside).await);
+    }
+}
@@ -0,0 +1,463 @@
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::sync::{mpsc, oneshot};
+
+/// Separates the archive from the entry in payload paths such as
+/// `release.zip!/payload.elf`
+pub const ENTRY_SEPARATOR: &str = "!/";
+
+/// Entries with these extensions are offered as payloads
+pub const PAYLOAD_EXTENSIONS: &[&str] = &["elf", "bin", "self", "prx", "sprx"];
+
+/// Size of the blocks payloads are read, hashed and sent in
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Chunks of an entry buffered ahead of the network
+const ENTRY_CHUNKS_IN_FLIGHT: usize = 4;
+
+/// A file inside an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchivePath {
+    pub archive: PathBuf,
+    pub entry: String, // Name inside the archive, `/`-separated
+}
+
+impl ArchivePath {
+    pub fn new<P: Into<PathBuf>>(archive: P, entry: &str) -> Self {
+        Self {
+            archive: archive.into(),
+            entry: entry.to_string(),
+        }
+    }
+
+    /// Split `archive.zip!/entry`; `None` for plain file paths
+    pub fn parse(path: &str) -> Option<Self> {
+        let (archive, entry) = path.split_once(ENTRY_SEPARATOR)?;
+        if !is_archive(archive) || entry.is_empty() {
+            return None;
+        }
+        Some(Self::new(archive, entry))
+    }
+}
+
+impl fmt::Display for ArchivePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}",
+            self.archive.display(),
+            ENTRY_SEPARATOR,
+            self.entry
+        )
+    }
+}
+
+/// A file listed in an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub size: u64, // Uncompressed
+}
+
+impl ArchiveEntry {
+    pub fn is_payload(&self) -> bool {
+        Path::new(&self.name)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                PAYLOAD_EXTENSIONS
+                    .iter()
+                    .any(|known| known.eq_ignore_ascii_case(extension))
+            })
+    }
+}
+
+/// Whether `path` names a supported archive, judged by its extension
+pub fn is_archive<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref()
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("zip"))
+}
+
+/// Whether a payload path, plain or inside an archive, points at something
+/// that exists. Only the archive itself is checked, so this is cheap enough
+/// to call every frame.
+pub fn payload_exists(path: &str) -> bool {
+    match ArchivePath::parse(path) {
+        Some(archive_path) => archive_path.archive.is_file(),
+        None => Path::new(path).exists(),
+    }
+}
+
+/// The file whose modification marks a change of the payload at `path`
+pub fn backing_file(path: &str) -> PathBuf {
+    match ArchivePath::parse(path) {
+        Some(archive_path) => archive_path.archive,
+        None => PathBuf::from(path),
+    }
+}
+
+fn open_archive(archive: &Path) -> Result<zip::ZipArchive<std::fs::File>, String> {
+    let file = std::fs::File::open(archive)
+        .map_err(|e| format!("Failed to open archive '{}': {}", archive.display(), e))?;
+    zip::ZipArchive::new(file)
+        .map_err(|e| format!("Failed to read archive '{}': {}", archive.display(), e))
+}
+
+/// All files in the archive, in archive order
+pub fn list_entries<P: AsRef<Path>>(archive: P) -> Result<Vec<ArchiveEntry>, String> {
+    let archive = archive.as_ref();
+    let mut zip = open_archive(archive)?;
+
+    let mut entries = Vec::new();
+    for index in 0..zip.len() {
+        let entry = zip
+            .by_index_raw(index)
+            .map_err(|e| format!("Failed to read archive '{}': {}", archive.display(), e))?;
+        if entry.is_file() {
+            entries.push(ArchiveEntry {
+                name: entry.name().to_string(),
+                size: entry.size(),
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Files in the archive that look like payloads
+pub fn payload_candidates<P: AsRef<Path>>(archive: P) -> Result<Vec<ArchiveEntry>, String> {
+    Ok(list_entries(archive)?
+        .into_iter()
+        .filter(ArchiveEntry::is_payload)
+        .collect())
+}
+
+/// Turn a bare archive path into the path of the one payload inside it.
+/// Other paths are returned unchanged.
+pub fn resolve_payload(path: &str) -> Result<String, String> {
+    if !is_archive(path) || ArchivePath::parse(path).is_some() {
+        return Ok(path.to_string());
+    }
+
+    let candidates = payload_candidates(path)?;
+    match candidates.as_slice() {
+        [] => Err(format!("No payload found in archive '{}'", path)),
+        [entry] => Ok(ArchivePath::new(path, &entry.name).to_string()),
+        _ => Err(format!(
+            "Archive '{}' contains several payloads, choose one with '{}{}<ENTRY>': {}",
+            path,
+            path,
+            ENTRY_SEPARATOR,
+            candidates
+                .iter()
+                .map(|entry| entry.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Open an entry and hand it to `read` along with its uncompressed size
+pub fn with_entry<T>(
+    path: &ArchivePath,
+    read: impl FnOnce(&mut dyn Read, u64) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut zip = open_archive(&path.archive)?;
+    let mut entry = zip.by_name(&path.entry).map_err(|e| match e {
+        zip::result::ZipError::FileNotFound => format!(
+            "No entry '{}' in archive '{}'",
+            path.entry,
+            path.archive.display()
+        ),
+        e => format!("Failed to open '{}': {}", path, e),
+    })?;
+    let size = entry.size();
+    read(&mut entry, size)
+}
+
+/// Open a payload, plain or inside an archive, and hand it to `read`
+pub fn with_payload<T>(
+    path: &Path,
+    read: impl FnOnce(&mut dyn Read) -> Result<T, String>,
+) -> Result<T, String> {
+    if let Some(archive_path) = path.to_str().and_then(ArchivePath::parse) {
+        return with_entry(&archive_path, |entry, _| read(entry));
+    }
+
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open file '{}': {}", path.display(), e))?;
+    read(&mut file)
+}
+
+/// Read a whole payload, plain or inside an archive
+pub fn read_payload(path: &str) -> Result<Vec<u8>, String> {
+    let Some(archive_path) = ArchivePath::parse(path) else {
+        return std::fs::read(path).map_err(|e| format!("Failed to read file '{}': {}", path, e));
+    };
+
+    with_entry(&archive_path, |entry, _| {
+        let mut bytes = Vec::new();
+        entry
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to read file '{}': {}", path, e))?;
+        Ok(bytes)
+    })
+}
+
+/// Read at most `len` bytes of a payload from `offset` on, plain or inside an
+/// archive, along with the payload's size. Plain files are read from `offset`
+/// directly; archive entries are inflated up to it and the rest thrown away.
+pub fn read_payload_range(path: &str, offset: u64, len: usize) -> Result<(Vec<u8>, u64), String> {
+    let read_error = |e: std::io::Error| format!("Failed to read file '{}': {}", path, e);
+    let read_range = |reader: &mut dyn Read| -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        reader
+            .take(len as u64)
+            .read_to_end(&mut bytes)
+            .map_err(read_error)?;
+        Ok(bytes)
+    };
+
+    let Some(archive_path) = ArchivePath::parse(path) else {
+        let mut file = std::fs::File::open(path).map_err(read_error)?;
+        let size = file.metadata().map_err(read_error)?.len();
+        file.seek(SeekFrom::Start(offset)).map_err(read_error)?;
+        return Ok((read_range(&mut file)?, size));
+    };
+
+    with_entry(&archive_path, |entry, size| {
+        std::io::copy(&mut entry.take(offset), &mut std::io::sink()).map_err(read_error)?;
+        Ok((read_range(entry)?, size))
+    })
+}
+
+/// Decompresses an archive entry on a background thread, so it can be sent
+/// without being extracted to disk
+pub struct EntryReader {
+    chunks: mpsc::Receiver<Result<Vec<u8>, String>>,
+    chunk: Vec<u8>,
+    position: usize,
+    size: u64,
+}
+
+impl EntryReader {
+    /// Open `path`, reporting a missing archive or entry before any data is read
+    pub async fn open(path: &ArchivePath) -> Result<Self, String> {
+        let (opened_sender, opened) = oneshot::channel();
+        let (chunk_sender, chunks) = mpsc::channel(ENTRY_CHUNKS_IN_FLIGHT);
+        let path = path.clone();
+
+        std::thread::spawn(move || {
+            let mut opened_sender = Some(opened_sender);
+            let result = with_entry(&path, |entry, size| {
+                if let Some(opened_sender) = opened_sender.take() {
+                    let _ = opened_sender.send(Ok(size));
+                }
+                let mut buffer = vec![0u8; CHUNK_SIZE];
+                loop {
+                    let read = entry
+                        .read(&mut buffer)
+                        .map_err(|e| format!("Failed to read '{}': {}", path, e))?;
+                    if read == 0 {
+                        return Ok(());
+                    }
+                    if chunk_sender
+                        .blocking_send(Ok(buffer[..read].to_vec()))
+                        .is_err()
+                    {
+                        return Ok(()); // Reader dropped, e.g. the send failed
+                    }
+                }
+            });
+
+            if let Err(e) = result {
+                match opened_sender.take() {
+                    Some(opened_sender) => {
+                        let _ = opened_sender.send(Err(e));
+                    }
+                    None => {
+                        let _ = chunk_sender.blocking_send(Err(e));
+                    }
+                }
+            }
+        });
+
+        let size = opened
+            .await
+            .map_err(|_| "Archive reader stopped unexpectedly".to_string())??;
+        Ok(Self {
+            chunks,
+            chunk: Vec::new(),
+            position: 0,
+            size,
+        })
+    }
+
+    /// Uncompressed size of the entry
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+impl AsyncRead for EntryReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        while self.position == self.chunk.len() {
+            match self.chunks.poll_recv(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Err(std::io::Error::other(e))),
+                Poll::Ready(None) => return Poll::Ready(Ok(())), // End of entry
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        let available = &self.chunk[self.position..];
+        let count = available.len().min(buf.remaining());
+        buf.put_slice(&available[..count]);
+        self.position += count;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tokio::io::AsyncReadExt;
+
+    /// Write a zip with the given files, deflated so reads go through decompression
+    fn write_zip(path: &Path, files: &[(&str, &[u8])]) {
+        let file = std::fs::File::create(path).expect("Failed to create archive");
+        let mut zip = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        zip.add_directory("docs/", options)
+            .expect("Failed to add directory");
+        for (name, data) in files {
+            zip.start_file(*name, options).expect("Failed to add file");
+            zip.write_all(data).expect("Failed to write file");
+        }
+        zip.finish().expect("Failed to finish archive");
+    }
+
+    #[test]
+    fn test_archive_path_parse() {
+        let path = ArchivePath::parse("/dl/release.ZIP!/bin/payload.elf").expect("Not parsed");
+        assert_eq!(path.archive, PathBuf::from("/dl/release.ZIP"));
+        assert_eq!(path.entry, "bin/payload.elf");
+        assert_eq!(path.to_string(), "/dl/release.ZIP!/bin/payload.elf");
+
+        assert_eq!(ArchivePath::parse("/dl/payload.elf"), None);
+        assert_eq!(ArchivePath::parse("/dl/odd!/name.elf"), None);
+        assert_eq!(ArchivePath::parse("/dl/release.zip!/"), None);
+    }
+
+    #[test]
+    fn test_list_and_resolve() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let single = temp_dir.path().join("single.zip");
+        write_zip(
+            &single,
+            &[("README.txt", b"read me"), ("bin/payload.elf", b"\x7fELF")],
+        );
+
+        let entries = list_entries(&single).expect("Failed to list");
+        assert_eq!(
+            entries,
+            vec![
+                ArchiveEntry {
+                    name: "README.txt".to_string(),
+                    size: 7
+                },
+                ArchiveEntry {
+                    name: "bin/payload.elf".to_string(),
+                    size: 4
+                },
+            ]
+        );
+        let candidates = payload_candidates(&single).expect("Failed to list");
+        assert_eq!(candidates, entries[1..]);
+
+        let single = single.to_str().unwrap();
+        let resolved = resolve_payload(single).expect("Failed to resolve");
+        assert_eq!(resolved, format!("{}!/bin/payload.elf", single));
+        assert_eq!(resolve_payload(&resolved), Ok(resolved.clone()));
+        assert_eq!(
+            resolve_payload("/dl/plain.bin"),
+            Ok("/dl/plain.bin".to_string())
+        );
+
+        let several = temp_dir.path().join("several.zip");
+        write_zip(&several, &[("ps4.bin", b"4"), ("ps5.elf", b"5")]);
+        let result = resolve_payload(several.to_str().unwrap());
+        assert!(result
+            .unwrap_err()
+            .contains("contains several payloads, choose one with"));
+
+        let result = list_entries(temp_dir.path().join("missing.zip"));
+        assert!(result.unwrap_err().contains("Failed to open archive"));
+    }
+
+    #[test]
+    fn test_read_payload() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let archive = temp_dir.path().join("release.zip");
+        write_zip(&archive, &[("payload.bin", b"abc")]);
+        let archive = archive.to_str().unwrap();
+
+        let path = format!("{}!/payload.bin", archive);
+        assert!(payload_exists(&path));
+        assert_eq!(read_payload(&path), Ok(b"abc".to_vec()));
+        assert_eq!(backing_file(&path), PathBuf::from(archive));
+
+        let result = read_payload_range(&path, 1, 10);
+        assert_eq!(result, Ok((b"bc".to_vec(), 3)));
+
+        let result = read_payload(&format!("{}!/other.bin", archive));
+        assert!(result
+            .unwrap_err()
+            .contains("No entry 'other.bin' in archive"));
+        assert!(!payload_exists("/missing.zip!/payload.bin"));
+
+        let plain = temp_dir.path().join("payload.bin");
+        std::fs::write(&plain, b"abcdef").expect("Failed to write test data");
+        let plain = plain.to_str().unwrap();
+        assert_eq!(read_payload_range(plain, 2, 3), Ok((b"cde".to_vec(), 6)));
+        assert_eq!(read_payload_range(plain, 9, 3), Ok((Vec::new(), 6)));
+    }
+
+    #[tokio::test]
+    async fn test_entry_reader_streams_entry() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let archive = temp_dir.path().join("release.zip");
+        let data: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+        write_zip(&archive, &[("payload.bin", &data)]);
+
+        let mut reader = EntryReader::open(&ArchivePath::new(&archive, "payload.bin"))
+            .await
+            .expect("Failed to open entry");
+        assert_eq!(reader.size(), data.len() as u64);
+        let mut read = Vec::new();
+        reader
+            .read_to_end(&mut read)
+            .await
+            .expect("Failed to read entry");
+        assert_eq!(read, data);
+
+        let result = EntryReader::open(&ArchivePath::new(&archive, "missing.bin")).await;
+        assert!(result
+            .err()
+            .unwrap()
+            .contains("No entry 'missing.bin' in archive"));
+    }
+}
@@ -0,0 +1,1033 @@
+use crate::config::{self, Config, ConfigLayer, DataFile};
+use crate::history::History;
+use crate::payload::{KnownPayload, Manifest};
+use crate::preferences::Preferences;
+use crate::profiles::{Profile, Profiles};
+use crate::queue::Queue;
+use crate::recent::{Recent, MAX_RECENT};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::path::Path;
+
+/// Format version of bundles written by this build
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// A part of the settings that can be imported on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Config,
+    Profiles,
+    Manifest,
+    History,
+    Preferences,
+    Recent,
+    Queue,
+}
+
+impl Section {
+    pub const ALL: [Section; 7] = [
+        Section::Config,
+        Section::Profiles,
+        Section::Manifest,
+        Section::History,
+        Section::Preferences,
+        Section::Recent,
+        Section::Queue,
+    ];
+
+    /// Name used on the command line
+    pub fn key(self) -> &'static str {
+        match self {
+            Section::Config => "config",
+            Section::Profiles => "profiles",
+            Section::Manifest => "manifest",
+            Section::History => "history",
+            Section::Preferences => "preferences",
+            Section::Recent => "recent",
+            Section::Queue => "queue",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|section| section.key() == key)
+            .ok_or_else(|| format!("Unknown settings section '{}'", key))
+    }
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Section::Config => "Target config",
+            Section::Profiles => "Profiles",
+            Section::Manifest => "Known payloads",
+            Section::History => "History",
+            Section::Preferences => "UI preferences",
+            Section::Recent => "Recent payloads and targets",
+            Section::Queue => "Injection queue",
+        }
+    }
+}
+
+/// How an imported section is combined with the existing one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    /// Add what is new; where both have a different version of the same
+    /// thing, keep this machine's and report a conflict
+    Merge,
+    /// Use the bundle's section as is
+    Replace,
+}
+
+/// What an import did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    /// Sections that now differ from their files
+    pub changed: Vec<Section>,
+    /// One line per section, e.g. "Profiles: 2 added, 1 unchanged"
+    pub summary: Vec<String>,
+    /// Things both sides have in different versions, where this machine's was kept
+    pub conflicts: Vec<String>,
+}
+
+/// Every setting of the app in one JSON file, for setting up another machine
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Bundle {
+    pub version: u32,
+    /// Only the values the user config sets, so the shared, project and
+    /// environment layers still show through after an import
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<ConfigLayer>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profiles: Option<Profiles>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<Manifest>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history: Option<History>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferences: Option<Preferences>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recent: Option<Recent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue: Option<Queue>,
+}
+
+impl Default for Bundle {
+    fn default() -> Self {
+        Self {
+            version: BUNDLE_VERSION,
+            config: None,
+            profiles: None,
+            manifest: None,
+            history: None,
+            preferences: None,
+            recent: None,
+            queue: None,
+        }
+    }
+}
+
+impl Bundle {
+    /// Settings saved on this machine. Sections with no file are left out;
+    /// sections whose file cannot be read are left out and returned with the error.
+    pub fn collect() -> (Self, Vec<(Section, String)>) {
+        let mut errors = Vec::new();
+        let config_path = Config::default_auto_save_path();
+        let config = if config_path.exists() {
+            Config::load_layer(&config_path)
+                .map_err(|e| errors.push((Section::Config, e)))
+                .ok()
+        } else {
+            None
+        };
+        let bundle = Self {
+            version: BUNDLE_VERSION,
+            config,
+            profiles: load_section(Section::Profiles, &mut errors),
+            manifest: load_section(Section::Manifest, &mut errors),
+            history: load_section(Section::History, &mut errors),
+            preferences: load_section(Section::Preferences, &mut errors),
+            recent: load_section(Section::Recent, &mut errors),
+            queue: load_section(Section::Queue, &mut errors),
+        };
+        (bundle, errors)
+    }
+
+    /// Write this machine's settings to `path`. Sections that could not be
+    /// read are left out and returned with their errors.
+    pub fn export<P: AsRef<Path>>(path: P) -> Result<(Self, Vec<(Section, String)>), String> {
+        let (bundle, errors) = Self::collect();
+        bundle.save_to_file(path)?;
+        Ok((bundle, errors))
+    }
+
+    /// Import sections of the bundle at `path` into this machine's settings
+    pub fn import<P: AsRef<Path>>(
+        path: P,
+        choices: &[(Section, ImportMode)],
+    ) -> Result<ImportReport, String> {
+        let incoming = Self::load_from_file(path)?;
+        let (mut current, errors) = Self::collect();
+        // Merging into a section that failed to read would overwrite its file
+        if let Some((section, e)) = errors
+            .iter()
+            .find(|(failed, _)| choices.iter().any(|(section, _)| section == failed))
+        {
+            return Err(format!("Cannot import {}: {}", section.title(), e));
+        }
+        let report = current.merge_from(&incoming, choices);
+        current.save_sections(&report.changed)?;
+        Ok(report)
+    }
+
+    /// Sections the bundle has
+    pub fn sections(&self) -> Vec<Section> {
+        Section::ALL
+            .into_iter()
+            .filter(|section| match section {
+                Section::Config => self.config.is_some(),
+                Section::Profiles => self.profiles.is_some(),
+                Section::Manifest => self.manifest.is_some(),
+                Section::History => self.history.is_some(),
+                Section::Preferences => self.preferences.is_some(),
+                Section::Recent => self.recent.is_some(),
+                Section::Queue => self.queue.is_some(),
+            })
+            .collect()
+    }
+
+    /// Combine the chosen sections of `incoming` into these settings
+    pub fn merge_from(
+        &mut self,
+        incoming: &Bundle,
+        choices: &[(Section, ImportMode)],
+    ) -> ImportReport {
+        let mut report = ImportReport::default();
+        for &(section, mode) in choices {
+            let changed = match section {
+                Section::Config => {
+                    merge_config(&mut self.config, &incoming.config, mode, &mut report)
+                }
+                Section::Profiles => merge_named(
+                    &mut self.profiles,
+                    &incoming.profiles,
+                    section,
+                    mode,
+                    &mut report,
+                ),
+                Section::Manifest => merge_named(
+                    &mut self.manifest,
+                    &incoming.manifest,
+                    section,
+                    mode,
+                    &mut report,
+                ),
+                Section::History => {
+                    merge_history(&mut self.history, &incoming.history, mode, &mut report)
+                }
+                Section::Preferences => merge_preferences(
+                    &mut self.preferences,
+                    &incoming.preferences,
+                    mode,
+                    &mut report,
+                ),
+                Section::Recent => {
+                    merge_recent(&mut self.recent, &incoming.recent, mode, &mut report)
+                }
+                Section::Queue => merge_queue(&mut self.queue, &incoming.queue, mode, &mut report),
+            };
+            if changed {
+                report.changed.push(section);
+            }
+        }
+        report
+    }
+
+    /// Write the given sections to their files
+    pub fn save_sections(&self, sections: &[Section]) -> Result<(), String> {
+        Config::ensure_config_dir()?;
+        for section in sections {
+            match section {
+                Section::Config => self
+                    .config
+                    .as_ref()
+                    .map(|layer| Config::update_auto_save(|current| *current = layer.clone())),
+                Section::Profiles => self.profiles.as_ref().map(Profiles::save),
+                Section::Manifest => self
+                    .manifest
+                    .as_ref()
+                    .map(|manifest| manifest.save_to_file(Manifest::default_path())),
+                Section::History => self
+                    .history
+                    .as_ref()
+                    .map(|history| history.save_to_file(History::default_path())),
+                Section::Preferences => self.preferences.as_ref().map(Preferences::save),
+                Section::Recent => self.recent.as_ref().map(Recent::save),
+                Section::Queue => self.queue.as_ref().map(Queue::save),
+            }
+            .transpose()?;
+        }
+        Ok(())
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        config::write_json_file(path.as_ref(), self, "settings bundle")
+    }
+
+    /// Load a bundle, migrating a config written with an older schema
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let document: serde_json::Value = config::read_json_file(path, "settings bundle")?;
+        let parse_error = |e: String| {
+            format!(
+                "Failed to parse settings bundle '{}': {}",
+                path.display(),
+                e
+            )
+        };
+
+        let version = document.get("version").and_then(serde_json::Value::as_u64);
+        if version.is_none_or(|version| version > u64::from(BUNDLE_VERSION)) {
+            return Err(parse_error(format!(
+                "bundle version {} is not supported (expected {})",
+                document.get("version").unwrap_or(&serde_json::Value::Null),
+                BUNDLE_VERSION
+            )));
+        }
+
+        let mut bundle: Self =
+            serde_json::from_value(document).map_err(|e| parse_error(e.to_string()))?;
+        if let Some(layer) = bundle.config.take() {
+            bundle.config = Some(layer.upgraded().map_err(parse_error)?);
+        }
+        if let Some(manifest) = bundle.manifest.take() {
+            bundle.manifest = Some(manifest.validated().map_err(parse_error)?);
+        }
+        Ok(bundle)
+    }
+}
+
+/// Read a section's data file, or `None` if it does not exist. A file that
+/// cannot be read is reported in `errors`.
+fn load_section<T: DataFile>(section: Section, errors: &mut Vec<(Section, String)>) -> Option<T> {
+    let path = T::default_path();
+    if !path.exists() {
+        return None;
+    }
+    T::load_from_file(&path)
+        .map_err(|e| errors.push((section, e)))
+        .ok()
+}
+
+/// Replace `current` with `incoming`, reporting it. Returns whether it changed.
+fn replace<T: Clone + PartialEq>(
+    current: &mut Option<T>,
+    incoming: &T,
+    section: Section,
+    report: &mut ImportReport,
+) -> bool {
+    if current.as_ref() == Some(incoming) {
+        report
+            .summary
+            .push(format!("{}: unchanged", section.title()));
+        return false;
+    }
+    *current = Some(incoming.clone());
+    report
+        .summary
+        .push(format!("{}: replaced", section.title()));
+    true
+}
+
+fn not_in_bundle(section: Section, report: &mut ImportReport) -> bool {
+    report
+        .summary
+        .push(format!("{}: not in the bundle", section.title()));
+    false
+}
+
+fn merge_config(
+    current: &mut Option<ConfigLayer>,
+    incoming: &Option<ConfigLayer>,
+    mode: ImportMode,
+    report: &mut ImportReport,
+) -> bool {
+    let section = Section::Config;
+    let Some(incoming) = incoming else {
+        return not_in_bundle(section, report);
+    };
+    let existing = match current {
+        Some(existing) if mode == ImportMode::Merge => existing,
+        _ => return replace(current, incoming, section, report),
+    };
+
+    let mut added = 0;
+    let mut differences = 0;
+    let fields = [
+        ("ip", &mut existing.ip, &incoming.ip),
+        ("port", &mut existing.port, &incoming.port),
+        ("file_path", &mut existing.file_path, &incoming.file_path),
+    ];
+    for (name, here, there) in fields {
+        merge_config_field(name, here, there, &mut added, &mut differences, report);
+    }
+    merge_config_field(
+        "auto_save_enabled",
+        &mut existing.auto_save_enabled,
+        &incoming.auto_save_enabled,
+        &mut added,
+        &mut differences,
+        report,
+    );
+
+    report.summary.push(match (added, differences) {
+        (0, 0) => format!("{}: unchanged", section.title()),
+        (0, count) => format!("{}: kept, {} difference(s)", section.title(), count),
+        (added, count) => format!(
+            "{}: {} added, {} difference(s)",
+            section.title(),
+            added,
+            count
+        ),
+    });
+    added > 0
+}
+
+/// Take a config value the bundle sets and this machine does not; where both
+/// set it differently, keep this machine's and report a conflict
+fn merge_config_field<T: Clone + PartialEq + Display>(
+    name: &str,
+    here: &mut Option<T>,
+    there: &Option<T>,
+    added: &mut usize,
+    differences: &mut usize,
+    report: &mut ImportReport,
+) {
+    let Some(there) = there else {
+        return;
+    };
+    match here {
+        None => {
+            *here = Some(there.clone());
+            *added += 1;
+        }
+        Some(value) if value != there => {
+            *differences += 1;
+            report.conflicts.push(format!(
+                "{}: {} is '{}' here but '{}' in the bundle; kept '{}'",
+                Section::Config.title(),
+                name,
+                value,
+                there,
+                value
+            ));
+        }
+        Some(_) => {}
+    }
+}
+
+/// A section that is a list of items told apart by name
+trait NamedList: Clone + PartialEq + Default {
+    type Item: Clone + PartialEq;
+
+    fn items(&self) -> &[Self::Item];
+    fn items_mut(&mut self) -> &mut Vec<Self::Item>;
+    fn name(item: &Self::Item) -> String;
+}
+
+impl NamedList for Profiles {
+    type Item = Profile;
+
+    fn items(&self) -> &[Profile] {
+        &self.profiles
+    }
+
+    fn items_mut(&mut self) -> &mut Vec<Profile> {
+        &mut self.profiles
+    }
+
+    fn name(profile: &Profile) -> String {
+        profile.name.clone()
+    }
+}
+
+impl NamedList for Manifest {
+    type Item = KnownPayload;
+
+    fn items(&self) -> &[KnownPayload] {
+        &self.payloads
+    }
+
+    fn items_mut(&mut self) -> &mut Vec<KnownPayload> {
+        &mut self.payloads
+    }
+
+    fn name(payload: &KnownPayload) -> String {
+        payload.title()
+    }
+}
+
+/// Merge lists of named items: new names are added, and a name both sides
+/// have with different contents is a conflict
+fn merge_named<S: NamedList>(
+    current: &mut Option<S>,
+    incoming: &Option<S>,
+    section: Section,
+    mode: ImportMode,
+    report: &mut ImportReport,
+) -> bool {
+    let Some(incoming) = incoming else {
+        return not_in_bundle(section, report);
+    };
+    if mode == ImportMode::Replace {
+        return replace(current, incoming, section, report);
+    }
+
+    let existing = current.get_or_insert_with(S::default).items_mut();
+    let (mut added, mut unchanged) = (0, 0);
+    for item in incoming.items() {
+        let name = S::name(item);
+        match existing.iter().find(|other| S::name(other) == name) {
+            None => {
+                existing.push(item.clone());
+                added += 1;
+            }
+            Some(other) if other == item => unchanged += 1,
+            Some(_) => report.conflicts.push(format!(
+                "{}: '{}' differs from the bundle's; kept this machine's",
+                section.title(),
+                name
+            )),
+        }
+    }
+    report.summary.push(format!(
+        "{}: {} added, {} unchanged",
+        section.title(),
+        added,
+        unchanged
+    ));
+    added > 0
+}
+
+/// Merge histories by adding the bundle's entries this one does not have,
+/// in time order
+fn merge_history(
+    current: &mut Option<History>,
+    incoming: &Option<History>,
+    mode: ImportMode,
+    report: &mut ImportReport,
+) -> bool {
+    let section = Section::History;
+    let Some(incoming) = incoming else {
+        return not_in_bundle(section, report);
+    };
+    if mode == ImportMode::Replace {
+        return replace(current, incoming, section, report);
+    }
+
+    let existing = current.get_or_insert_with(History::default);
+    let mut entries = existing.entries.clone();
+    let before = entries.len();
+    for entry in &incoming.entries {
+        if !entries.contains(entry) {
+            entries.push(entry.clone());
+        }
+    }
+    let added = entries.len() - before;
+    entries.sort_by_key(|entry| entry.timestamp);
+
+    // Pushing again keeps only the newest entries
+    let mut merged = History::default();
+    for entry in entries {
+        merged.push(entry);
+    }
+    *existing = merged;
+    report
+        .summary
+        .push(format!("{}: {} added", section.title(), added));
+    added > 0
+}
+
+/// Preferences are taken as a whole, except for the window position, which
+/// only makes sense on the screen it was saved on
+fn merge_preferences(
+    current: &mut Option<Preferences>,
+    incoming: &Option<Preferences>,
+    mode: ImportMode,
+    report: &mut ImportReport,
+) -> bool {
+    let section = Section::Preferences;
+    let Some(incoming) = incoming else {
+        return not_in_bundle(section, report);
+    };
+    let mut incoming = incoming.clone();
+    incoming.window = current.as_ref().and_then(|existing| existing.window);
+
+    match current {
+        Some(existing) if mode == ImportMode::Merge && *existing != incoming => {
+            report.conflicts.push(format!(
+                "{}: differ from the bundle's; kept this machine's",
+                section.title()
+            ));
+            report.summary.push(format!("{}: kept", section.title()));
+            false
+        }
+        _ => replace(current, &incoming, section, report),
+    }
+}
+
+/// Merge recent lists by adding the bundle's entries after this machine's,
+/// as far as the lists have room. The last folder is kept if there is one.
+fn merge_recent(
+    current: &mut Option<Recent>,
+    incoming: &Option<Recent>,
+    mode: ImportMode,
+    report: &mut ImportReport,
+) -> bool {
+    let section = Section::Recent;
+    let Some(incoming) = incoming else {
+        return not_in_bundle(section, report);
+    };
+    if mode == ImportMode::Replace {
+        return replace(current, incoming, section, report);
+    }
+
+    let existing = current.get_or_insert_with(Recent::default);
+    let added = append_missing(&mut existing.files, &incoming.files)
+        + append_missing(&mut existing.targets, &incoming.targets);
+    let directory_added = existing.last_directory.is_none() && incoming.last_directory.is_some();
+    if directory_added {
+        existing.last_directory = incoming.last_directory.clone();
+    }
+    report
+        .summary
+        .push(format!("{}: {} added", section.title(), added));
+    added > 0 || directory_added
+}
+
+/// Append the items of `incoming` that `list` lacks, up to `MAX_RECENT` in all
+fn append_missing<T: Clone + PartialEq>(list: &mut Vec<T>, incoming: &[T]) -> usize {
+    let before = list.len();
+    for item in incoming {
+        if list.len() >= MAX_RECENT {
+            break;
+        }
+        if !list.contains(item) {
+            list.push(item.clone());
+        }
+    }
+    list.len() - before
+}
+
+/// Merge queues by adding the bundle's jobs for payloads and targets that are
+/// not queued yet. Added jobs wait to run, whatever their state in the bundle.
+fn merge_queue(
+    current: &mut Option<Queue>,
+    incoming: &Option<Queue>,
+    mode: ImportMode,
+    report: &mut ImportReport,
+) -> bool {
+    let section = Section::Queue;
+    let Some(incoming) = incoming else {
+        return not_in_bundle(section, report);
+    };
+    if mode == ImportMode::Replace {
+        return replace(current, incoming, section, report);
+    }
+
+    let existing = current.get_or_insert_with(Queue::default);
+    let (mut added, mut unchanged) = (0, 0);
+    for job in &incoming.jobs {
+        let queued = existing.jobs.iter().any(|other| {
+            other.ip == job.ip && other.port == job.port && other.file_path == job.file_path
+        });
+        if queued {
+            unchanged += 1;
+            continue;
+        }
+        let id = existing.add(&job.ip, &job.port, &job.file_path);
+        existing.set_paused(id, job.paused);
+        added += 1;
+    }
+    report.summary.push(format!(
+        "{}: {} added, {} already queued",
+        section.title(),
+        added,
+        unchanged
+    ));
+    added > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{HistoryEntry, Trigger, MAX_HISTORY_ENTRIES};
+    use crate::preferences::{Theme, WindowGeometry};
+    use crate::status::InjectionStatus;
+
+    fn known_payload(name: &str, sha256_digit: char) -> KnownPayload {
+        KnownPayload {
+            name: name.to_string(),
+            version: "1.0".to_string(),
+            sha256: sha256_digit.to_string().repeat(64),
+            platform: None,
+            default_port: None,
+            file_name: None,
+        }
+    }
+
+    fn history_entry(timestamp: u64, ip: &str) -> HistoryEntry {
+        HistoryEntry {
+            timestamp,
+            ..HistoryEntry::new(Trigger::Manual, ip, "9021", "/a.bin")
+        }
+    }
+
+    fn profiles(list: &[Profile]) -> Option<Profiles> {
+        Some(Profiles {
+            profiles: list.to_vec(),
+        })
+    }
+
+    #[test]
+    fn test_merge_reports_conflicts() {
+        let mut current = Bundle {
+            config: Some(ConfigLayer {
+                ip: Some("10.0.0.2".to_string()),
+                port: Some("9021".to_string()),
+                ..ConfigLayer::default()
+            }),
+            profiles: profiles(&[
+                Profile::new("PS4", "10.0.0.4", "9020", "/a.bin"),
+                Profile::new("PS5", "10.0.0.5", "9021", "/b.elf"),
+            ]),
+            manifest: None,
+            history: Some(History {
+                entries: vec![history_entry(10, "10.0.0.2"), history_entry(30, "10.0.0.2")],
+            }),
+            ..Bundle::default()
+        };
+        let incoming = Bundle {
+            config: Some(ConfigLayer {
+                ip: Some("10.0.0.3".to_string()),
+                port: Some("9021".to_string()),
+                file_path: Some("/a.bin".to_string()),
+                ..ConfigLayer::default()
+            }),
+            profiles: profiles(&[
+                Profile::new("PS4", "10.0.0.4", "9020", "/a.bin"),
+                Profile::new("PS5", "10.0.0.55", "9021", "/b.elf"),
+                Profile::new("Lab", "10.0.0.9", "9021", "/c.elf"),
+            ]),
+            manifest: Some(Manifest {
+                payloads: vec![known_payload("GoldHEN", 'a')],
+            }),
+            history: Some(History {
+                entries: vec![history_entry(20, "10.0.0.3"), history_entry(30, "10.0.0.2")],
+            }),
+            preferences: None,
+            ..Bundle::default()
+        };
+
+        let choices: Vec<(Section, ImportMode)> = Section::ALL
+            .into_iter()
+            .map(|section| (section, ImportMode::Merge))
+            .collect();
+        let report = current.merge_from(&incoming, &choices);
+
+        assert_eq!(
+            report.changed,
+            [
+                Section::Config,
+                Section::Profiles,
+                Section::Manifest,
+                Section::History
+            ]
+        );
+        assert_eq!(
+            report.summary,
+            [
+                "Target config: 1 added, 1 difference(s)",
+                "Profiles: 1 added, 1 unchanged",
+                "Known payloads: 1 added, 0 unchanged",
+                "History: 1 added",
+                "UI preferences: not in the bundle",
+                "Recent payloads and targets: not in the bundle",
+                "Injection queue: not in the bundle",
+            ]
+        );
+        assert_eq!(
+            report.conflicts,
+            [
+                "Target config: ip is '10.0.0.2' here but '10.0.0.3' in the bundle; kept '10.0.0.2'",
+                "Profiles: 'PS5' differs from the bundle's; kept this machine's",
+            ]
+        );
+
+        // Only values this machine's user config leaves out are taken
+        let config = current.config.as_ref().unwrap();
+        assert_eq!(config.ip.as_deref(), Some("10.0.0.2"));
+        assert_eq!(config.file_path.as_deref(), Some("/a.bin"));
+        assert_eq!(config.auto_save_enabled, None);
+        let names: Vec<&str> = current
+            .profiles
+            .as_ref()
+            .unwrap()
+            .profiles
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        assert_eq!(names, ["PS4", "PS5", "Lab"]);
+        assert_eq!(
+            current.profiles.as_ref().unwrap().profiles[1].ip,
+            "10.0.0.5"
+        );
+        let times: Vec<u64> = current
+            .history
+            .as_ref()
+            .unwrap()
+            .entries
+            .iter()
+            .map(|e| e.timestamp)
+            .collect();
+        assert_eq!(times, [10, 20, 30]);
+
+        // Replacing takes the bundle's sections as they are
+        let report = current.merge_from(
+            &incoming,
+            &[
+                (Section::Config, ImportMode::Replace),
+                (Section::Profiles, ImportMode::Replace),
+            ],
+        );
+        assert_eq!(report.changed, [Section::Config, Section::Profiles]);
+        assert!(report.conflicts.is_empty());
+        assert_eq!(current.config, incoming.config);
+        assert_eq!(current.profiles, incoming.profiles);
+    }
+
+    #[test]
+    fn test_merge_history_keeps_newest() {
+        let mut current = Bundle {
+            history: Some(History {
+                entries: (0..MAX_HISTORY_ENTRIES as u64)
+                    .map(|time| history_entry(time * 2, "10.0.0.2"))
+                    .collect(),
+            }),
+            ..Bundle::default()
+        };
+        let incoming = Bundle {
+            history: Some(History {
+                entries: vec![
+                    history_entry(1, "10.0.0.3"),
+                    history_entry(1000, "10.0.0.3"),
+                ],
+            }),
+            ..Bundle::default()
+        };
+
+        current.merge_from(&incoming, &[(Section::History, ImportMode::Merge)]);
+        let entries = &current.history.as_ref().unwrap().entries;
+        assert_eq!(entries.len(), MAX_HISTORY_ENTRIES);
+        assert_eq!(entries.first().unwrap().timestamp, 2);
+        assert_eq!(entries.last().unwrap().timestamp, 1000);
+    }
+
+    #[test]
+    fn test_preferences_keep_window() {
+        let window = Some(WindowGeometry {
+            position: Some([10.0, 20.0]),
+            width: 800.0,
+            height: 600.0,
+        });
+        let mut current = Bundle {
+            preferences: Some(Preferences {
+                window,
+                ..Preferences::default()
+            }),
+            ..Bundle::default()
+        };
+        let incoming = Bundle {
+            preferences: Some(Preferences {
+                window: None,
+                theme: Theme::Light,
+                ..Preferences::default()
+            }),
+            ..Bundle::default()
+        };
+
+        let report = current.merge_from(&incoming, &[(Section::Preferences, ImportMode::Merge)]);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(current.preferences.as_ref().unwrap().theme, Theme::System);
+
+        let report = current.merge_from(&incoming, &[(Section::Preferences, ImportMode::Replace)]);
+        assert_eq!(report.changed, [Section::Preferences]);
+        let preferences = current.preferences.as_ref().unwrap();
+        assert_eq!(preferences.theme, Theme::Light);
+        assert_eq!(preferences.window, window);
+    }
+
+    #[test]
+    fn test_merge_recent_and_queue() {
+        let mut recent = Recent::default();
+        recent.add_file("/a.elf");
+        recent.add_target("10.0.0.2", "9021");
+        let mut queue = Queue::default();
+        queue.add("10.0.0.2", "9021", "/a.elf");
+        let mut current = Bundle {
+            recent: Some(recent),
+            queue: Some(queue),
+            ..Bundle::default()
+        };
+
+        let mut recent = Recent::default();
+        recent.add_file("/a.elf");
+        recent.add_file("/b.elf");
+        recent.set_directory_of(Path::new("/payloads/b.elf"));
+        let mut queue = Queue::default();
+        queue.add("10.0.0.2", "9021", "/a.elf");
+        let finished = queue.add("10.0.0.3", "9021", "/b.elf");
+        queue.set_status(finished, InjectionStatus::Error("refused".to_string()));
+        let paused = queue.add("10.0.0.3", "9021", "/c.elf");
+        queue.set_paused(paused, true);
+        let incoming = Bundle {
+            recent: Some(recent),
+            queue: Some(queue),
+            ..Bundle::default()
+        };
+
+        let report = current.merge_from(
+            &incoming,
+            &[
+                (Section::Recent, ImportMode::Merge),
+                (Section::Queue, ImportMode::Merge),
+            ],
+        );
+        assert_eq!(report.changed, [Section::Recent, Section::Queue]);
+        assert_eq!(
+            report.summary,
+            [
+                "Recent payloads and targets: 1 added",
+                "Injection queue: 2 added, 1 already queued",
+            ]
+        );
+
+        // This machine's recent entries stay first
+        let recent = current.recent.as_ref().unwrap();
+        assert_eq!(recent.files, ["/a.elf", "/b.elf"]);
+        assert_eq!(recent.targets.len(), 1);
+        assert_eq!(recent.last_directory, Some("/payloads".into()));
+
+        // Imported jobs wait to run under new ids
+        let jobs = &current.queue.as_ref().unwrap().jobs;
+        let titles: Vec<String> = jobs.iter().map(|job| job.title()).collect();
+        assert_eq!(
+            titles,
+            [
+                "a.elf -> 10.0.0.2:9021",
+                "b.elf -> 10.0.0.3:9021",
+                "c.elf -> 10.0.0.3:9021"
+            ]
+        );
+        assert!(jobs[1].is_runnable());
+        assert!(jobs[2].paused);
+        assert_eq!(jobs[2].id, 3);
+
+        let report = current.merge_from(&incoming, &[(Section::Queue, ImportMode::Replace)]);
+        assert_eq!(report.changed, [Section::Queue]);
+        assert_eq!(current.queue, incoming.queue);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("settings.json");
+
+        let bundle = Bundle {
+            profiles: profiles(&[Profile::new("PS5", "10.0.0.5", "9021", "/b.elf")]),
+            manifest: Some(Manifest {
+                payloads: vec![known_payload("GoldHEN", 'A')],
+            }),
+            ..Bundle::default()
+        };
+        bundle.save_to_file(&path).expect("Failed to save");
+        let loaded = Bundle::load_from_file(&path).expect("Failed to load");
+        assert_eq!(loaded.sections(), [Section::Profiles, Section::Manifest]);
+        assert_eq!(loaded.profiles, bundle.profiles);
+        // Hashes are checked and normalised like an imported manifest
+        assert_eq!(loaded.manifest.unwrap().payloads[0].sha256, "a".repeat(64));
+
+        // A config from before schema versions keeps only what it sets
+        std::fs::write(
+            &path,
+            r#"{"version": 1, "config": {"ip": "10.0.0.2", "port": 9021}}"#,
+        )
+        .expect("Failed to write");
+        let loaded = Bundle::load_from_file(&path).expect("Failed to load");
+        assert_eq!(
+            loaded.config,
+            Some(ConfigLayer {
+                version: Some(config::CONFIG_VERSION),
+                ip: Some("10.0.0.2".to_string()),
+                port: Some("9021".to_string()),
+                ..ConfigLayer::default()
+            })
+        );
+
+        std::fs::write(&path, r#"{"version": 1, "config": {"version": 99}}"#)
+            .expect("Failed to write");
+        let error = Bundle::load_from_file(&path).unwrap_err();
+        assert!(error.contains("config version 99"), "{}", error);
+
+        std::fs::write(&path, r#"{"version": 99}"#).expect("Failed to write");
+        let error = Bundle::load_from_file(&path).unwrap_err();
+        assert!(
+            error.contains("bundle version 99 is not supported"),
+            "{}",
+            error
+        );
+
+        std::fs::write(&path, r#"{"profiles": []}"#).expect("Failed to write");
+        assert!(Bundle::load_from_file(&path).is_err());
+    }
+
+    #[test]
+    fn test_export_reports_unreadable_sections() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("settings.json");
+
+        let profiles = Profiles {
+            profiles: vec![Profile::new("PS5", "10.0.0.5", "9021", "/b.elf")],
+        };
+        profiles.save().expect("Failed to save profiles");
+        std::fs::write(History::default_path(), "{ not json").expect("Failed to write");
+
+        // Missing files are left out quietly; unreadable ones are reported
+        let (bundle, errors) = Bundle::export(&path).expect("Failed to export");
+        assert_eq!(bundle.sections(), [Section::Profiles]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, Section::History);
+        assert!(errors[0].1.contains("history file"), "{}", errors[0].1);
+
+        // Importing into the unreadable section would overwrite its file
+        let error = Bundle::import(&path, &[(Section::History, ImportMode::Merge)]).unwrap_err();
+        assert!(error.starts_with("Cannot import History"), "{}", error);
+        assert_eq!(
+            std::fs::read_to_string(History::default_path()).expect("Failed to read"),
+            "{ not json"
+        );
+        let report = Bundle::import(&path, &[(Section::Profiles, ImportMode::Merge)])
+            .expect("Failed to import");
+        assert_eq!(report.summary, ["Profiles: 0 added, 1 unchanged"]);
+
+        let _ = std::fs::remove_file(Profiles::default_path());
+        let _ = std::fs::remove_file(History::default_path());
+    }
+
+    #[test]
+    fn test_section_keys() {
+        for section in Section::ALL {
+            assert_eq!(Section::from_key(section.key()), Ok(section));
+        }
+        assert!(Section::from_key("window").is_err());
+    }
+}
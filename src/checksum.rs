@@ -0,0 +1,147 @@
+use crate::archive::{self, CHUNK_SIZE};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::Path;
+
+/// Hashes of a payload, used to confirm two sends were of the identical build
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Checksums {
+    pub sha256: String, // Lowercase hex
+    pub crc32: Option<u32>,
+}
+
+impl Checksums {
+    pub fn short_sha256(&self) -> &str {
+        short_hash(&self.sha256)
+    }
+
+    pub fn crc32_text(&self) -> Option<String> {
+        self.crc32.map(|crc32| format!("{:08x}", crc32))
+    }
+
+    /// Short form for status lines, e.g. `SHA-256 ba7816bf8f01, CRC32 352441c2`
+    pub fn summary(&self) -> String {
+        match self.crc32_text() {
+            Some(crc32) => format!("SHA-256 {}, CRC32 {}", self.short_sha256(), crc32),
+            None => format!("SHA-256 {}", self.short_sha256()),
+        }
+    }
+}
+
+/// Enough of a hex SHA-256 to tell builds apart at a glance
+pub fn short_hash(sha256: &str) -> &str {
+    &sha256[..sha256.len().min(12)]
+}
+
+/// Incremental SHA-256 and optional CRC32, fed as data is streamed
+pub struct ChecksumHasher {
+    sha256: Sha256,
+    crc32: Option<crc32fast::Hasher>,
+}
+
+impl ChecksumHasher {
+    pub fn new(with_crc32: bool) -> Self {
+        Self {
+            sha256: Sha256::new(),
+            crc32: with_crc32.then(crc32fast::Hasher::new),
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        self.sha256.update(bytes);
+        if let Some(crc32) = &mut self.crc32 {
+            crc32.update(bytes);
+        }
+    }
+
+    pub fn finish(self) -> Checksums {
+        Checksums {
+            sha256: to_hex(&self.sha256.finalize()),
+            crc32: self.crc32.map(crc32fast::Hasher::finalize),
+        }
+    }
+}
+
+/// Lowercase hex SHA-256 of `bytes`
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    to_hex(&Sha256::digest(bytes))
+}
+
+/// Hash a file, or a file inside an archive, without loading it into memory
+pub fn file_checksums<P: AsRef<Path>>(path: P, with_crc32: bool) -> Result<Checksums, String> {
+    let path = path.as_ref();
+    archive::with_payload(path, |reader| reader_checksums(reader, path, with_crc32))
+}
+
+fn reader_checksums(
+    reader: &mut dyn Read,
+    path: &Path,
+    with_crc32: bool,
+) -> Result<Checksums, String> {
+    let mut hasher = ChecksumHasher::new(with_crc32);
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    loop {
+        let read = reader
+            .read(&mut buffer)
+            .map_err(|e| format!("Failed to read file '{}': {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hasher.finish())
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(sha256_hex(b"abc"), ABC_SHA256);
+    }
+
+    #[test]
+    fn test_hasher_matches_one_shot() {
+        let mut hasher = ChecksumHasher::new(true);
+        hasher.update(b"a");
+        hasher.update(b"");
+        hasher.update(b"bc");
+        let checksums = hasher.finish();
+
+        assert_eq!(checksums.sha256, ABC_SHA256);
+        assert_eq!(checksums.short_sha256(), "ba7816bf8f01");
+        assert_eq!(checksums.crc32, Some(0x352441c2));
+        assert_eq!(checksums.crc32_text().as_deref(), Some("352441c2"));
+        assert_eq!(checksums.summary(), "SHA-256 ba7816bf8f01, CRC32 352441c2");
+        assert_eq!(short_hash("ab12"), "ab12");
+
+        assert_eq!(ChecksumHasher::new(false).finish().crc32, None);
+    }
+
+    #[test]
+    fn test_file_checksums() {
+        let temp_file = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+        let data = vec![0xa5; CHUNK_SIZE * 2 + 7];
+        std::fs::write(&temp_file, &data).expect("Failed to write test data");
+
+        let checksums = file_checksums(temp_file.path(), false).expect("Failed to hash");
+        assert_eq!(checksums.sha256, sha256_hex(&data));
+        assert_eq!(checksums.crc32, None);
+
+        let result = file_checksums("/path/that/does/not/exist", false);
+        assert!(result.unwrap_err().contains("Failed to open file"));
+    }
+}
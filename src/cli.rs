@@ -0,0 +1,1271 @@
+use crate::api::{self, ApiOptions, DEFAULT_API_BIND};
+use crate::archive::{self, ArchivePath};
+use crate::bundle::{Bundle, ImportMode, Section};
+use crate::config::{Config, ConfigLocation, DataFile};
+use crate::history::{History, HistoryEntry, Trigger};
+use crate::network::host::{self, HostOptions, DEFAULT_HOST_PORT};
+use crate::network::mock::{self, MockOptions};
+use crate::network::{FileTransfer, STDIN_PATH};
+use crate::payload::{Manifest, PayloadCheck};
+use crate::project::PROJECT_FILE_NAME;
+use crate::settings::{Overrides, Settings, ENV_FILE_PATH, ENV_IP, ENV_PORT, ENV_SHARED_CONFIG};
+use crate::status::InjectionStatus;
+use crate::watch::{self, WatchOptions};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::Duration;
+
+pub const USAGE: &str = "\
+Usage: ps-payload-injector [OPTIONS] [COMMAND]
+
+Without a command the GUI is started.
+
+Commands:
+  send [SEND OPTIONS] [FILE]    Send FILE once; use - to read it from standard input
+  watch [WATCH OPTIONS] [FILE]  Send FILE again every time it changes
+  host [HOST OPTIONS] [DIR]     Serve DIR (default: current directory) over HTTP
+  serve-mock [MOCK OPTIONS]     Run a fake payload loader for testing and dry runs
+  verify [--manifest PATH] <FILE>...
+                                Check files against the known-payload manifest
+  import-manifest <PATH>        Use the JSON or TOML manifest at PATH for checks
+  list-archive <ARCHIVE>        List the payloads inside a zip archive
+  show-config                   Print the effective settings and where each came from
+  export-settings <PATH>        Write all settings to the bundle file PATH
+  import-settings [IMPORT OPTIONS] <PATH>
+                                Import settings from the bundle file PATH
+
+A FILE can be inside a zip archive, written as ARCHIVE!/ENTRY (e.g.
+release.zip!/payload.elf). An archive holding a single payload can be given
+as is.
+
+Without a FILE, send and watch use the payload from the settings: --file,
+PS_INJECTOR_FILE, a project file's artifact or the config files.
+
+Options:
+  --config <PATH>  Use PATH as the auto-save config file
+  --portable       Keep the config file next to the executable
+  --shared-config <PATH>
+                   Read team defaults from PATH (default: shared_config.json
+                   next to the config file, if present)
+  --ip <IP>        Target IP address, overriding the config files
+  --port <PORT>    Target port, overriding the config files
+  --file <FILE>    Payload file, overriding the config files
+  --api            Start the HTTP control API (default address: 127.0.0.1:8787)
+  --api-bind <ADDR>
+                   Address for the control API, implies --api
+  --api-token <TOKEN>
+                   Require TOKEN on every control API request; needed when
+                   the API listens on a non-loopback address
+  --api-payload-root <DIR>
+                   Let control API clients send any payload under DIR, besides
+                   the configured, profile and manifest payloads
+  -h, --help       Print this help
+
+Settings are layered: built-in defaults, the shared config, the user config,
+the nearest .ps-injector.toml in the current directory or a parent, then the
+environment variables PS_INJECTOR_IP, PS_INJECTOR_PORT and PS_INJECTOR_FILE,
+then the options above. PS_INJECTOR_SHARED_CONFIG sets the shared config path.
+
+Send options:
+  --ip <IP>        Target IP address (default: from config)
+  --port <PORT>    Target port (default: from config)
+  --crc32          Also print a CRC32 of the sent bytes
+
+Watch options:
+  --ip <IP>        Target IP address (default: from config)
+  --port <PORT>    Target port (default: from config)
+  --settle <MS>    Wait until FILE has been unchanged for MS milliseconds (default: 500)
+  --require-elf    Only send FILE if it is a valid x86-64 payload ELF
+  --crc32          Also print a CRC32 of each send
+
+Import options (SECTIONS is a comma-separated list of config, profiles,
+manifest, history, preferences, recent and queue, or all; the default is
+--merge all):
+  --merge <SECTIONS>
+                   Add what is new, keeping existing entries on conflicts
+  --replace <SECTIONS>
+                   Replace these sections with the bundle's
+
+Host options:
+  --port <PORT>    Port to listen on (default: 8080)
+  --bind <ADDR>    Address to listen on instead of 0.0.0.0:PORT
+
+Mock options:
+  --port <PORT>    Port to listen on (default: 9021)
+  --bind <ADDR>    Address to listen on instead of 127.0.0.1:PORT
+  --out <DIR>      Save every received payload in DIR
+  --accept-delay <MS>
+                   Wait MS milliseconds before handling each connection
+  --read-delay <MS>
+                   Pause MS milliseconds between reads
+  --chunk <BYTES>  Read at most BYTES at a time
+  --reset-after <BYTES>
+                   Reset the connection after BYTES have been received
+  --banner <TEXT>  Send TEXT to each client when it connects
+  --refuse         Reset every connection without reading it
+";
+
+/// Options given on the command line
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CliArgs {
+    pub config_path: Option<PathBuf>,
+    pub portable: bool,
+    pub overrides: Overrides,
+    pub api: bool,
+    pub api_bind: Option<String>,
+    pub api_token: Option<String>,
+    pub api_payload_root: Option<PathBuf>,
+    pub help: bool,
+    pub command: Option<Command>,
+}
+
+/// Headless commands; the GUI runs when none is given
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Send(SendArgs),
+    Watch(WatchArgs),
+    Host(HostArgs),
+    ServeMock(MockOptions),
+    Verify(VerifyArgs),
+    ImportManifest(PathBuf),
+    ListArchive(PathBuf),
+    ShowConfig,
+    ExportSettings(PathBuf),
+    ImportSettings(ImportArgs),
+}
+
+impl Command {
+    fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "send" => Ok(Command::Send(SendArgs::default())),
+            "watch" => Ok(Command::Watch(WatchArgs::default())),
+            "host" => Ok(Command::Host(HostArgs::default())),
+            "serve-mock" => Ok(Command::ServeMock(MockOptions::default())),
+            "verify" => Ok(Command::Verify(VerifyArgs::default())),
+            "import-manifest" => Ok(Command::ImportManifest(PathBuf::new())),
+            "list-archive" => Ok(Command::ListArchive(PathBuf::new())),
+            "show-config" => Ok(Command::ShowConfig),
+            "export-settings" => Ok(Command::ExportSettings(PathBuf::new())),
+            "import-settings" => Ok(Command::ImportSettings(ImportArgs::default())),
+            _ => Err(format!("Unknown argument '{}'", name)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SendArgs {
+    pub ip: Option<String>,
+    pub port: Option<String>,
+    pub file_path: String, // `STDIN_PATH` for standard input; empty for the settings' payload
+    pub crc32: bool,
+}
+
+impl SendArgs {
+    fn parse_arg(
+        &mut self,
+        arg: String,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<(), String> {
+        match arg.as_str() {
+            "--ip" => self.ip = Some(next_value(args, "--ip")?),
+            "--port" => self.port = Some(next_value(args, "--port")?),
+            "--crc32" => self.crc32 = true,
+            _ if arg.starts_with('-') && arg != STDIN_PATH => {
+                return Err(format!("Unknown argument '{}'", arg))
+            }
+            _ if !self.file_path.is_empty() => {
+                return Err(format!("Unexpected argument '{}'", arg))
+            }
+            _ => self.file_path = arg,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WatchArgs {
+    pub ip: Option<String>,
+    pub port: Option<String>,
+    pub file_path: String, // Empty for the settings' payload
+    pub settle_ms: Option<u64>,
+    pub require_elf: bool,
+    pub crc32: bool,
+}
+
+impl WatchArgs {
+    fn parse_arg(
+        &mut self,
+        arg: String,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<(), String> {
+        match arg.as_str() {
+            "--ip" => self.ip = Some(next_value(args, "--ip")?),
+            "--port" => self.port = Some(next_value(args, "--port")?),
+            "--settle" => self.settle_ms = Some(parse_value(args, "--settle")?),
+            "--require-elf" => self.require_elf = true,
+            "--crc32" => self.crc32 = true,
+            _ if arg.starts_with('-') => return Err(format!("Unknown argument '{}'", arg)),
+            _ if !self.file_path.is_empty() => {
+                return Err(format!("Unexpected argument '{}'", arg))
+            }
+            _ => self.file_path = arg,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct HostArgs {
+    pub root: Option<PathBuf>,
+    pub port: Option<u16>,
+    pub bind: Option<String>,
+}
+
+impl HostArgs {
+    fn parse_arg(
+        &mut self,
+        arg: String,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<(), String> {
+        match arg.as_str() {
+            "--port" => self.port = Some(parse_value(args, "--port")?),
+            "--bind" => self.bind = Some(next_value(args, "--bind")?),
+            _ if arg.starts_with('-') => return Err(format!("Unknown argument '{}'", arg)),
+            _ if self.root.is_some() => return Err(format!("Unexpected argument '{}'", arg)),
+            _ => self.root = Some(PathBuf::from(arg)),
+        }
+        Ok(())
+    }
+
+    fn options(&self) -> HostOptions {
+        let root = self.root.clone().unwrap_or_else(|| PathBuf::from("."));
+        let mut options = HostOptions::new(root, self.port.unwrap_or(DEFAULT_HOST_PORT));
+        if let Some(bind) = &self.bind {
+            options.bind = bind.clone();
+        }
+        options
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VerifyArgs {
+    pub manifest: Option<PathBuf>, // Instead of the imported one
+    pub files: Vec<PathBuf>,
+}
+
+impl VerifyArgs {
+    fn parse_arg(
+        &mut self,
+        arg: String,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<(), String> {
+        match arg.as_str() {
+            "--manifest" => self.manifest = Some(PathBuf::from(next_value(args, "--manifest")?)),
+            _ if arg.starts_with('-') => return Err(format!("Unknown argument '{}'", arg)),
+            _ => self.files.push(PathBuf::from(arg)),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ImportArgs {
+    pub path: PathBuf,
+    pub merge: Vec<Section>,
+    pub replace: Vec<Section>,
+}
+
+impl ImportArgs {
+    fn parse_arg(
+        &mut self,
+        arg: String,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<(), String> {
+        match arg.as_str() {
+            "--merge" => self.merge = parse_sections(&next_value(args, "--merge")?)?,
+            "--replace" => self.replace = parse_sections(&next_value(args, "--replace")?)?,
+            _ if arg.starts_with('-') => return Err(format!("Unknown argument '{}'", arg)),
+            _ if !self.path.as_os_str().is_empty() => {
+                return Err(format!("Unexpected argument '{}'", arg))
+            }
+            _ => self.path = PathBuf::from(arg),
+        }
+        Ok(())
+    }
+
+    /// How to import each section; everything is merged when nothing was chosen
+    pub fn choices(&self) -> Result<Vec<(Section, ImportMode)>, String> {
+        if let Some(section) = self.merge.iter().find(|s| self.replace.contains(s)) {
+            return Err(format!(
+                "'{}' cannot be both merged and replaced",
+                section.key()
+            ));
+        }
+        if self.merge.is_empty() && self.replace.is_empty() {
+            return Ok(Section::ALL
+                .into_iter()
+                .map(|section| (section, ImportMode::Merge))
+                .collect());
+        }
+        // Keep the order sections are listed in everywhere else
+        Ok(Section::ALL
+            .into_iter()
+            .filter_map(|section| {
+                if self.merge.contains(&section) {
+                    Some((section, ImportMode::Merge))
+                } else if self.replace.contains(&section) {
+                    Some((section, ImportMode::Replace))
+                } else {
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+/// Comma-separated section keys, or `all`
+fn parse_sections(list: &str) -> Result<Vec<Section>, String> {
+    if list == "all" {
+        return Ok(Section::ALL.to_vec());
+    }
+    list.split(',')
+        .map(|key| Section::from_key(key.trim()))
+        .collect()
+}
+
+impl MockOptions {
+    fn parse_arg(
+        &mut self,
+        arg: String,
+        args: &mut impl Iterator<Item = String>,
+    ) -> Result<(), String> {
+        match arg.as_str() {
+            "--port" => {
+                let port: u16 = parse_value(args, "--port")?;
+                self.bind = format!("127.0.0.1:{}", port);
+            }
+            "--bind" => self.bind = next_value(args, "--bind")?,
+            "--out" => self.output_dir = Some(PathBuf::from(next_value(args, "--out")?)),
+            "--accept-delay" => {
+                self.accept_delay = Duration::from_millis(parse_value(args, "--accept-delay")?)
+            }
+            "--read-delay" => {
+                self.read_delay = Duration::from_millis(parse_value(args, "--read-delay")?)
+            }
+            "--chunk" => self.read_chunk = parse_value(args, "--chunk")?,
+            "--reset-after" => self.reset_after = Some(parse_value(args, "--reset-after")?),
+            "--banner" => self.banner = Some(next_value(args, "--banner")?),
+            "--refuse" => self.refuse = true,
+            _ => return Err(format!("Unknown argument '{}'", arg)),
+        }
+        Ok(())
+    }
+}
+
+fn next_value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next()
+        .ok_or_else(|| format!("Missing value for {}", flag))
+}
+
+fn parse_value<T: std::str::FromStr>(
+    args: &mut impl Iterator<Item = String>,
+    flag: &str,
+) -> Result<T, String> {
+    let value = next_value(args, flag)?;
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value '{}' for {}", value, flag))
+}
+
+impl CliArgs {
+    /// Parse arguments, excluding the program name
+    pub fn parse<I, S>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter().map(Into::into);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => parsed.help = true,
+                "--portable" => parsed.portable = true,
+                "--api" => parsed.api = true,
+                "--api-bind" => parsed.api_bind = Some(next_value(&mut args, "--api-bind")?),
+                "--api-token" => parsed.api_token = Some(next_value(&mut args, "--api-token")?),
+                "--api-payload-root" => {
+                    parsed.api_payload_root =
+                        Some(PathBuf::from(next_value(&mut args, "--api-payload-root")?))
+                }
+                "--config" => {
+                    parsed.config_path = Some(PathBuf::from(next_value(&mut args, "--config")?))
+                }
+                _ if arg.starts_with("--config=") => {
+                    parsed.config_path = Some(PathBuf::from(&arg["--config=".len()..]))
+                }
+                "--shared-config" => {
+                    parsed.overrides.shared_config =
+                        Some(PathBuf::from(next_value(&mut args, "--shared-config")?))
+                }
+                // After a command these are the command's own options
+                "--ip" if parsed.command.is_none() => {
+                    parsed.overrides.ip = Some(next_value(&mut args, "--ip")?)
+                }
+                "--port" if parsed.command.is_none() => {
+                    parsed.overrides.port = Some(next_value(&mut args, "--port")?)
+                }
+                "--file" if parsed.command.is_none() => {
+                    parsed.overrides.file_path = Some(next_value(&mut args, "--file")?)
+                }
+                _ => match &mut parsed.command {
+                    None => parsed.command = Some(Command::from_name(&arg)?),
+                    Some(Command::Send(send)) => send.parse_arg(arg, &mut args)?,
+                    Some(Command::Watch(watch)) => watch.parse_arg(arg, &mut args)?,
+                    Some(Command::Host(host)) => host.parse_arg(arg, &mut args)?,
+                    Some(Command::ServeMock(mock)) => mock.parse_arg(arg, &mut args)?,
+                    Some(Command::Verify(verify)) => verify.parse_arg(arg, &mut args)?,
+                    Some(Command::ImportSettings(import)) => import.parse_arg(arg, &mut args)?,
+                    Some(
+                        Command::ImportManifest(path)
+                        | Command::ListArchive(path)
+                        | Command::ExportSettings(path),
+                    ) if path.as_os_str().is_empty() => *path = PathBuf::from(arg),
+                    Some(
+                        Command::ImportManifest(_)
+                        | Command::ListArchive(_)
+                        | Command::ExportSettings(_)
+                        | Command::ShowConfig,
+                    ) => return Err(format!("Unexpected argument '{}'", arg)),
+                },
+            }
+        }
+
+        match &parsed.command {
+            Some(Command::Verify(verify)) if verify.files.is_empty() => {
+                return Err("Missing FILE for verify".to_string());
+            }
+            Some(Command::ImportManifest(path)) if path.as_os_str().is_empty() => {
+                return Err("Missing PATH for import-manifest".to_string());
+            }
+            Some(Command::ListArchive(path)) if path.as_os_str().is_empty() => {
+                return Err("Missing ARCHIVE for list-archive".to_string());
+            }
+            Some(Command::ExportSettings(path)) if path.as_os_str().is_empty() => {
+                return Err("Missing PATH for export-settings".to_string());
+            }
+            Some(Command::ImportSettings(import)) if import.path.as_os_str().is_empty() => {
+                return Err("Missing PATH for import-settings".to_string());
+            }
+            _ => {}
+        }
+
+        if parsed.portable && parsed.config_path.is_some() {
+            return Err("--config and --portable cannot be used together".to_string());
+        }
+
+        if parsed.api_token.is_some() && !parsed.api_enabled() {
+            return Err("--api-token requires --api or --api-bind".to_string());
+        }
+        if parsed.api_payload_root.is_some() && !parsed.api_enabled() {
+            return Err("--api-payload-root requires --api or --api-bind".to_string());
+        }
+
+        Ok(parsed)
+    }
+
+    /// Parse the arguments of the current process
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    /// Where the auto-save config file should live for these options
+    pub fn config_location(&self) -> ConfigLocation {
+        match &self.config_path {
+            Some(path) => ConfigLocation::Custom(path.clone()),
+            None if self.portable => ConfigLocation::Portable,
+            None => ConfigLocation::Platform,
+        }
+    }
+
+    pub fn api_enabled(&self) -> bool {
+        self.api || self.api_bind.is_some()
+    }
+
+    /// Control API settings, if the API was requested
+    pub fn api_options(&self) -> Option<ApiOptions> {
+        self.api_enabled().then(|| ApiOptions {
+            bind: self
+                .api_bind
+                .clone()
+                .unwrap_or_else(|| DEFAULT_API_BIND.to_string()),
+            token: self.api_token.clone(),
+            payload_root: self.api_payload_root.clone(),
+        })
+    }
+}
+
+/// Run a headless command and return the process exit code
+pub fn run(command: &Command) -> i32 {
+    let result = match command {
+        Command::Send(args) => run_send(args),
+        Command::Watch(args) => run_watch(args),
+        Command::Host(args) => run_host(args),
+        Command::ServeMock(options) => run_mock(options),
+        Command::Verify(args) => run_verify(args),
+        Command::ImportManifest(path) => run_import_manifest(path),
+        Command::ListArchive(path) => run_list_archive(path),
+        Command::ShowConfig => run_show_config(),
+        Command::ExportSettings(path) => run_export_settings(path),
+        Command::ImportSettings(args) => run_import_settings(args),
+    };
+    exit_code(result)
+}
+
+/// Run the control API in the foreground, printing the status of every
+/// injection it starts, and return the process exit code
+pub fn serve_api(options: ApiOptions) -> i32 {
+    let (sender, receiver) = mpsc::channel();
+    let handle = match api::start(options, Some(sender)) {
+        Ok(handle) => handle,
+        Err(e) => return exit_code(Err(e)),
+    };
+
+    println!("Control API listening at {} (Ctrl+C to stop)", handle.url());
+
+    // The API runs until interrupted, so this never ends on its own
+    for status in receiver {
+        print_status(&status);
+    }
+    0
+}
+
+fn exit_code(result: Result<(), String>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            1
+        }
+    }
+}
+
+/// Target and payload from the command's options, falling back to the
+/// layered settings
+fn resolve_target(
+    ip: &Option<String>,
+    port: &Option<String>,
+    file_path: &str,
+) -> Result<(String, String, String), String> {
+    let settings = Settings::load();
+    for error in settings.errors.iter().chain(&settings.warnings) {
+        eprintln!("Warning: {}", error);
+    }
+    if let Some(project) = &settings.project {
+        eprintln!("Using project file '{}'", project.display());
+    }
+    let target = target_from(&settings, ip, port, file_path)?;
+    if file_path.is_empty() {
+        eprintln!(
+            "Using payload '{}' from {}",
+            target.2, settings.file_path.source
+        );
+    }
+    Ok(target)
+}
+
+fn target_from(
+    settings: &Settings,
+    ip: &Option<String>,
+    port: &Option<String>,
+    file_path: &str,
+) -> Result<(String, String, String), String> {
+    let ip = ip.clone().unwrap_or_else(|| settings.ip.value.clone());
+    let port = port.clone().unwrap_or_else(|| settings.port.value.clone());
+    let file_path = match file_path {
+        "" => settings.file_path.value.clone(),
+        file_path => file_path.to_string(),
+    };
+
+    if ip.trim().is_empty() {
+        return Err("IP address is required".to_string());
+    }
+    if port.parse::<u16>().is_err() {
+        return Err(format!("Invalid port number: {}", port));
+    }
+    if file_path.trim().is_empty() {
+        return Err(
+            "Missing FILE; pass one or set a payload with --file, PS_INJECTOR_FILE or a project file"
+                .to_string(),
+        );
+    }
+    Ok((ip, port, file_path))
+}
+
+fn run_send(args: &SendArgs) -> Result<(), String> {
+    let (ip, port, file_path) = resolve_target(&args.ip, &args.port, &args.file_path)?;
+    let file_path = match file_path.as_str() {
+        STDIN_PATH => STDIN_PATH.to_string(),
+        file_path if !archive::payload_exists(file_path) => {
+            return Err(format!("File does not exist: {}", file_path));
+        }
+        file_path => archive::resolve_payload(file_path)?,
+    };
+
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| format!("Failed to create async runtime: {}", e))?;
+    let result = runtime.block_on(
+        FileTransfer::new(ip.clone(), port.clone(), file_path.clone())
+            .with_crc32(args.crc32)
+            .send_file_with_checksums(),
+    );
+
+    let entry = HistoryEntry::new(Trigger::Cli, &ip, &port, &file_path);
+    let entry = match &result {
+        Ok(report) => entry.succeeded(report.bytes_sent, Some(&report.checksums.sha256)),
+        Err(e) => entry.failed(e),
+    };
+    if let Err(e) = History::record(entry) {
+        eprintln!("Warning: {}", e);
+    }
+
+    let report = result?;
+    print_status(&InjectionStatus::Success(
+        report.bytes_sent,
+        Some(report.checksums),
+    ));
+    Ok(())
+}
+
+fn run_watch(args: &WatchArgs) -> Result<(), String> {
+    let (ip, port, file_path) = resolve_target(&args.ip, &args.port, &args.file_path)?;
+    if !archive::payload_exists(&file_path) {
+        return Err(format!("File does not exist: {}", file_path));
+    }
+    let file_path = archive::resolve_payload(&file_path)?;
+
+    let mut options = WatchOptions {
+        require_valid_elf: args.require_elf,
+        crc32: args.crc32,
+        ..WatchOptions::default()
+    };
+    if let Some(settle_ms) = args.settle_ms {
+        options.settle_time = Duration::from_millis(settle_ms);
+    }
+
+    println!(
+        "Watching '{}', sending changes to {}:{} (Ctrl+C to stop)",
+        file_path, ip, port
+    );
+
+    let (sender, receiver) = mpsc::channel();
+    let _handle = watch::start_watch(&ip, &port, &file_path, options, sender);
+
+    // The watch thread only exits when stopped, so this runs until interrupted
+    for status in receiver {
+        print_status(&status);
+    }
+
+    Ok(())
+}
+
+fn run_host(args: &HostArgs) -> Result<(), String> {
+    let options = args.options();
+    let (sender, receiver) = mpsc::channel();
+    let host = host::start_host(options, sender)?;
+
+    println!(
+        "Serving '{}' at {} (Ctrl+C to stop)",
+        host.root.display(),
+        host.url()
+    );
+
+    // The server runs until interrupted, so this never ends on its own
+    for entry in receiver {
+        println!("{}", entry.summary());
+    }
+
+    Ok(())
+}
+
+fn run_mock(options: &MockOptions) -> Result<(), String> {
+    let (sender, receiver) = mpsc::channel();
+    let loader = mock::start_mock(options.clone(), sender)?;
+
+    println!(
+        "Mock loader listening on {} (Ctrl+C to stop)",
+        loader.local_addr()
+    );
+
+    // The loader runs until interrupted, so this never ends on its own
+    for payload in receiver {
+        println!("{}", payload.summary());
+    }
+
+    Ok(())
+}
+
+fn run_verify(args: &VerifyArgs) -> Result<(), String> {
+    let manifest = match &args.manifest {
+        Some(path) => Manifest::load_from_file(path)?,
+        None => Manifest::load_or_default(),
+    };
+    if manifest.payloads.is_empty() {
+        return Err("No known payloads; import a manifest or pass --manifest".to_string());
+    }
+
+    let mut mismatches = 0;
+    for file in &args.files {
+        let file = PathBuf::from(archive::resolve_payload(&file.to_string_lossy())?);
+        let message = match manifest.check_file(&file)? {
+            PayloadCheck::Unknown => "Unknown payload".to_string(),
+            check @ PayloadCheck::HashMismatch(_) => {
+                mismatches += 1;
+                check.message().unwrap_or_default()
+            }
+            check => check.message().unwrap_or_default(),
+        };
+        println!("{}: {}", file.display(), message);
+    }
+
+    match mismatches {
+        0 => Ok(()),
+        count => Err(format!("{} file(s) do not match the manifest", count)),
+    }
+}
+
+fn run_import_manifest(path: &Path) -> Result<(), String> {
+    let manifest = Manifest::import(path)?;
+    println!(
+        "Imported {} known payload(s) from '{}'",
+        manifest.payloads.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn run_list_archive(path: &Path) -> Result<(), String> {
+    let candidates = archive::payload_candidates(path)?;
+    if candidates.is_empty() {
+        return Err(format!("No payload found in archive '{}'", path.display()));
+    }
+
+    for entry in candidates {
+        println!(
+            "{}  ({} bytes)",
+            ArchivePath::new(path, &entry.name),
+            entry.size
+        );
+    }
+    Ok(())
+}
+
+fn run_show_config() -> Result<(), String> {
+    let overrides = Settings::overrides();
+    let settings = Settings::load();
+
+    println!(
+        "User config: {}",
+        Config::default_auto_save_path().display()
+    );
+    match Settings::shared_config_path(&overrides) {
+        Some(path) => println!("Shared config: {}", path.display()),
+        None => println!(
+            "Shared config: none (set {} or --shared-config)",
+            ENV_SHARED_CONFIG
+        ),
+    }
+    match &settings.project {
+        Some(path) => println!("Project file: {}", path.display()),
+        None => println!("Project file: none ({} not found)", PROJECT_FILE_NAME),
+    }
+    println!(
+        "Environment variables: {}, {}, {}",
+        ENV_IP, ENV_PORT, ENV_FILE_PATH
+    );
+    println!();
+    for (name, value, source) in settings.describe() {
+        println!("{} = {:?}  ({})", name, value, source);
+    }
+    for warning in &settings.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+
+    if settings.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(settings.errors.join("; "))
+    }
+}
+
+fn run_export_settings(path: &Path) -> Result<(), String> {
+    let (bundle, errors) = Bundle::export(path)?;
+    let sections: Vec<&str> = bundle.sections().into_iter().map(Section::title).collect();
+    println!(
+        "Exported {} to '{}'",
+        if sections.is_empty() {
+            "no saved settings".to_string()
+        } else {
+            sections.join(", ")
+        },
+        path.display()
+    );
+    for (section, e) in &errors {
+        eprintln!("Warning: left out {}: {}", section.title(), e);
+    }
+    Ok(())
+}
+
+fn run_import_settings(args: &ImportArgs) -> Result<(), String> {
+    let report = Bundle::import(&args.path, &args.choices()?)?;
+    for line in &report.summary {
+        println!("{}", line);
+    }
+    for conflict in &report.conflicts {
+        println!("Conflict: {}", conflict);
+    }
+    Ok(())
+}
+
+fn print_status(status: &InjectionStatus) {
+    let time = chrono::Local::now().format("%H:%M:%S");
+    match status {
+        InjectionStatus::Success(bytes, checksums) => match checksums {
+            Some(checksums) => {
+                let crc32 = checksums
+                    .crc32_text()
+                    .map(|crc32| format!(", CRC32 {}", crc32))
+                    .unwrap_or_default();
+                println!(
+                    "[{}] Success! Sent {} bytes, SHA-256 {}{}",
+                    time, bytes, checksums.sha256, crc32
+                )
+            }
+            None => println!("[{}] Success! Sent {} bytes", time, bytes),
+        },
+        InjectionStatus::Error(msg) => eprintln!("[{}] Error: {}", time, msg),
+        InjectionStatus::InProgress(msg) | InjectionStatus::ConfigSaved(msg) => {
+            println!("[{}] {}", time, msg)
+        }
+        InjectionStatus::Idle | InjectionStatus::ConfigLoaded(_, _, _) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_no_args() {
+        let args = CliArgs::parse(Vec::<String>::new()).expect("Failed to parse");
+        assert_eq!(args, CliArgs::default());
+        assert_eq!(args.config_location(), ConfigLocation::Platform);
+    }
+
+    #[test]
+    fn test_parse_config_path() {
+        let args = CliArgs::parse(["--config", "/tmp/my config.json"]).expect("Failed to parse");
+        assert_eq!(args.config_path, Some(PathBuf::from("/tmp/my config.json")));
+        assert_eq!(
+            args.config_location(),
+            ConfigLocation::Custom(PathBuf::from("/tmp/my config.json"))
+        );
+
+        let args = CliArgs::parse(["--config=/tmp/other.json"]).expect("Failed to parse");
+        assert_eq!(args.config_path, Some(PathBuf::from("/tmp/other.json")));
+    }
+
+    #[test]
+    fn test_parse_portable() {
+        let args = CliArgs::parse(["--portable"]).expect("Failed to parse");
+        assert!(args.portable);
+        assert_eq!(args.config_location(), ConfigLocation::Portable);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let result = CliArgs::parse(["--config"]);
+        assert!(result.unwrap_err().contains("Missing value for --config"));
+
+        let result = CliArgs::parse(["--bogus"]);
+        assert!(result.unwrap_err().contains("Unknown argument '--bogus'"));
+
+        let result = CliArgs::parse(["--portable", "--config", "a.json"]);
+        assert!(result.unwrap_err().contains("cannot be used together"));
+
+        // Command options are only valid after the command
+        let result = CliArgs::parse(["--settle", "100", "watch", "payload.elf"]);
+        assert!(result.unwrap_err().contains("Unknown argument '--settle'"));
+
+        let result = CliArgs::parse(["--file"]);
+        assert!(result.unwrap_err().contains("Missing value for --file"));
+
+        let result = CliArgs::parse(["show-config", "extra"]);
+        assert!(result.unwrap_err().contains("Unexpected argument 'extra'"));
+    }
+
+    #[test]
+    fn test_parse_overrides() {
+        let args = CliArgs::parse([
+            "--shared-config",
+            "team.json",
+            "--ip",
+            "10.0.0.2",
+            "--file",
+            "build/payload.elf",
+            "send",
+            "--ip",
+            "10.0.0.3",
+            "other.elf",
+        ])
+        .expect("Failed to parse");
+
+        assert_eq!(
+            args.overrides,
+            Overrides {
+                shared_config: Some(PathBuf::from("team.json")),
+                ip: Some("10.0.0.2".to_string()),
+                port: None,
+                file_path: Some("build/payload.elf".to_string()),
+            }
+        );
+        // The command's own --ip takes precedence when it runs
+        assert!(matches!(
+            args.command,
+            Some(Command::Send(SendArgs { ref ip, .. })) if ip.as_deref() == Some("10.0.0.3")
+        ));
+
+        let args = CliArgs::parse(["--port", "9020", "show-config"]).expect("Failed to parse");
+        assert_eq!(args.overrides.port.as_deref(), Some("9020"));
+        assert_eq!(args.command, Some(Command::ShowConfig));
+    }
+
+    #[test]
+    fn test_parse_api() {
+        let args = CliArgs::parse(Vec::<String>::new()).expect("Failed to parse");
+        assert_eq!(args.api_options(), None);
+
+        let args = CliArgs::parse(["--api"]).expect("Failed to parse");
+        assert_eq!(args.api_options(), Some(ApiOptions::default()));
+
+        let args = CliArgs::parse(["--api-bind", "0.0.0.0:9000", "--api-token", "s3cret"])
+            .expect("Failed to parse");
+        assert_eq!(
+            args.api_options(),
+            Some(ApiOptions {
+                bind: "0.0.0.0:9000".to_string(),
+                token: Some("s3cret".to_string()),
+                payload_root: None,
+            })
+        );
+
+        let args = CliArgs::parse(["--api", "--api-payload-root", "/srv/payloads"])
+            .expect("Failed to parse");
+        assert_eq!(
+            args.api_options().unwrap().payload_root,
+            Some(PathBuf::from("/srv/payloads"))
+        );
+
+        let result = CliArgs::parse(["--api-token", "s3cret"]);
+        assert!(result.unwrap_err().contains("requires --api"));
+        let result = CliArgs::parse(["--api-payload-root", "/srv/payloads"]);
+        assert!(result.unwrap_err().contains("requires --api"));
+    }
+
+    #[test]
+    fn test_parse_watch() {
+        let args = CliArgs::parse([
+            "--portable",
+            "watch",
+            "--ip",
+            "10.0.0.2",
+            "--port",
+            "9021",
+            "--settle",
+            "1500",
+            "--require-elf",
+            "--crc32",
+            "build/payload.elf",
+        ])
+        .expect("Failed to parse");
+
+        assert!(args.portable);
+        assert_eq!(
+            args.command,
+            Some(Command::Watch(WatchArgs {
+                ip: Some("10.0.0.2".to_string()),
+                port: Some("9021".to_string()),
+                file_path: "build/payload.elf".to_string(),
+                settle_ms: Some(1500),
+                require_elf: true,
+                crc32: true,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_host() {
+        let args = CliArgs::parse(["host"]).expect("Failed to parse");
+        let Some(Command::Host(host)) = args.command else {
+            panic!("Expected host command");
+        };
+        assert_eq!(host, HostArgs::default());
+        assert_eq!(host.options(), HostOptions::new(PathBuf::from("."), 8080));
+
+        let args = CliArgs::parse(["host", "--port", "9000", "www"]).expect("Failed to parse");
+        let Some(Command::Host(host)) = args.command else {
+            panic!("Expected host command");
+        };
+        assert_eq!(host.options(), HostOptions::new(PathBuf::from("www"), 9000));
+
+        let args = CliArgs::parse(["host", "--bind", "127.0.0.1:81"]).expect("Failed to parse");
+        let Some(Command::Host(host)) = args.command else {
+            panic!("Expected host command");
+        };
+        assert_eq!(host.options().bind, "127.0.0.1:81");
+
+        let result = CliArgs::parse(["host", "--port", "http"]);
+        assert!(result
+            .unwrap_err()
+            .contains("Invalid value 'http' for --port"));
+
+        let result = CliArgs::parse(["host", "a", "b"]);
+        assert!(result.unwrap_err().contains("Unexpected argument 'b'"));
+    }
+
+    #[test]
+    fn test_parse_serve_mock() {
+        let args = CliArgs::parse(["serve-mock"]).expect("Failed to parse");
+        assert_eq!(
+            args.command,
+            Some(Command::ServeMock(MockOptions::default()))
+        );
+
+        let args = CliArgs::parse([
+            "serve-mock",
+            "--port",
+            "9020",
+            "--out",
+            "received",
+            "--accept-delay",
+            "100",
+            "--read-delay",
+            "5",
+            "--chunk",
+            "512",
+            "--reset-after",
+            "4096",
+            "--banner",
+            "ready",
+            "--refuse",
+        ])
+        .expect("Failed to parse");
+        assert_eq!(
+            args.command,
+            Some(Command::ServeMock(MockOptions {
+                bind: "127.0.0.1:9020".to_string(),
+                output_dir: Some(PathBuf::from("received")),
+                accept_delay: Duration::from_millis(100),
+                read_chunk: 512,
+                read_delay: Duration::from_millis(5),
+                reset_after: Some(4096),
+                banner: Some("ready".to_string()),
+                refuse: true,
+            }))
+        );
+
+        let result = CliArgs::parse(["serve-mock", "--chunk", "big"]);
+        assert!(result
+            .unwrap_err()
+            .contains("Invalid value 'big' for --chunk"));
+
+        let result = CliArgs::parse(["serve-mock", "extra"]);
+        assert!(result.unwrap_err().contains("Unknown argument 'extra'"));
+    }
+
+    #[test]
+    fn test_parse_manifest_commands() {
+        let args = CliArgs::parse(["verify", "--manifest", "team.toml", "a.elf", "b.bin"])
+            .expect("Failed to parse");
+        assert_eq!(
+            args.command,
+            Some(Command::Verify(VerifyArgs {
+                manifest: Some(PathBuf::from("team.toml")),
+                files: vec![PathBuf::from("a.elf"), PathBuf::from("b.bin")],
+            }))
+        );
+
+        let args = CliArgs::parse(["import-manifest", "team.json"]).expect("Failed to parse");
+        assert_eq!(
+            args.command,
+            Some(Command::ImportManifest(PathBuf::from("team.json")))
+        );
+
+        let result = CliArgs::parse(["verify"]);
+        assert!(result.unwrap_err().contains("Missing FILE for verify"));
+
+        let result = CliArgs::parse(["import-manifest"]);
+        assert!(result
+            .unwrap_err()
+            .contains("Missing PATH for import-manifest"));
+
+        let result = CliArgs::parse(["import-manifest", "a.json", "b.json"]);
+        assert!(result.unwrap_err().contains("Unexpected argument 'b.json'"));
+    }
+
+    #[test]
+    fn test_parse_send() {
+        let args = CliArgs::parse(["send", "--ip", "10.0.0.2", "-"]).expect("Failed to parse");
+        assert_eq!(
+            args.command,
+            Some(Command::Send(SendArgs {
+                ip: Some("10.0.0.2".to_string()),
+                port: None,
+                file_path: STDIN_PATH.to_string(),
+                crc32: false,
+            }))
+        );
+
+        let args = CliArgs::parse(["send", "--crc32", "payload.bin"]).expect("Failed to parse");
+        assert!(matches!(
+            args.command,
+            Some(Command::Send(SendArgs { crc32: true, .. }))
+        ));
+
+        // FILE falls back to the settings when it is left out
+        let args = CliArgs::parse(["send", "--port", "9021"]).expect("Failed to parse");
+        assert!(matches!(
+            args.command,
+            Some(Command::Send(SendArgs { ref file_path, .. })) if file_path.is_empty()
+        ));
+
+        let result = CliArgs::parse(["send", "-x", "payload.bin"]);
+        assert!(result.unwrap_err().contains("Unknown argument '-x'"));
+
+        let result = CliArgs::parse(["send", "a.bin", "b.bin"]);
+        assert!(result.unwrap_err().contains("Unexpected argument 'b.bin'"));
+    }
+
+    #[test]
+    fn test_parse_settings_commands() {
+        let args = CliArgs::parse(["export-settings", "team.json"]).expect("Failed to parse");
+        assert_eq!(
+            args.command,
+            Some(Command::ExportSettings(PathBuf::from("team.json")))
+        );
+
+        let args = CliArgs::parse(["import-settings", "team.json"]).expect("Failed to parse");
+        let Some(Command::ImportSettings(import)) = args.command else {
+            panic!("Expected import-settings command");
+        };
+        assert_eq!(import.choices().unwrap().len(), Section::ALL.len());
+
+        let args = CliArgs::parse([
+            "import-settings",
+            "--merge",
+            "history,profiles",
+            "--replace",
+            "preferences",
+            "team.json",
+        ])
+        .expect("Failed to parse");
+        let Some(Command::ImportSettings(import)) = args.command else {
+            panic!("Expected import-settings command");
+        };
+        assert_eq!(
+            import.choices(),
+            Ok(vec![
+                (Section::Profiles, ImportMode::Merge),
+                (Section::History, ImportMode::Merge),
+                (Section::Preferences, ImportMode::Replace),
+            ])
+        );
+
+        let args = CliArgs::parse([
+            "import-settings",
+            "--merge",
+            "all",
+            "--replace",
+            "config",
+            "a",
+        ])
+        .expect("Failed to parse");
+        let Some(Command::ImportSettings(import)) = args.command else {
+            panic!("Expected import-settings command");
+        };
+        assert!(import
+            .choices()
+            .unwrap_err()
+            .contains("both merged and replaced"));
+
+        let result = CliArgs::parse(["import-settings", "--merge", "window", "a.json"]);
+        assert!(result
+            .unwrap_err()
+            .contains("Unknown settings section 'window'"));
+
+        let result = CliArgs::parse(["export-settings"]);
+        assert!(result
+            .unwrap_err()
+            .contains("Missing PATH for export-settings"));
+    }
+
+    #[test]
+    fn test_parse_list_archive() {
+        let args = CliArgs::parse(["list-archive", "release.zip"]).expect("Failed to parse");
+        assert_eq!(
+            args.command,
+            Some(Command::ListArchive(PathBuf::from("release.zip")))
+        );
+
+        let result = CliArgs::parse(["list-archive"]);
+        assert!(result
+            .unwrap_err()
+            .contains("Missing ARCHIVE for list-archive"));
+
+        let args = CliArgs::parse(["watch", "release.zip!/payload.elf"]).expect("Failed to parse");
+        assert!(matches!(
+            args.command,
+            Some(Command::Watch(WatchArgs { ref file_path, .. })) if file_path == "release.zip!/payload.elf"
+        ));
+    }
+
+    #[test]
+    fn test_parse_watch_errors() {
+        let result = CliArgs::parse(["watch", "a.elf", "b.elf"]);
+        assert!(result.unwrap_err().contains("Unexpected argument 'b.elf'"));
+
+        let result = CliArgs::parse(["watch", "--settle", "soon", "a.elf"]);
+        assert!(result
+            .unwrap_err()
+            .contains("Invalid value 'soon' for --settle"));
+
+        let result = CliArgs::parse(["payload.elf"]);
+        assert!(result
+            .unwrap_err()
+            .contains("Unknown argument 'payload.elf'"));
+    }
+
+    #[test]
+    fn test_target_falls_back_to_settings() {
+        let missing_user = PathBuf::from("/nonexistent/app_config.json");
+        let env = |name: &str| (name == ENV_FILE_PATH).then(|| "build/payload.elf".to_string());
+        let settings = Settings::resolve(None, &missing_user, None, env, &Overrides::default());
+
+        let (ip, port, file_path) =
+            target_from(&settings, &Some("10.0.0.2".to_string()), &None, "")
+                .expect("Failed to resolve target");
+        assert_eq!(ip, "10.0.0.2");
+        assert_eq!(port, settings.port.value);
+        assert_eq!(file_path, "build/payload.elf");
+
+        // A FILE on the command line wins over the settings
+        let (_, _, file_path) =
+            target_from(&settings, &None, &None, "other.elf").expect("Failed to resolve target");
+        assert_eq!(file_path, "other.elf");
+
+        let settings =
+            Settings::resolve(None, &missing_user, None, |_| None, &Overrides::default());
+        let result = target_from(&settings, &None, &None, "");
+        assert!(result.unwrap_err().contains("Missing FILE"));
+    }
+}
@@ -1,7 +1,10 @@
-use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
 use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
 
 // Default configuration values
 pub const DEFAULT_IP: &str = "192.168.1.4";
@@ -9,17 +12,408 @@ pub const DEFAULT_PORT: &str = "9025";
 pub const DEFAULT_FILE_PATH: &str = "";
 pub const DEFAULT_AUTO_SAVE_ENABLED: bool = false;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Schema version written to config files by this build
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Upgrades a config from one schema version to the next.
+/// `MIGRATIONS[n]` turns a version `n` config into version `n + 1`.
+type Migration = fn(Config) -> Config;
+
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1];
+
+/// Version 0 files predate the `version` field; their fields are unchanged
+fn migrate_v0_to_v1(config: Config) -> Config {
+    Config {
+        version: 1,
+        ..config
+    }
+}
+
+// Auto-save config file location
+pub const APP_DIR_NAME: &str = "ps-payload-injector";
+pub const CONFIG_FILE_NAME: &str = "app_config.json";
+
+static CONFIG_LOCATION: OnceLock<ConfigLocation> = OnceLock::new();
+
+/// Where the auto-save config file lives
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigLocation {
+    /// The OS config directory (e.g. `$XDG_CONFIG_HOME/ps-payload-injector` on Linux)
+    Platform,
+    /// Next to the executable, for running from removable media
+    Portable,
+    /// An explicit file path given with `--config`
+    Custom(PathBuf),
+}
+
+impl ConfigLocation {
+    /// Resolve the config file path for this location
+    pub fn resolve(&self) -> PathBuf {
+        match self {
+            ConfigLocation::Platform => match dirs::config_dir() {
+                Some(dir) => dir.join(APP_DIR_NAME).join(CONFIG_FILE_NAME),
+                // No known config directory, fall back to the executable's directory
+                None => ConfigLocation::Portable.resolve(),
+            },
+            ConfigLocation::Portable => executable_dir().join(CONFIG_FILE_NAME),
+            ConfigLocation::Custom(path) => path.clone(),
+        }
+    }
+}
+
+/// Bring a config up to `CONFIG_VERSION`.
+/// Returns the migrated config and the version it started at.
+pub fn migrate(config: Config) -> Result<(Config, u32), String> {
+    let original_version = check_version(config.version)?;
+    let config = MIGRATIONS[original_version as usize..]
+        .iter()
+        .fold(config, |config, migration| migration(config));
+
+    Ok((config, original_version))
+}
+
+fn check_version(version: u32) -> Result<u32, String> {
+    if version > CONFIG_VERSION {
+        return Err(format!(
+            "config version {} is newer than supported version {}",
+            version, CONFIG_VERSION
+        ));
+    }
+    Ok(version)
+}
+
+/// Ports are strings in the config, but a number reads more naturally in a
+/// hand-written file, so take either
+pub(crate) fn port_text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    struct PortVisitor;
+
+    impl serde::de::Visitor<'_> for PortVisitor {
+        type Value = String;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("a port number or string")
+        }
+
+        fn visit_u64<E: serde::de::Error>(self, port: u64) -> Result<String, E> {
+            u16::try_from(port)
+                .map(|port| port.to_string())
+                .map_err(|_| E::invalid_value(serde::de::Unexpected::Unsigned(port), &self))
+        }
+
+        fn visit_i64<E: serde::de::Error>(self, port: i64) -> Result<String, E> {
+            u64::try_from(port)
+                .map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(port), &self))
+                .and_then(|port| self.visit_u64(port))
+        }
+
+        fn visit_str<E: serde::de::Error>(self, port: &str) -> Result<String, E> {
+            Ok(port.to_string())
+        }
+    }
+
+    deserializer.deserialize_any(PortVisitor)
+}
+
+pub(crate) fn optional_port_text<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<String>, D::Error> {
+    port_text(deserializer).map(Some)
+}
+
+/// Formats a config file can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Extensions of every supported format, for file dialogs
+    pub const EXTENSIONS: &[&str] = &["json", "toml", "yaml", "yml"];
+
+    /// Format for a path from its extension; anything unrecognised is JSON
+    pub fn from_path(path: &Path) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "toml" => ConfigFormat::Toml,
+            "yaml" | "yml" => ConfigFormat::Yaml,
+            _ => ConfigFormat::Json,
+        }
+    }
+
+    fn serialize<T: Serialize>(self, config: &T) -> Result<String, String> {
+        match self {
+            ConfigFormat::Json => serde_json::to_string_pretty(config).map_err(|e| e.to_string()),
+            ConfigFormat::Toml => toml::to_string_pretty(config).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_norway::to_string(config).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Deserialize with the format's own deserializer, so syntax and type
+    /// errors say where in the file they are
+    fn deserialize<T: DeserializeOwned>(self, content: &str) -> Result<T, String> {
+        match self {
+            // Both already end their messages with "at line L column C"
+            ConfigFormat::Json => serde_json::from_str(content).map_err(|e| e.to_string()),
+            ConfigFormat::Yaml => serde_norway::from_str(content).map_err(|e| e.to_string()),
+            ConfigFormat::Toml => toml::from_str(content).map_err(|e| toml_error(content, &e)),
+        }
+    }
+}
+
+/// A TOML error on one line, ending in "at line L column C" like JSON and YAML
+/// errors do, instead of the multi-line snippet `toml` formats
+pub fn toml_error(content: &str, e: &toml::de::Error) -> String {
+    let message = e.message().trim().lines().collect::<Vec<_>>().join(", ");
+    match e.span() {
+        Some(span) => {
+            let (line, column) = line_column(content, span.start);
+            format!("{} at line {} column {}", message, line, column)
+        }
+        None => message,
+    }
+}
+
+/// One-based line and column of a byte offset
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset.min(content.len())];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Read a config file in the format its extension names
+fn read_config_file<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let file_content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read config file '{}': {}", path.display(), e))?;
+
+    ConfigFormat::from_path(path)
+        .deserialize(&file_content)
+        .map_err(|e| parse_error(path, e))
+}
+
+fn parse_error(path: &Path, e: String) -> String {
+    format!("Failed to parse config file '{}': {}", path.display(), e)
+}
+
+/// Backup file name for a config written with schema `version`
+fn backup_path_for(path: &Path, version: u32) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".v{}.bak", version));
+    path.with_file_name(file_name)
+}
+
+// Numbers the temporary files of `write_file_atomic`
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Write `contents` to `path` by writing a temporary file in the same directory,
+/// syncing it to disk and renaming it over `path`. Readers see either the old or
+/// the new contents, never a partial write. `what` names the file in error messages.
+pub fn write_file_atomic(path: &Path, contents: &[u8], what: &str) -> Result<(), String> {
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(path.file_name().unwrap_or_default());
+    // Unique per call, so two saves of the same file cannot share a temporary file
+    let count = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+    temp_name.push(format!(".{}.{}.tmp", std::process::id(), count));
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = write_and_rename(&temp_path, path, contents, what);
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+        return result;
+    }
+
+    // Persist the rename itself; not supported for directories on Windows
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        let dir = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+
+    Ok(())
+}
+
+fn write_and_rename(
+    temp_path: &Path,
+    path: &Path,
+    contents: &[u8],
+    what: &str,
+) -> Result<(), String> {
+    let mut file = File::create(temp_path)
+        .map_err(|e| format!("Failed to create {} '{}': {}", what, path.display(), e))?;
+
+    file.write_all(contents)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write {} '{}': {}", what, path.display(), e))?;
+
+    std::fs::rename(temp_path, path)
+        .map_err(|e| format!("Failed to replace {} '{}': {}", what, path.display(), e))
+}
+
+/// Parse a JSON file. `what` names the file in error messages.
+pub fn read_json_file<T: DeserializeOwned>(path: &Path, what: &str) -> Result<T, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {} '{}': {}", what, path.display(), e))?;
+
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {} '{}': {}", what, path.display(), e))
+}
+
+/// Write `value` as pretty-printed JSON with `write_file_atomic`
+pub fn write_json_file<T: Serialize>(path: &Path, value: &T, what: &str) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", what, e))?;
+
+    write_file_atomic(path, json.as_bytes(), what)
+}
+
+/// Data kept in its own JSON file next to the config, such as the history or
+/// the profiles. Implementors only name the file.
+pub trait DataFile: Serialize + DeserializeOwned + Default {
+    const FILE_NAME: &'static str;
+    /// Names the file in error messages, e.g. `history file`
+    const WHAT: &'static str;
+
+    fn default_path() -> PathBuf {
+        Config::data_file_path(Self::FILE_NAME)
+    }
+
+    /// Load the default file, or the default value if it is missing or unreadable
+    fn load_or_default() -> Self {
+        Self::load_from_file(Self::default_path()).unwrap_or_default()
+    }
+
+    fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        read_json_file(path.as_ref(), Self::WHAT)
+    }
+
+    /// Save to the default file, creating the config directory if needed
+    fn save(&self) -> Result<(), String> {
+        Config::ensure_config_dir()?;
+        self.save_to_file(Self::default_path())
+    }
+
+    fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        write_json_file(path.as_ref(), self, Self::WHAT)
+    }
+}
+
+/// Directory containing the running executable, or the current directory if unknown
+pub fn executable_dir() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+/// Path of the config file written by versions that saved next to the executable
+pub fn legacy_config_path() -> PathBuf {
+    executable_dir().join(CONFIG_FILE_NAME)
+}
+
+/// Move a legacy config file to `target` if `target` does not exist yet.
+/// Returns `Ok(true)` when a file was migrated.
+pub fn migrate_legacy_file(legacy: &Path, target: &Path) -> Result<bool, String> {
+    if target.exists() || !legacy.is_file() || legacy == target {
+        return Ok(false);
+    }
+
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            format!(
+                "Failed to create config directory '{}': {}",
+                parent.display(),
+                e
+            )
+        })?;
+    }
+
+    // Copy then remove, since a rename fails across filesystems
+    std::fs::copy(legacy, target).map_err(|e| {
+        format!(
+            "Failed to migrate config file '{}' to '{}': {}",
+            legacy.display(),
+            target.display(),
+            e
+        )
+    })?;
+    let _ = std::fs::remove_file(legacy);
+
+    Ok(true)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
 pub struct Config {
+    /// Files written before schema versions have none
+    #[serde(default)]
+    pub version: u32,
     pub ip: String,
+    #[serde(deserialize_with = "port_text")]
     pub port: String,
     pub file_path: String,
     pub auto_save_enabled: bool,
 }
 
+/// The values one config file sets, for layering it over others.
+/// Fields the file leaves out are `None`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct ConfigLayer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+    #[serde(
+        deserialize_with = "optional_port_text",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub port: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_save_enabled: Option<bool>,
+}
+
+impl ConfigLayer {
+    /// Check the schema version of a layer read from elsewhere, such as a
+    /// settings bundle, and mark it current
+    pub fn upgraded(mut self) -> Result<Self, String> {
+        check_version(self.version.unwrap_or(0))?;
+        self.version = Some(CONFIG_VERSION);
+        Ok(self)
+    }
+}
+
+impl Default for Config {
+    /// Create a config with default values
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_IP.to_string(),
+            DEFAULT_PORT.to_string(),
+            DEFAULT_FILE_PATH.to_string(),
+        )
+    }
+}
+
 impl Config {
     pub fn new(ip: String, port: String, file_path: String) -> Self {
         Self {
+            version: CONFIG_VERSION,
             ip,
             port,
             file_path,
@@ -34,6 +428,7 @@ impl Config {
         auto_save_enabled: bool,
     ) -> Self {
         Self {
+            version: CONFIG_VERSION,
             ip,
             port,
             file_path,
@@ -41,15 +436,6 @@ impl Config {
         }
     }
 
-    /// Create a config with default values
-    pub fn default() -> Self {
-        Self::new(
-            DEFAULT_IP.to_string(),
-            DEFAULT_PORT.to_string(),
-            DEFAULT_FILE_PATH.to_string(),
-        )
-    }
-
     /// Clean up test config files (only available in test builds)
     #[cfg(test)]
     pub fn cleanup_test_files() {
@@ -73,6 +459,35 @@ impl Config {
         }
     }
 
+    /// Set where the auto-save config file lives. Must be called before the
+    /// config is first used; later calls are ignored.
+    pub fn set_location(location: ConfigLocation) {
+        let _ = CONFIG_LOCATION.set(location);
+    }
+
+    /// The active auto-save config location
+    pub fn location() -> ConfigLocation {
+        CONFIG_LOCATION
+            .get()
+            .cloned()
+            .unwrap_or(ConfigLocation::Platform)
+    }
+
+    /// Move an `app_config.json` left next to the executable into the platform
+    /// config directory. Only applies when using the platform location.
+    pub fn migrate_legacy_config() -> Result<Option<PathBuf>, String> {
+        if Self::location() != ConfigLocation::Platform {
+            return Ok(None);
+        }
+
+        let legacy = legacy_config_path();
+        if migrate_legacy_file(&legacy, &Self::default_auto_save_path())? {
+            Ok(Some(legacy))
+        } else {
+            Ok(None)
+        }
+    }
+
     /// Get the default path for auto-save config file
     pub fn default_auto_save_path() -> PathBuf {
         // Use different filenames for tests vs production
@@ -84,71 +499,157 @@ impl Config {
             let safe_thread_id = thread_id.replace("ThreadId(", "").replace(")", "");
             PathBuf::from(format!("test-app_config-{}.json", safe_thread_id))
         } else {
-            PathBuf::from("app_config.json")
+            Self::location().resolve()
+        }
+    }
+
+    /// Path of another app data file (history, queue, ...) stored alongside the
+    /// auto-save config file
+    pub fn data_file_path(file_name: &str) -> PathBuf {
+        let config_path = Self::default_auto_save_path();
+        if cfg!(test) {
+            // Keep the test prefix so cleanup_test_files removes it
+            let stem = config_path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default()
+                .to_string();
+            config_path.with_file_name(format!("{}-{}", stem, file_name))
+        } else {
+            config_path.with_file_name(file_name)
+        }
+    }
+
+    /// Create the directory that holds the auto-save config and data files
+    pub fn ensure_config_dir() -> Result<(), String> {
+        let config_path = Self::default_auto_save_path();
+        match config_path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => std::fs::create_dir_all(parent)
+                .map_err(|e| {
+                    format!(
+                        "Failed to create config directory '{}': {}",
+                        parent.display(),
+                        e
+                    )
+                }),
+            _ => Ok(()),
         }
     }
 
     /// Load config from default auto-save path with fallback to defaults
     /// Does not create a config file if none exists
     pub fn load_or_default() -> Self {
+        // Return default config if loading fails - do NOT save anything
+        Self::load_auto_save().ok().flatten().unwrap_or_default()
+    }
+
+    /// Load the auto-save config, migrated to the current schema in memory.
+    /// Returns `Ok(None)` if no config file exists.
+    pub fn load_auto_save() -> Result<Option<Self>, String> {
         let config_path = Self::default_auto_save_path();
+        if !config_path.exists() {
+            return Ok(None);
+        }
 
-        match Self::load_from_file(&config_path) {
-            Ok(config) => config,
-            Err(_) => {
-                // Return default config if loading fails - do NOT save anything
-                Self::default()
-            }
+        Self::load_from_file(&config_path).map(Some)
+    }
+
+    /// Rewrite the auto-save config in place if it uses an older schema.
+    /// Does nothing if no config file exists.
+    pub fn upgrade_auto_save() -> Result<(), String> {
+        let config_path = Self::default_auto_save_path();
+        if !config_path.exists() {
+            return Ok(());
         }
+
+        Self::load_and_upgrade(&config_path).map(|_| ())
+    }
+
+    /// Load a config file and, if it was migrated from an older schema, rewrite it
+    /// with the current schema after copying the original to `<file>.v<N>.bak`.
+    /// Fails if the file cannot be read, backed up or rewritten; the file is
+    /// left as it was unless the rewrite succeeds.
+    pub fn load_and_upgrade<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let (config, original_version) = Self::load_versioned(path)?;
+
+        if original_version < CONFIG_VERSION {
+            let backup_path = backup_path_for(path, original_version);
+            std::fs::copy(path, &backup_path).map_err(|e| {
+                format!(
+                    "Not upgrading config file '{}', backup to '{}' failed: {}",
+                    path.display(),
+                    backup_path.display(),
+                    e
+                )
+            })?;
+            config
+                .save_to_file(path)
+                .map_err(|e| format!("Failed to upgrade config file: {}", e))?;
+        }
+
+        Ok(config)
     }
 
     /// Auto-save config to default location
     pub fn auto_save(&self) -> Result<(), String> {
-        let config_path = Self::default_auto_save_path();
-        self.save_to_file(&config_path)
+        // The platform config directory may not exist on first run
+        Self::ensure_config_dir()?;
+
+        self.save_to_file(Self::default_auto_save_path())
     }
 
-    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
-        let json = serde_json::to_string_pretty(self)
+    /// Change the fields the auto-save config sets. Fields it leaves out stay
+    /// out, so the defaults and the shared config still show through them.
+    pub fn update_auto_save(update: impl FnOnce(&mut ConfigLayer)) -> Result<(), String> {
+        let path = Self::default_auto_save_path();
+        let mut layer = if path.exists() {
+            Self::load_layer(&path)?
+        } else {
+            ConfigLayer::default()
+        };
+        update(&mut layer);
+        layer.version = Some(CONFIG_VERSION);
+
+        let content = ConfigFormat::from_path(&path)
+            .serialize(&layer)
             .map_err(|e| format!("Failed to serialize config: {}", e))?;
 
-        let mut file = File::create(path.as_ref()).map_err(|e| {
-            format!(
-                "Failed to create config file '{}': {}",
-                path.as_ref().display(),
-                e
-            )
-        })?;
+        Self::ensure_config_dir()?;
+        write_file_atomic(&path, content.as_bytes(), "config file")
+    }
 
-        file.write_all(json.as_bytes()).map_err(|e| {
-            format!(
-                "Failed to write config file '{}': {}",
-                path.as_ref().display(),
-                e
-            )
-        })?;
+    /// Save the config atomically, so a crash mid-write never leaves a truncated
+    /// file. The format follows the extension: `.toml`, `.yaml`/`.yml` or JSON.
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path = path.as_ref();
+        let content = ConfigFormat::from_path(path)
+            .serialize(self)
+            .map_err(|e| format!("Failed to serialize config: {}", e))?;
 
-        Ok(())
+        write_file_atomic(path, content.as_bytes(), "config file")
     }
 
+    /// Load a config file in any `ConfigFormat`, migrating older schemas in
+    /// memory. Missing fields take their default values.
     pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
-        let file_content = std::fs::read_to_string(path.as_ref()).map_err(|e| {
-            format!(
-                "Failed to read config file '{}': {}",
-                path.as_ref().display(),
-                e
-            )
-        })?;
+        Self::load_versioned(path.as_ref()).map(|(config, _)| config)
+    }
 
-        let config: Config = serde_json::from_str(&file_content).map_err(|e| {
-            format!(
-                "Failed to parse config file '{}': {}",
-                path.as_ref().display(),
-                e
-            )
-        })?;
+    /// Load only the values a config file sets. Every schema so far has the
+    /// same fields, so older files need no migrating.
+    pub fn load_layer<P: AsRef<Path>>(path: P) -> Result<ConfigLayer, String> {
+        let path = path.as_ref();
+        let layer: ConfigLayer = read_config_file(path)?;
+        check_version(layer.version.unwrap_or(0)).map_err(|e| parse_error(path, e))?;
+        Ok(layer)
+    }
 
-        Ok(config)
+    /// Load a config file, migrated to `CONFIG_VERSION`, and return it with the
+    /// schema version it was written with
+    fn load_versioned(path: &Path) -> Result<(Self, u32), String> {
+        let config: Config = read_config_file(path)?;
+        migrate(config).map_err(|e| parse_error(path, e))
     }
 
     /// Check if auto-save config file exists
@@ -190,7 +691,7 @@ mod tests {
         assert_eq!(config.ip, "192.168.1.1");
         assert_eq!(config.port, "8080");
         assert_eq!(config.file_path, "/path/to/file");
-        assert_eq!(config.auto_save_enabled, true);
+        assert!(config.auto_save_enabled);
     }
 
     #[test]
@@ -239,6 +740,60 @@ mod tests {
         assert!(file_content.contains("\"file_path\": \"/home/user/file.txt\""));
     }
 
+    #[test]
+    fn test_save_config_replaces_existing_file() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let config_path = temp_dir.path().join(CONFIG_FILE_NAME);
+
+        let long_config = Config::new(
+            "192.168.100.200".to_string(),
+            "9025".to_string(),
+            "/a/very/long/path/to/some/payload/file.bin".to_string(),
+        );
+        long_config
+            .save_to_file(&config_path)
+            .expect("Failed to save");
+
+        let short_config = Config::new("1.2.3.4".to_string(), "1".to_string(), "".to_string());
+        short_config
+            .save_to_file(&config_path)
+            .expect("Failed to save");
+
+        // The new content fully replaces the old one and no temp files are left behind
+        let loaded = Config::load_from_file(&config_path).expect("Failed to load config");
+        assert_eq!(loaded.ip, "1.2.3.4");
+        assert_eq!(loaded.file_path, "");
+        let entries: Vec<_> = fs::read_dir(temp_dir.path())
+            .expect("Failed to list dir")
+            .flatten()
+            .map(|entry| entry.file_name())
+            .collect();
+        assert_eq!(entries, vec![std::ffi::OsString::from(CONFIG_FILE_NAME)]);
+    }
+
+    #[test]
+    fn test_concurrent_saves_do_not_mix() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("data.json");
+        let contents: Vec<Vec<u8>> = (0..8u8).map(|i| vec![b'a' + i; 64 * 1024]).collect();
+
+        std::thread::scope(|scope| {
+            for content in &contents {
+                let path = &path;
+                scope.spawn(move || {
+                    for _ in 0..10 {
+                        write_file_atomic(path, content, "data file").expect("Failed to write");
+                    }
+                });
+            }
+        });
+
+        // The file holds one whole write
+        let written = fs::read(&path).expect("Failed to read");
+        assert!(contents.contains(&written));
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
     #[test]
     fn test_load_config_invalid_json() {
         let temp_file = NamedTempFile::new().expect("Failed to create temp file");
@@ -252,6 +807,103 @@ mod tests {
         assert!(result.unwrap_err().contains("Failed to parse config file"));
     }
 
+    #[test]
+    fn test_save_and_load_toml_and_yaml() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let config = Config::new_with_auto_save(
+            "10.0.0.2".to_string(),
+            "9021".to_string(),
+            "/payloads/goldhen.bin".to_string(),
+            true,
+        );
+
+        for name in ["config.toml", "config.yaml", "config.YML", "config.cfg"] {
+            let path = temp_dir.path().join(name);
+            config.save_to_file(&path).expect("Failed to save config");
+            let loaded = Config::load_from_file(&path).expect("Failed to load config");
+            assert_eq!(loaded, config, "{}", name);
+        }
+
+        let toml = fs::read_to_string(temp_dir.path().join("config.toml")).unwrap();
+        assert!(toml.contains("ip = \"10.0.0.2\""));
+        let yaml = fs::read_to_string(temp_dir.path().join("config.yaml")).unwrap();
+        assert!(yaml.contains("port: '9021'"));
+        let json = fs::read_to_string(temp_dir.path().join("config.cfg")).unwrap();
+        assert!(json.contains("\"port\": \"9021\""));
+
+        // Hand-edited TOML with comments and an older schema
+        let path = temp_dir.path().join("targets.toml");
+        fs::write(
+            &path,
+            "# Lab console\nip = \"10.0.0.3\" # bench 2\nport = \"9020\"\n",
+        )
+        .expect("Failed to write file");
+        let loaded = Config::load_from_file(&path).expect("Failed to load config");
+        assert_eq!(loaded.ip, "10.0.0.3");
+        assert_eq!(loaded.version, CONFIG_VERSION);
+
+        // Hand-written ports are usually numbers
+        for (name, content) in [
+            ("number.toml", "port = 9021\n"),
+            ("number.yaml", "port: 9021\n"),
+            ("number.json", r#"{"port": 9021}"#),
+        ] {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, content).expect("Failed to write file");
+            let loaded = Config::load_from_file(&path).expect("Failed to load config");
+            assert_eq!(loaded.port, "9021", "{}", name);
+            let layer = Config::load_layer(&path).expect("Failed to load layer");
+            assert_eq!(layer.port.as_deref(), Some("9021"), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors_have_line_and_column() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let cases = [
+            (
+                "broken.json",
+                "{\n  \"ip\": \"10.0.0.2\",\n  port\n}",
+                "at line 3 column 3",
+            ),
+            (
+                "broken.toml",
+                "ip = \"10.0.0.2\"\nport = = 9021\n",
+                "at line 2 column 8",
+            ),
+            (
+                "broken.yaml",
+                "ip: 10.0.0.2\n  port: 9021\n",
+                "at line 2 column 7",
+            ),
+            // Values of the wrong type are located too
+            (
+                "wrong_type.json",
+                "{\n  \"ip\": \"10.0.0.2\",\n  \"auto_save_enabled\": \"yes\"\n}",
+                "at line 3 column",
+            ),
+            (
+                "wrong_type.toml",
+                "ip = \"10.0.0.2\"\nport = true\n",
+                "at line 2 column 8",
+            ),
+            (
+                "wrong_type.yaml",
+                "ip: 10.0.0.2\nport: [9021]\n",
+                "at line 2 column",
+            ),
+        ];
+
+        for (name, content, location) in cases {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, content).expect("Failed to write file");
+            let error = Config::load_from_file(&path).unwrap_err();
+            assert!(error.contains("Failed to parse config file"), "{}", error);
+            assert!(error.contains(location), "{}", error);
+            assert!(!error.contains('\n'), "{}", error);
+        }
+    }
+
     #[test]
     fn test_load_config_missing_fields() {
         let temp_file = NamedTempFile::new().expect("Failed to create temp file");
@@ -260,11 +912,131 @@ mod tests {
         // Write JSON with missing fields
         fs::write(temp_path, r#"{"ip": "192.168.1.1"}"#).expect("Failed to write file");
 
+        // Missing fields fall back to their defaults
+        let config = Config::load_from_file(temp_path).expect("Failed to load config");
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.ip, "192.168.1.1");
+        assert_eq!(config.port, DEFAULT_PORT);
+        assert_eq!(config.file_path, DEFAULT_FILE_PATH);
+        assert_eq!(config.auto_save_enabled, DEFAULT_AUTO_SAVE_ENABLED);
+    }
+
+    #[test]
+    fn test_load_layer_only_has_set_fields() {
+        let temp_file = NamedTempFile::new().expect("Failed to create temp file");
+        fs::write(
+            temp_file.path(),
+            r#"{"port": "9021", "comment": "team targets"}"#,
+        )
+        .expect("Failed to write file");
+
+        let layer = Config::load_layer(temp_file.path()).expect("Failed to load layer");
+        assert_eq!(
+            layer,
+            ConfigLayer {
+                port: Some("9021".to_string()),
+                ..ConfigLayer::default()
+            }
+        );
+
+        fs::write(temp_file.path(), r#"{"version": 999}"#).expect("Failed to write file");
+        let result = Config::load_layer(temp_file.path());
+        assert!(result.unwrap_err().contains("newer than supported version"));
+    }
+
+    #[test]
+    fn test_load_config_wrong_field_type() {
+        let temp_file = NamedTempFile::new().expect("Failed to create temp file");
+        let temp_path = temp_file.path();
+
+        fs::write(temp_path, r#"{"version": 1, "ip": 42}"#).expect("Failed to write file");
+
         let result = Config::load_from_file(temp_path);
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("Failed to parse config file"));
     }
 
+    #[test]
+    fn test_load_config_newer_version() {
+        let temp_file = NamedTempFile::new().expect("Failed to create temp file");
+        let temp_path = temp_file.path();
+
+        fs::write(temp_path, r#"{"version": 999, "ip": "192.168.1.1"}"#)
+            .expect("Failed to write file");
+
+        let result = Config::load_from_file(temp_path);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("newer than supported version"));
+    }
+
+    #[test]
+    fn test_migrations_cover_every_version() {
+        assert_eq!(MIGRATIONS.len(), CONFIG_VERSION as usize);
+    }
+
+    #[test]
+    fn test_load_and_upgrade_legacy_config() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let config_path = temp_dir.path().join(CONFIG_FILE_NAME);
+        let legacy_content = r#"{
+            "ip": "10.0.0.9",
+            "port": "9021",
+            "file_path": "/legacy/payload.elf",
+            "auto_save_enabled": true
+        }"#;
+        fs::write(&config_path, legacy_content).expect("Failed to write file");
+
+        let config = Config::load_and_upgrade(&config_path).expect("Failed to load config");
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.ip, "10.0.0.9");
+        assert_eq!(config.port, "9021");
+        assert_eq!(config.file_path, "/legacy/payload.elf");
+        assert!(config.auto_save_enabled);
+
+        // The original is kept as a backup and the file is rewritten with a version
+        let backup_path = temp_dir.path().join("app_config.json.v0.bak");
+        assert_eq!(
+            fs::read_to_string(&backup_path).expect("Missing backup"),
+            legacy_content
+        );
+        let upgraded = fs::read_to_string(&config_path).expect("Failed to read file");
+        assert!(upgraded.contains(&format!("\"version\": {}", CONFIG_VERSION)));
+
+        // Loading an up-to-date file does not create further backups
+        Config::load_and_upgrade(&config_path).expect("Failed to reload config");
+        let backups = fs::read_dir(temp_dir.path())
+            .expect("Failed to list dir")
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .map(|e| e.file_name().to_string_lossy().ends_with(".bak"))
+                    .unwrap_or(false)
+            })
+            .count();
+        assert_eq!(backups, 1);
+    }
+
+    #[test]
+    fn test_failed_upgrade_is_reported() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let config_path = temp_dir.path().join(CONFIG_FILE_NAME);
+        let legacy_content = r#"{"ip": "10.0.0.9", "port": "9021", "file_path": ""}"#;
+        fs::write(&config_path, legacy_content).expect("Failed to write file");
+        // A directory in the way of the backup
+        fs::create_dir(temp_dir.path().join("app_config.json.v0.bak"))
+            .expect("Failed to create dir");
+
+        let error = Config::load_and_upgrade(&config_path).unwrap_err();
+        assert!(error.contains("Not upgrading config file"));
+        assert_eq!(
+            fs::read_to_string(&config_path).expect("Failed to read file"),
+            legacy_content
+        );
+        // The file can still be used as it is
+        let config = Config::load_from_file(&config_path).expect("Failed to load config");
+        assert_eq!(config.ip, "10.0.0.9");
+    }
+
     #[test]
     fn test_load_config_nonexistent_file() {
         let result = Config::load_from_file("/path/that/does/not/exist.json");
@@ -366,6 +1138,75 @@ mod tests {
         assert_eq!(config.auto_save_enabled, DEFAULT_AUTO_SAVE_ENABLED);
     }
 
+    #[test]
+    fn test_config_location_resolve() {
+        let custom = ConfigLocation::Custom(PathBuf::from("/tmp/custom/config.json"));
+        assert_eq!(custom.resolve(), PathBuf::from("/tmp/custom/config.json"));
+
+        let portable = ConfigLocation::Portable.resolve();
+        assert_eq!(portable, executable_dir().join(CONFIG_FILE_NAME));
+
+        let platform = ConfigLocation::Platform.resolve();
+        assert!(platform.ends_with(CONFIG_FILE_NAME));
+        if let Some(config_dir) = dirs::config_dir() {
+            assert_eq!(
+                platform,
+                config_dir.join(APP_DIR_NAME).join(CONFIG_FILE_NAME)
+            );
+        }
+    }
+
+    #[test]
+    fn test_migrate_legacy_file() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let legacy = temp_dir.path().join(CONFIG_FILE_NAME);
+        let target = temp_dir
+            .path()
+            .join("config")
+            .join(APP_DIR_NAME)
+            .join(CONFIG_FILE_NAME);
+
+        // Nothing to migrate
+        assert_eq!(migrate_legacy_file(&legacy, &target), Ok(false));
+
+        let config = Config::new(
+            "10.0.0.7".to_string(),
+            "9020".to_string(),
+            "/legacy/payload.bin".to_string(),
+        );
+        config
+            .save_to_file(&legacy)
+            .expect("Failed to write legacy config");
+
+        assert_eq!(migrate_legacy_file(&legacy, &target), Ok(true));
+        assert!(!legacy.exists());
+
+        let migrated = Config::load_from_file(&target).expect("Failed to load migrated config");
+        assert_eq!(migrated.ip, "10.0.0.7");
+        assert_eq!(migrated.port, "9020");
+        assert_eq!(migrated.file_path, "/legacy/payload.bin");
+    }
+
+    #[test]
+    fn test_migrate_legacy_file_keeps_existing_target() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let legacy = temp_dir.path().join("legacy.json");
+        let target = temp_dir.path().join("target.json");
+
+        Config::new("1.1.1.1".to_string(), "1".to_string(), "".to_string())
+            .save_to_file(&legacy)
+            .expect("Failed to write legacy config");
+        Config::new("2.2.2.2".to_string(), "2".to_string(), "".to_string())
+            .save_to_file(&target)
+            .expect("Failed to write target config");
+
+        // An existing config in the new location always wins
+        assert_eq!(migrate_legacy_file(&legacy, &target), Ok(false));
+        assert!(legacy.exists());
+        let loaded = Config::load_from_file(&target).expect("Failed to load target config");
+        assert_eq!(loaded.ip, "2.2.2.2");
+    }
+
     // This test should run last to clean up any test config files
     // The test name starts with 'z' to ensure it runs after other tests alphabetically
     #[test]
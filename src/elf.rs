@@ -0,0 +1,389 @@
+// ELF identification and header constants
+const ELF_MAGIC: &[u8; 4] = b"\x7fELF";
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const ET_EXEC: u16 = 2;
+const ET_DYN: u16 = 3;
+const EM_X86_64: u16 = 62;
+const ELF64_HEADER_SIZE: usize = 64;
+const SECTION_HEADER_SIZE: usize = 64;
+const PROGRAM_HEADER_SIZE: usize = 56;
+const SHT_NULL: u32 = 0;
+const SHT_NOBITS: u32 = 8;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+
+/// The fields of an ELF64 header that matter for payload checks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ElfHeader {
+    pub elf_type: u16,
+    pub machine: u16,
+    pub entry: u64,
+}
+
+impl ElfHeader {
+    pub fn type_name(&self) -> &'static str {
+        match self.elf_type {
+            1 => "relocatable",
+            ET_EXEC => "executable",
+            ET_DYN => "shared object",
+            4 => "core",
+            _ => "unknown",
+        }
+    }
+}
+
+/// Parse the header of a little-endian ELF64 file
+pub fn parse_header(bytes: &[u8]) -> Result<ElfHeader, String> {
+    if bytes.len() < 4 || &bytes[..4] != ELF_MAGIC {
+        return Err("Not an ELF file".to_string());
+    }
+
+    if bytes.len() < ELF64_HEADER_SIZE {
+        return Err(format!(
+            "ELF header is truncated ({} of {} bytes)",
+            bytes.len(),
+            ELF64_HEADER_SIZE
+        ));
+    }
+
+    if bytes[4] != ELFCLASS64 {
+        return Err("Only 64-bit ELF files are supported".to_string());
+    }
+
+    if bytes[5] != ELFDATA2LSB {
+        return Err("Only little-endian ELF files are supported".to_string());
+    }
+
+    Ok(ElfHeader {
+        elf_type: read_u16(bytes, 16),
+        machine: read_u16(bytes, 18),
+        entry: read_u64(bytes, 24),
+    })
+}
+
+/// Check that `bytes` is an ELF a console payload loader can run:
+/// a 64-bit little-endian x86-64 executable or shared object
+pub fn validate_payload(bytes: &[u8]) -> Result<ElfHeader, String> {
+    let header = parse_header(bytes)?;
+
+    if header.machine != EM_X86_64 {
+        return Err(format!("ELF machine type {} is not x86-64", header.machine));
+    }
+
+    if header.elf_type != ET_EXEC && header.elf_type != ET_DYN {
+        return Err(format!(
+            "ELF type '{}' is not an executable or shared object",
+            header.type_name()
+        ));
+    }
+
+    Ok(header)
+}
+
+/// A section from the section header table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElfSection {
+    pub name: String,
+    pub section_type: u32,
+    pub address: u64,
+    pub offset: u64,
+    pub size: u64,
+}
+
+impl ElfSection {
+    /// Whether the section has bytes in the file, unlike .bss
+    pub fn has_file_data(&self) -> bool {
+        self.section_type != SHT_NOBITS && self.section_type != SHT_NULL
+    }
+}
+
+/// A segment from the program header table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ElfSegment {
+    pub segment_type: u32,
+    pub flags: u32,
+    pub offset: u64,
+    pub address: u64,
+    pub file_size: u64,
+    pub memory_size: u64,
+}
+
+impl ElfSegment {
+    pub fn type_name(&self) -> &'static str {
+        match self.segment_type {
+            0 => "NULL",
+            1 => "LOAD",
+            2 => "DYNAMIC",
+            3 => "INTERP",
+            4 => "NOTE",
+            6 => "PHDR",
+            7 => "TLS",
+            0x6474e550 => "GNU_EH_FRAME",
+            0x6474e551 => "GNU_STACK",
+            0x6474e552 => "GNU_RELRO",
+            _ => "OTHER",
+        }
+    }
+
+    /// Permissions in `readelf` style, e.g. `R E`
+    pub fn flags_text(&self) -> String {
+        [(PF_R, 'R'), (PF_W, 'W'), (PF_X, 'E')]
+            .iter()
+            .map(|(flag, letter)| if self.flags & flag != 0 { *letter } else { ' ' })
+            .collect()
+    }
+}
+
+/// The sections of an ELF64 file, named from the section name table.
+/// Files without a section header table have none.
+pub fn sections(bytes: &[u8]) -> Result<Vec<ElfSection>, String> {
+    parse_header(bytes)?;
+    let table_offset = read_u64(bytes, 0x28);
+    let entry_size = read_u16(bytes, 0x3a) as usize;
+    let count = read_u16(bytes, 0x3c) as usize;
+    let names_index = read_u16(bytes, 0x3e) as usize;
+    if table_offset == 0 || count == 0 {
+        return Ok(Vec::new());
+    }
+    let entries = table(
+        bytes,
+        table_offset,
+        entry_size,
+        count,
+        SECTION_HEADER_SIZE,
+        "section",
+    )?;
+
+    let names = entries.get(names_index).map(|entry| {
+        let offset = read_u64(entry, 24) as usize;
+        let size = read_u64(entry, 32) as usize;
+        bytes
+            .get(offset..offset.saturating_add(size))
+            .unwrap_or_default()
+    });
+
+    Ok(entries
+        .iter()
+        .map(|entry| ElfSection {
+            name: names
+                .map(|names| read_name(names, read_u32(entry, 0) as usize))
+                .unwrap_or_default(),
+            section_type: read_u32(entry, 4),
+            address: read_u64(entry, 16),
+            offset: read_u64(entry, 24),
+            size: read_u64(entry, 32),
+        })
+        .collect())
+}
+
+/// The segments of an ELF64 file
+pub fn segments(bytes: &[u8]) -> Result<Vec<ElfSegment>, String> {
+    parse_header(bytes)?;
+    let table_offset = read_u64(bytes, 0x20);
+    let entry_size = read_u16(bytes, 0x36) as usize;
+    let count = read_u16(bytes, 0x38) as usize;
+    if table_offset == 0 || count == 0 {
+        return Ok(Vec::new());
+    }
+    let entries = table(
+        bytes,
+        table_offset,
+        entry_size,
+        count,
+        PROGRAM_HEADER_SIZE,
+        "program",
+    )?;
+
+    Ok(entries
+        .iter()
+        .map(|entry| ElfSegment {
+            segment_type: read_u32(entry, 0),
+            flags: read_u32(entry, 4),
+            offset: read_u64(entry, 8),
+            address: read_u64(entry, 16),
+            file_size: read_u64(entry, 32),
+            memory_size: read_u64(entry, 40),
+        })
+        .collect())
+}
+
+/// The entries of a header table, checked to lie within the file
+fn table<'a>(
+    bytes: &'a [u8],
+    offset: u64,
+    entry_size: usize,
+    count: usize,
+    min_entry_size: usize,
+    kind: &str,
+) -> Result<Vec<&'a [u8]>, String> {
+    if entry_size < min_entry_size {
+        return Err(format!(
+            "ELF {} header size {} is smaller than {} bytes",
+            kind, entry_size, min_entry_size
+        ));
+    }
+    let start = usize::try_from(offset).unwrap_or(usize::MAX);
+    let end = start.saturating_add(entry_size.saturating_mul(count));
+    let table = bytes
+        .get(start..end)
+        .ok_or_else(|| format!("ELF {} header table is past the end of the file", kind))?;
+    Ok(table.chunks_exact(entry_size).collect())
+}
+
+/// A NUL-terminated name from a string table
+fn read_name(names: &[u8], offset: usize) -> String {
+    let name = names.get(offset..).unwrap_or_default();
+    let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+    String::from_utf8_lossy(&name[..end]).into_owned()
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    let mut value = [0u8; 4];
+    value.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(value)
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    let mut value = [0u8; 8];
+    value.copy_from_slice(&bytes[offset..offset + 8]);
+    u64::from_le_bytes(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elf_header(elf_type: u16, machine: u16) -> Vec<u8> {
+        let mut bytes = vec![0u8; ELF64_HEADER_SIZE];
+        bytes[..4].copy_from_slice(ELF_MAGIC);
+        bytes[4] = ELFCLASS64;
+        bytes[5] = ELFDATA2LSB;
+        bytes[6] = 1; // EV_CURRENT
+        bytes[16..18].copy_from_slice(&elf_type.to_le_bytes());
+        bytes[18..20].copy_from_slice(&machine.to_le_bytes());
+        bytes[24..32].copy_from_slice(&0x401000u64.to_le_bytes());
+        bytes
+    }
+
+    /// A header followed by one LOAD segment and the sections
+    /// null, .text and .shstrtab
+    fn elf_with_tables() -> Vec<u8> {
+        let mut bytes = elf_header(ET_EXEC, EM_X86_64);
+        let names = b"\0.text\0.shstrtab\0";
+        let program_offset = bytes.len();
+        let text_offset = program_offset + PROGRAM_HEADER_SIZE;
+        let names_offset = text_offset + 4;
+        let section_offset = names_offset + names.len();
+
+        bytes[0x20..0x28].copy_from_slice(&(program_offset as u64).to_le_bytes());
+        bytes[0x28..0x30].copy_from_slice(&(section_offset as u64).to_le_bytes());
+        bytes[0x36..0x38].copy_from_slice(&(PROGRAM_HEADER_SIZE as u16).to_le_bytes());
+        bytes[0x38..0x3a].copy_from_slice(&1u16.to_le_bytes());
+        bytes[0x3a..0x3c].copy_from_slice(&(SECTION_HEADER_SIZE as u16).to_le_bytes());
+        bytes[0x3c..0x3e].copy_from_slice(&3u16.to_le_bytes());
+        bytes[0x3e..0x40].copy_from_slice(&2u16.to_le_bytes());
+
+        let mut segment = vec![0u8; PROGRAM_HEADER_SIZE];
+        segment[0..4].copy_from_slice(&1u32.to_le_bytes());
+        segment[4..8].copy_from_slice(&(PF_R | PF_X).to_le_bytes());
+        segment[16..24].copy_from_slice(&0x401000u64.to_le_bytes());
+        segment[32..40].copy_from_slice(&(names_offset as u64).to_le_bytes());
+        segment[40..48].copy_from_slice(&(names_offset as u64).to_le_bytes());
+        bytes.extend(segment);
+
+        bytes.extend([0x90, 0x90, 0xc3, 0x00]);
+        bytes.extend(names);
+
+        let section = |name: u32, section_type: u32, offset: usize, size: usize| {
+            let mut entry = vec![0u8; SECTION_HEADER_SIZE];
+            entry[0..4].copy_from_slice(&name.to_le_bytes());
+            entry[4..8].copy_from_slice(&section_type.to_le_bytes());
+            entry[24..32].copy_from_slice(&(offset as u64).to_le_bytes());
+            entry[32..40].copy_from_slice(&(size as u64).to_le_bytes());
+            entry
+        };
+        bytes.extend(section(0, SHT_NULL, 0, 0));
+        bytes.extend(section(1, 1, text_offset, 4));
+        bytes.extend(section(7, 3, names_offset, names.len()));
+        bytes
+    }
+
+    #[test]
+    fn test_sections_and_segments() {
+        let bytes = elf_with_tables();
+
+        let found = sections(&bytes).expect("Failed to read sections");
+        let names: Vec<&str> = found.iter().map(|section| section.name.as_str()).collect();
+        assert_eq!(names, ["", ".text", ".shstrtab"]);
+        assert_eq!(
+            found[1].offset,
+            (ELF64_HEADER_SIZE + PROGRAM_HEADER_SIZE) as u64
+        );
+        assert_eq!(found[1].size, 4);
+        assert!(!found[0].has_file_data());
+        assert!(found[1].has_file_data());
+
+        let loads = segments(&bytes).expect("Failed to read segments");
+        assert_eq!(loads.len(), 1);
+        assert_eq!(loads[0].type_name(), "LOAD");
+        assert_eq!(loads[0].flags_text(), "R E");
+        assert_eq!(loads[0].address, 0x401000);
+
+        // A header alone has no tables
+        assert!(sections(&elf_header(ET_EXEC, EM_X86_64))
+            .unwrap()
+            .is_empty());
+
+        // Tables past the end of the file are reported, not read
+        let result = sections(&bytes[..bytes.len() - 1]);
+        assert!(result.unwrap_err().contains("past the end"));
+    }
+
+    #[test]
+    fn test_validate_payload_executable() {
+        let header = validate_payload(&elf_header(ET_EXEC, EM_X86_64)).expect("Should be valid");
+        assert_eq!(header.elf_type, ET_EXEC);
+        assert_eq!(header.machine, EM_X86_64);
+        assert_eq!(header.entry, 0x401000);
+        assert_eq!(header.type_name(), "executable");
+
+        assert!(validate_payload(&elf_header(ET_DYN, EM_X86_64)).is_ok());
+    }
+
+    #[test]
+    fn test_validate_payload_rejects_non_elf() {
+        let result = validate_payload(b"just some raw payload bytes");
+        assert_eq!(result.unwrap_err(), "Not an ELF file");
+
+        let result = validate_payload(b"");
+        assert_eq!(result.unwrap_err(), "Not an ELF file");
+    }
+
+    #[test]
+    fn test_validate_payload_rejects_truncated() {
+        let bytes = elf_header(ET_EXEC, EM_X86_64);
+        let result = validate_payload(&bytes[..20]);
+        assert!(result.unwrap_err().contains("truncated"));
+    }
+
+    #[test]
+    fn test_validate_payload_rejects_wrong_target() {
+        // AArch64
+        let result = validate_payload(&elf_header(ET_EXEC, 183));
+        assert!(result.unwrap_err().contains("not x86-64"));
+
+        // Relocatable object
+        let result = validate_payload(&elf_header(1, EM_X86_64));
+        assert!(result.unwrap_err().contains("'relocatable'"));
+
+        let mut bytes = elf_header(ET_EXEC, EM_X86_64);
+        bytes[4] = 1; // ELFCLASS32
+        assert!(validate_payload(&bytes).unwrap_err().contains("64-bit"));
+    }
+}
@@ -1,13 +1,23 @@
-use crate::config::{
-    Config, DEFAULT_AUTO_SAVE_ENABLED, DEFAULT_FILE_PATH, DEFAULT_IP, DEFAULT_PORT,
-};
+use crate::config::Config;
+#[cfg(feature = "gui")]
+use crate::config::{ConfigFormat, DataFile};
 use crate::network::FileTransfer;
-use crate::ui::InjectionStatus;
+#[cfg(feature = "gui")]
+use crate::recent::Recent;
+use crate::status::InjectionStatus;
 use std::sync::mpsc;
+use tokio::sync::oneshot;
 
+/// Injections stop as soon as `()` is sent on their cancel channel; dropping
+/// its sender without sending lets the injection run to the end
 pub fn create_inject_fn(
-) -> impl Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>) + Send + 'static {
-    |ip: &str, port: &str, file_path: &str, sender: mpsc::Sender<InjectionStatus>| {
+) -> impl Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>, oneshot::Receiver<()>) + Send + 'static
+{
+    |ip: &str,
+     port: &str,
+     file_path: &str,
+     sender: mpsc::Sender<InjectionStatus>,
+     cancel: oneshot::Receiver<()>| {
         let ip = ip.to_string();
         let port = port.to_string();
         let file_path = file_path.to_string();
@@ -28,51 +38,74 @@ pub fn create_inject_fn(
 
             // Execute the async task
             rt.block_on(async move {
-                // Extract filename for display
-                let filename = std::path::Path::new(&file_path)
-                    .file_name()
-                    .and_then(|name| name.to_str())
-                    .unwrap_or("unknown");
-
-                // Send status update: Reading file
-                let _ = sender.send(InjectionStatus::InProgress(format!(
-                    "Reading file '{}'...",
-                    filename
-                )));
-
-                // Small delay to show the status
-                tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
-
-                let file_transfer = FileTransfer::new(ip.clone(), port.clone(), file_path.clone());
-
-                // Send status update: Connecting
-                let _ = sender.send(InjectionStatus::InProgress(format!(
-                    "Connecting to {}:{}...",
-                    ip, port
-                )));
-
-                // Small delay to show the status
-                tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
-
-                // Send status update: Sending data
-                let _ = sender.send(InjectionStatus::InProgress(format!(
-                    "Sending '{}' to {}:{}...",
-                    filename, ip, port
-                )));
-
-                match file_transfer.send_file().await {
-                    Ok(bytes_sent) => {
-                        let _ = sender.send(InjectionStatus::Success(bytes_sent));
-                    }
-                    Err(e) => {
-                        let _ = sender.send(InjectionStatus::Error(e));
-                    }
+                // Dropping the injection closes its connection
+                tokio::select! {
+                    _ = inject(&ip, &port, &file_path, &sender) => {}
+                    Ok(()) = cancel => {}
                 }
             });
         });
     }
 }
 
+async fn inject(ip: &str, port: &str, file_path: &str, sender: &mpsc::Sender<InjectionStatus>) {
+    // Extract filename for display
+    let filename = std::path::Path::new(file_path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("unknown");
+
+    // Send status update: Reading file
+    let _ = sender.send(InjectionStatus::InProgress(format!(
+        "Reading file '{}'...",
+        filename
+    )));
+
+    // Small delay to show the status
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+
+    // Send status update: Connecting
+    let _ = sender.send(InjectionStatus::InProgress(format!(
+        "Connecting to {}:{}...",
+        ip, port
+    )));
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(200)).await;
+
+    // The CRC32 costs next to nothing beside the SHA-256
+    let file_transfer =
+        FileTransfer::new(ip.to_string(), port.to_string(), file_path.to_string()).with_crc32(true);
+
+    // Send status update: Sending data
+    let _ = sender.send(InjectionStatus::InProgress(format!(
+        "Sending '{}' to {}:{}...",
+        filename, ip, port
+    )));
+
+    match file_transfer.send_file_with_checksums().await {
+        Ok(report) => {
+            let _ = sender.send(InjectionStatus::Success(
+                report.bytes_sent,
+                Some(report.checksums),
+            ));
+        }
+        Err(e) => {
+            let _ = sender.send(InjectionStatus::Error(e));
+        }
+    }
+}
+
+/// Folder config dialogs open in: where a payload was last browsed from,
+/// otherwise the current directory
+#[cfg(feature = "gui")]
+fn dialog_directory() -> Option<std::path::PathBuf> {
+    Recent::load_or_default()
+        .last_directory
+        .filter(|directory| directory.is_dir())
+        .or_else(|| std::env::current_dir().ok())
+}
+
+#[cfg(feature = "gui")]
 pub fn create_save_config_fn(
 ) -> impl Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>) + Send + 'static {
     |ip: &str, port: &str, file_path: &str, sender: mpsc::Sender<InjectionStatus>| {
@@ -87,14 +120,15 @@ pub fn create_save_config_fn(
                 "Preparing to save config...".to_string(),
             ));
 
-            // Create file dialog
+            // Create file dialog; the chosen extension sets the format
             let mut dialog = rfd::FileDialog::new()
                 .add_filter("JSON files", &["json"])
+                .add_filter("TOML files", &["toml"])
+                .add_filter("YAML files", &["yaml", "yml"])
                 .set_file_name("config.json");
 
-            // Set current directory as default
-            if let Ok(current_dir) = std::env::current_dir() {
-                dialog = dialog.set_directory(&current_dir);
+            if let Some(directory) = dialog_directory() {
+                dialog = dialog.set_directory(&directory);
             }
 
             if let Some(path) = dialog.save_file() {
@@ -128,6 +162,7 @@ pub fn create_save_config_fn(
     }
 }
 
+#[cfg(feature = "gui")]
 pub fn create_load_config_fn() -> impl Fn(mpsc::Sender<InjectionStatus>) + Send + 'static {
     |sender: mpsc::Sender<InjectionStatus>| {
         // Spawn the load config task in a separate thread
@@ -138,11 +173,11 @@ pub fn create_load_config_fn() -> impl Fn(mpsc::Sender<InjectionStatus>) + Send
             ));
 
             // Create file dialog for loading
-            let mut dialog = rfd::FileDialog::new().add_filter("JSON files", &["json"]);
+            let mut dialog =
+                rfd::FileDialog::new().add_filter("Config files", ConfigFormat::EXTENSIONS);
 
-            // Set current directory as default
-            if let Ok(current_dir) = std::env::current_dir() {
-                dialog = dialog.set_directory(&current_dir);
+            if let Some(directory) = dialog_directory() {
+                dialog = dialog.set_directory(&directory);
             }
 
             if let Some(path) = dialog.pick_file() {
@@ -192,36 +227,23 @@ pub fn create_load_config_fn() -> impl Fn(mpsc::Sender<InjectionStatus>) + Send
     }
 }
 
-pub fn create_auto_save_fn() -> impl Fn(&str, &str, &str) + Send + 'static {
-    |ip: &str, port: &str, file_path: &str| {
+/// Auto-save the given fields. `None` leaves a field as the config file has
+/// it, which may be not set at all.
+pub fn create_auto_save_fn() -> impl Fn(Option<&str>, Option<&str>, Option<&str>) + Send + 'static {
+    |ip: Option<&str>, port: Option<&str>, file_path: Option<&str>| {
         // Only auto-save if a config file already exists (meaning auto-save is enabled)
         if Config::config_file_exists() {
-            let current_config = Config::load_or_default();
-
-            // Create config with current values and preserve auto-save preference
-            let config = Config::new_with_auto_save(
-                ip.to_string(),
-                port.to_string(),
-                file_path.to_string(),
-                current_config.auto_save_enabled,
-            );
-
-            // Ensure the save operation completes successfully
-            let _ = config.auto_save();
-
-            // In test mode, add a small verification to ensure the save worked
-            #[cfg(test)]
-            {
-                // Verify the save worked by reading it back
-                let verification_config = Config::load_or_default();
-                if verification_config.ip != ip
-                    || verification_config.port != port
-                    || verification_config.file_path != file_path
-                {
-                    // If verification fails, try saving again
-                    let _ = config.auto_save();
+            let _ = Config::update_auto_save(|layer| {
+                if let Some(ip) = ip {
+                    layer.ip = Some(ip.to_string());
                 }
-            }
+                if let Some(port) = port {
+                    layer.port = Some(port.to_string());
+                }
+                if let Some(file_path) = file_path {
+                    layer.file_path = Some(file_path.to_string());
+                }
+            });
         }
     }
 }
@@ -229,15 +251,9 @@ pub fn create_auto_save_fn() -> impl Fn(&str, &str, &str) + Send + 'static {
 pub fn create_auto_save_preference_fn() -> impl Fn(bool) + Send + 'static {
     |auto_save_enabled: bool| {
         if auto_save_enabled {
-            // Only save if auto-save is being enabled
-            let current_config = Config::load_or_default();
-            let config = Config::new_with_auto_save(
-                current_config.ip,
-                current_config.port,
-                current_config.file_path,
-                true,
-            );
-            let _ = config.auto_save();
+            // Only save if auto-save is being enabled. Values are only added
+            // once they are auto-saved, so lower layers are not shadowed.
+            let _ = Config::update_auto_save(|layer| layer.auto_save_enabled = Some(true));
         } else {
             // If auto-save is being disabled, delete the config file if it exists
             if Config::config_file_exists() {
@@ -255,45 +271,86 @@ pub fn create_reset_fn() -> impl Fn(&str, &str, &str, mpsc::Sender<InjectionStat
     }
 }
 
-pub fn load_startup_config() -> (String, String, String, bool) {
-    if Config::config_file_exists() {
-        let config = Config::load_or_default();
-        (
-            config.ip,
-            config.port,
-            config.file_path,
-            config.auto_save_enabled,
-        )
-    } else {
-        // No config file exists, return defaults with auto-save disabled
-        (
-            DEFAULT_IP.to_string(),
-            DEFAULT_PORT.to_string(),
-            DEFAULT_FILE_PATH.to_string(),
-            DEFAULT_AUTO_SAVE_ENABLED,
-        )
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::DEFAULT_IP;
+    use crate::settings::{Overrides, Settings, Source};
+    use std::time::Duration;
+
+    /// Settings from the test user config alone, so the developer's
+    /// environment and any project file above the checkout do not leak in
+    fn user_settings() -> Settings {
+        Settings::resolve(
+            None,
+            &Config::default_auto_save_path(),
+            None,
+            |_| None,
+            &Overrides::default(),
+        )
+    }
 
     #[test]
     fn test_handler_functions_creation() {
         // Test that all handler functions can be created without panicking
         let _inject_fn = create_inject_fn();
-        let _save_config_fn = create_save_config_fn();
-        let _load_config_fn = create_load_config_fn();
+        #[cfg(feature = "gui")]
+        {
+            let _save_config_fn = create_save_config_fn();
+            let _load_config_fn = create_load_config_fn();
+        }
         let _auto_save_fn = create_auto_save_fn();
         let _auto_save_preference_fn = create_auto_save_preference_fn();
 
-        // Test startup config loading (this will create or load existing config)
-        let startup_config = load_startup_config();
-        assert!(startup_config.0.len() > 0); // IP should not be empty
-        assert!(startup_config.1.len() > 0); // Port should not be empty
-                                             // File path can be empty in defaults
-                                             // Auto-save enabled is a boolean (can be true or false)
+        // Test startup settings loading (this will load an existing config, if any)
+        let settings = user_settings();
+        assert!(!settings.ip.value.is_empty()); // IP should not be empty
+        assert!(!settings.port.value.is_empty()); // Port should not be empty
+                                                  // File path can be empty in defaults
+                                                  // Auto-save enabled is a boolean (can be true or false)
+    }
+
+    #[test]
+    fn test_inject_stops_when_cancelled() {
+        use crate::network::mock::{self, MockOptions};
+
+        let (received_sender, received) = mpsc::channel();
+        let mock = mock::start_mock(MockOptions::local(), received_sender)
+            .expect("Failed to start mock loader");
+        let payload = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+        std::fs::write(&payload, b"payload").expect("Failed to write payload");
+
+        // Cancelling before it connects stops the injection
+        let (sender, receiver) = mpsc::channel();
+        let (cancel, cancelled) = oneshot::channel();
+        create_inject_fn()(
+            "127.0.0.1",
+            &mock.local_addr().port().to_string(),
+            payload.path().to_str().unwrap(),
+            sender,
+            cancelled,
+        );
+        cancel.send(()).expect("Injection already finished");
+
+        assert!(received.recv_timeout(Duration::from_millis(1500)).is_err());
+        // Nothing is reported after the cancel, and no status repeats
+        let statuses: Vec<InjectionStatus> = receiver.iter().collect();
+        assert!(statuses.len() <= 1);
+
+        // Without a cancel the injection runs to the end
+        let (sender, receiver) = mpsc::channel();
+        let (_, cancelled) = oneshot::channel();
+        create_inject_fn()(
+            "127.0.0.1",
+            &mock.local_addr().port().to_string(),
+            payload.path().to_str().unwrap(),
+            sender,
+            cancelled,
+        );
+        let statuses: Vec<InjectionStatus> = receiver.iter().collect();
+        assert_eq!(statuses.len(), 4);
+        assert!(matches!(statuses[3], InjectionStatus::Success(7, _)));
+        assert!(received.recv_timeout(Duration::from_secs(5)).is_ok());
     }
 
     #[test]
@@ -311,7 +368,11 @@ mod tests {
 
         // Enable auto-save first, then test auto-saving some values
         auto_save_preference_fn(true);
-        auto_save_fn("10.0.0.100", "3000", "/test/auto_save.bin");
+        auto_save_fn(
+            Some("10.0.0.100"),
+            Some("3000"),
+            Some("/test/auto_save.bin"),
+        );
 
         // Load it back to verify it was saved
         let config = Config::load_or_default();
@@ -324,9 +385,9 @@ mod tests {
             if original_config.auto_save_enabled {
                 auto_save_preference_fn(true);
                 auto_save_fn(
-                    &original_config.ip,
-                    &original_config.port,
-                    &original_config.file_path,
+                    Some(&original_config.ip),
+                    Some(&original_config.port),
+                    Some(&original_config.file_path),
                 );
             } else {
                 auto_save_preference_fn(false);
@@ -336,6 +397,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_auto_save_leaves_other_layers_showing() {
+        let auto_save_fn = create_auto_save_fn();
+        let auto_save_preference_fn = create_auto_save_preference_fn();
+        let original = std::fs::read(Config::default_auto_save_path()).ok();
+        let _ = std::fs::remove_file(Config::default_auto_save_path());
+
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let shared = temp_dir.path().join("shared_config.json");
+        std::fs::write(&shared, r#"{"ip": "10.0.0.2", "port": "9021"}"#)
+            .expect("Failed to write shared config");
+
+        // Turning auto-save on and saving one value only adds those to the user config
+        auto_save_preference_fn(true);
+        auto_save_fn(None, Some("9020"), None);
+        let settings = Settings::resolve(
+            Some(&shared),
+            &Config::default_auto_save_path(),
+            None,
+            |_| None,
+            &Overrides::default(),
+        );
+        let user_file = Config::default_auto_save_path();
+        match original {
+            Some(content) => std::fs::write(&user_file, content).expect("Failed to restore"),
+            None => std::fs::remove_file(&user_file).expect("Failed to clean up"),
+        }
+
+        assert!(settings.auto_save_enabled.value);
+        assert_eq!(settings.ip.value, "10.0.0.2");
+        assert_eq!(settings.ip.source, Source::SharedFile(shared));
+        assert_eq!(settings.port.value, "9020");
+        assert_eq!(settings.port.source, Source::UserFile(user_file));
+        assert_eq!(settings.file_path.source, Source::Default);
+    }
+
     #[test]
     fn test_auto_save_edge_cases() {
         let auto_save_fn = create_auto_save_fn();
@@ -355,17 +452,21 @@ mod tests {
         // Verify auto-save is enabled
         assert!(Config::config_file_exists());
         let initial_config = Config::load_or_default();
-        assert_eq!(initial_config.auto_save_enabled, true);
+        assert!(initial_config.auto_save_enabled);
 
         // Test with empty values
-        auto_save_fn("", "", "");
+        auto_save_fn(Some(""), Some(""), Some(""));
         let config = Config::load_or_default();
         assert_eq!(config.ip, "");
         assert_eq!(config.port, "");
         assert_eq!(config.file_path, "");
 
         // Test with maximum port value
-        auto_save_fn("255.255.255.255", "65535", "/max/test.bin");
+        auto_save_fn(
+            Some("255.255.255.255"),
+            Some("65535"),
+            Some("/max/test.bin"),
+        );
         let config = Config::load_or_default();
         assert_eq!(config.ip, "255.255.255.255");
         assert_eq!(config.port, "65535");
@@ -376,9 +477,9 @@ mod tests {
             if original_config.auto_save_enabled {
                 auto_save_preference_fn(true);
                 auto_save_fn(
-                    &original_config.ip,
-                    &original_config.port,
-                    &original_config.file_path,
+                    Some(&original_config.ip),
+                    Some(&original_config.port),
+                    Some(&original_config.file_path),
                 );
             } else {
                 auto_save_preference_fn(false);
@@ -405,7 +506,11 @@ mod tests {
         auto_save_preference_fn(true);
 
         // Test with special characters in file path
-        auto_save_fn("127.0.0.1", "8080", "/path with spaces/file-name_test.txt");
+        auto_save_fn(
+            Some("127.0.0.1"),
+            Some("8080"),
+            Some("/path with spaces/file-name_test.txt"),
+        );
 
         let config = Config::load_or_default();
         assert_eq!(config.ip, "127.0.0.1");
@@ -417,9 +522,9 @@ mod tests {
             if original_config.auto_save_enabled {
                 auto_save_preference_fn(true);
                 auto_save_fn(
-                    &original_config.ip,
-                    &original_config.port,
-                    &original_config.file_path,
+                    Some(&original_config.ip),
+                    Some(&original_config.port),
+                    Some(&original_config.file_path),
                 );
             } else {
                 auto_save_preference_fn(false);
@@ -444,30 +549,38 @@ mod tests {
 
         // Enable auto-save first, then save some specific values
         auto_save_preference_fn(true);
-        auto_save_fn("172.16.0.1", "5555", "/consistent/test.bin");
+        auto_save_fn(
+            Some("172.16.0.1"),
+            Some("5555"),
+            Some("/consistent/test.bin"),
+        );
 
-        // Verify the config was actually saved correctly before testing load_startup_config
+        // Verify the config was actually saved correctly before resolving the settings
         let saved_config = Config::load_or_default();
         assert_eq!(saved_config.ip, "172.16.0.1");
         assert_eq!(saved_config.port, "5555");
         assert_eq!(saved_config.file_path, "/consistent/test.bin");
-        assert_eq!(saved_config.auto_save_enabled, true);
-
-        // Load startup config should return the same values
-        let startup_config = load_startup_config();
-        assert_eq!(startup_config.0, "172.16.0.1");
-        assert_eq!(startup_config.1, "5555");
-        assert_eq!(startup_config.2, "/consistent/test.bin");
-        assert_eq!(startup_config.3, true); // Auto-save should be enabled
+        assert!(saved_config.auto_save_enabled);
+
+        // Startup settings should have the same values, from the user config
+        let settings = user_settings();
+        assert_eq!(settings.ip.value, "172.16.0.1");
+        assert_eq!(settings.port.value, "5555");
+        assert_eq!(settings.file_path.value, "/consistent/test.bin");
+        assert!(settings.auto_save_enabled.value); // Auto-save should be enabled
+        assert_eq!(
+            settings.ip.source,
+            Source::UserFile(Config::default_auto_save_path())
+        );
 
         // Restore original state
         if let Some(original_config) = original_config {
             if original_config.auto_save_enabled {
                 auto_save_preference_fn(true);
                 auto_save_fn(
-                    &original_config.ip,
-                    &original_config.port,
-                    &original_config.file_path,
+                    Some(&original_config.ip),
+                    Some(&original_config.port),
+                    Some(&original_config.file_path),
                 );
             } else {
                 auto_save_preference_fn(false);
@@ -494,7 +607,7 @@ mod tests {
         auto_save_preference_fn(true);
         assert!(Config::config_file_exists());
         let config = Config::load_or_default();
-        assert_eq!(config.auto_save_enabled, true);
+        assert!(config.auto_save_enabled);
 
         // Test setting auto-save to false (should delete config file)
         auto_save_preference_fn(false);
@@ -510,6 +623,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_startup_config_error_reported() {
+        let config_path = Config::default_auto_save_path();
+        let original = std::fs::read(&config_path).ok();
+
+        std::fs::write(&config_path, "{ not valid json").expect("Failed to write config");
+
+        // Startup falls back to defaults but keeps the broken file and reports it
+        let settings = user_settings();
+        assert_eq!(settings.ip.value, DEFAULT_IP);
+        assert!(!settings.auto_save_enabled.value);
+        let error = settings.startup_error().expect("Expected a startup error");
+        assert!(error.contains("Failed to parse config file"));
+        assert!(config_path.exists());
+
+        match original {
+            Some(content) => std::fs::write(&config_path, content).expect("Failed to restore"),
+            None => std::fs::remove_file(&config_path).expect("Failed to clean up"),
+        }
+        assert!(user_settings().startup_error().is_none());
+    }
+
     // This test should run last to clean up any test config files
     // The test name starts with 'z' to ensure it runs after other tests alphabetically
     #[test]
@@ -0,0 +1,228 @@
+use crate::checksum::short_hash;
+use crate::config::{Config, DataFile};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const HISTORY_FILE_NAME: &str = "history.json";
+
+/// Oldest entries are dropped beyond this many
+pub const MAX_HISTORY_ENTRIES: usize = 200;
+
+// Serializes read-modify-write cycles on the history file within this process
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+/// What started an injection
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Trigger {
+    Manual,
+    Watch,
+    Api,
+    Cli,   // One-off `send` from the command line
+    Queue, // A job of the injection queue
+}
+
+/// One completed or failed injection
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub timestamp: u64, // Seconds since the Unix epoch
+    pub trigger: Trigger,
+    pub ip: String,
+    pub port: String,
+    pub file_path: String,
+    pub bytes_sent: Option<usize>,
+    #[serde(default)]
+    pub sha256: Option<String>, // Of the bytes sent
+    pub error: Option<String>,
+}
+
+impl HistoryEntry {
+    /// Entry stamped with the current time and no result yet
+    pub fn new(trigger: Trigger, ip: &str, port: &str, file_path: &str) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            trigger,
+            ip: ip.to_string(),
+            port: port.to_string(),
+            file_path: file_path.to_string(),
+            bytes_sent: None,
+            sha256: None,
+            error: None,
+        }
+    }
+
+    pub fn succeeded(mut self, bytes_sent: usize, sha256: Option<&str>) -> Self {
+        self.bytes_sent = Some(bytes_sent);
+        self.sha256 = sha256.map(str::to_string);
+        self
+    }
+
+    pub fn failed(mut self, error: &str) -> Self {
+        self.error = Some(error.to_string());
+        self
+    }
+
+    /// Local time of the entry, e.g. `2025-06-14 18:03:27`
+    pub fn time_text(&self) -> String {
+        format_timestamp(self.timestamp)
+    }
+
+    pub fn summary(&self) -> String {
+        let filename = Path::new(&self.file_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&self.file_path);
+        let result = match (&self.bytes_sent, &self.error) {
+            (_, Some(error)) => format!("failed: {}", error),
+            (Some(bytes), None) => match &self.sha256 {
+                Some(sha256) => format!("{} bytes, sha256 {}", bytes, short_hash(sha256)),
+                None => format!("{} bytes", bytes),
+            },
+            (None, None) => "no result".to_string(),
+        };
+        format!(
+            "{} [{:?}] '{}' -> {}:{}, {}",
+            self.time_text(),
+            self.trigger,
+            filename,
+            self.ip,
+            self.port,
+            result
+        )
+    }
+}
+
+/// Format seconds since the Unix epoch as local time
+pub fn format_timestamp(timestamp: u64) -> String {
+    use chrono::TimeZone;
+
+    match chrono::Local.timestamp_opt(timestamp as i64, 0) {
+        chrono::LocalResult::Single(time) | chrono::LocalResult::Ambiguous(time, _) => {
+            time.format("%Y-%m-%d %H:%M:%S").to_string()
+        }
+        chrono::LocalResult::None => timestamp.to_string(),
+    }
+}
+
+/// Injection history, oldest entry first
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+}
+
+impl DataFile for History {
+    const FILE_NAME: &'static str = HISTORY_FILE_NAME;
+    const WHAT: &'static str = "history file";
+}
+
+impl History {
+    /// Append an entry to the default history file
+    pub fn record(entry: HistoryEntry) -> Result<(), String> {
+        Config::ensure_config_dir()?;
+        Self::record_to_file(Self::default_path(), entry)
+    }
+
+    pub fn record_to_file<P: AsRef<Path>>(path: P, entry: HistoryEntry) -> Result<(), String> {
+        let _guard = HISTORY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let path = path.as_ref();
+        let mut history = if path.exists() {
+            Self::load_from_file(path)?
+        } else {
+            Self::default()
+        };
+        history.push(entry);
+        history.save_to_file(path)
+    }
+
+    pub fn push(&mut self, entry: HistoryEntry) {
+        self.entries.push(entry);
+        if self.entries.len() > MAX_HISTORY_ENTRIES {
+            let excess = self.entries.len() - MAX_HISTORY_ENTRIES;
+            self.entries.drain(..excess);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_results() {
+        let entry = HistoryEntry::new(Trigger::Watch, "10.0.0.2", "9021", "/build/payload.elf");
+        assert!(entry.timestamp > 0);
+        assert_eq!(entry.bytes_sent, None);
+
+        let success = entry.clone().succeeded(4096, None);
+        assert_eq!(success.bytes_sent, Some(4096));
+        assert!(success
+            .summary()
+            .contains("'payload.elf' -> 10.0.0.2:9021, 4096 bytes"));
+
+        let hashed = entry.clone().succeeded(4096, Some("ba7816bf8f01cfea4141"));
+        assert_eq!(hashed.sha256.as_deref(), Some("ba7816bf8f01cfea4141"));
+        assert!(hashed
+            .summary()
+            .ends_with("4096 bytes, sha256 ba7816bf8f01"));
+        assert!(success.summary().contains("[Watch]"));
+
+        let failure = entry.failed("Connection refused");
+        assert!(failure.summary().contains("failed: Connection refused"));
+    }
+
+    #[test]
+    fn test_record_and_load() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join(HISTORY_FILE_NAME);
+
+        let first = HistoryEntry::new(Trigger::Manual, "10.0.0.2", "9021", "/a.elf")
+            .succeeded(1, Some("ab"));
+        let second = HistoryEntry::new(Trigger::Watch, "10.0.0.2", "9021", "/b.elf").failed("x");
+        History::record_to_file(&path, first.clone()).expect("Failed to record");
+        History::record_to_file(&path, second.clone()).expect("Failed to record");
+
+        let history = History::load_from_file(&path).expect("Failed to load");
+        assert_eq!(history.entries, vec![first, second]);
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let mut history = History::default();
+        for i in 0..MAX_HISTORY_ENTRIES + 5 {
+            history.push(HistoryEntry::new(
+                Trigger::Manual,
+                "10.0.0.2",
+                "9021",
+                &format!("/payload{}.elf", i),
+            ));
+        }
+
+        assert_eq!(history.entries.len(), MAX_HISTORY_ENTRIES);
+        assert_eq!(history.entries[0].file_path, "/payload5.elf");
+    }
+
+    #[test]
+    fn test_load_history_without_hashes() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join(HISTORY_FILE_NAME);
+        let json = r#"{"entries": [{"timestamp": 1, "trigger": "manual", "ip": "10.0.0.2",
+            "port": "9021", "file_path": "/a.elf", "bytes_sent": 5, "error": null}]}"#;
+        std::fs::write(&path, json).expect("Failed to write history");
+
+        let history = History::load_from_file(&path).expect("Failed to load");
+        assert_eq!(history.entries[0].bytes_sent, Some(5));
+        assert_eq!(history.entries[0].sha256, None);
+    }
+
+    #[test]
+    fn test_load_missing_history() {
+        let result = History::load_from_file("/path/that/does/not/exist/history.json");
+        assert!(result.unwrap_err().contains("Failed to read history file"));
+    }
+}
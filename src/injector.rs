@@ -0,0 +1,463 @@
+use crate::archive::{ArchivePath, EntryReader, CHUNK_SIZE};
+use crate::checksum::ChecksumHasher;
+use crate::network::{TransferReport, STDIN_PATH};
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// A payload loader listening on a console
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Target {
+    pub host: String, // IP address or host name, IPv6 without brackets
+    pub port: u16,
+}
+
+impl Target {
+    pub fn new(host: impl Into<String>, port: u16) -> Self {
+        Self {
+            host: host.into(),
+            port,
+        }
+    }
+
+    /// Target from a host and a port that is still text, e.g. from a config
+    pub fn from_parts(host: &str, port: &str) -> Result<Self, String> {
+        let port = port
+            .trim()
+            .parse::<u16>()
+            .map_err(|_| format!("Invalid port number: {}", port))?;
+        Ok(Self::new(host.trim(), port))
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// Parses `host:port`, with IPv6 addresses in brackets (`[::1]:9021`)
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (host, port) = text
+            .rsplit_once(':')
+            .ok_or_else(|| format!("Missing port in target '{}'", text))?;
+        let host = host
+            .strip_prefix('[')
+            .and_then(|host| host.strip_suffix(']'))
+            .unwrap_or(host);
+        if host.is_empty() {
+            return Err(format!("Missing host in target '{}'", text));
+        }
+        Self::from_parts(host, port)
+    }
+}
+
+/// Where the bytes of a payload come from
+pub enum Payload {
+    /// A file, or a file inside an archive written as `release.zip!/payload.elf`
+    File(PathBuf),
+    Bytes(Vec<u8>),
+    Stdin,
+    /// Anything else that can be read, e.g. the output of another tool
+    Reader(Box<dyn AsyncRead + Unpin + Send>),
+}
+
+impl Payload {
+    pub fn file(path: impl Into<PathBuf>) -> Self {
+        Payload::File(path.into())
+    }
+
+    pub fn bytes(bytes: impl Into<Vec<u8>>) -> Self {
+        Payload::Bytes(bytes.into())
+    }
+
+    pub fn reader<R: AsyncRead + Unpin + Send + 'static>(reader: R) -> Self {
+        Payload::Reader(Box::new(reader))
+    }
+
+    /// Payload for a command-line style path, where `-` means standard input
+    pub fn from_arg(path: &str) -> Self {
+        match path {
+            STDIN_PATH => Payload::Stdin,
+            path => Payload::file(path),
+        }
+    }
+
+    /// Describes the payload in error messages, e.g. `file 'payload.elf'`
+    fn description(&self) -> String {
+        match self {
+            Payload::File(path) => format!("file '{}'", path.display()),
+            Payload::Bytes(_) | Payload::Reader(_) => "payload".to_string(),
+            Payload::Stdin => "standard input".to_string(),
+        }
+    }
+
+    /// Open the payload for reading, along with its size when it is known
+    async fn open(self) -> Result<(Box<dyn AsyncRead + Unpin + Send>, Option<usize>), String> {
+        match self {
+            Payload::File(path) => {
+                if let Some(archive_path) = path.to_str().and_then(ArchivePath::parse) {
+                    let entry = EntryReader::open(&archive_path).await?;
+                    let size = entry.size() as usize;
+                    return Ok((Box::new(entry), Some(size)));
+                }
+
+                let file = tokio::fs::File::open(&path)
+                    .await
+                    .map_err(|e| format!("Failed to open file '{}': {}", path.display(), e))?;
+                let size = file.metadata().await.ok().map(|m| m.len() as usize);
+                Ok((Box::new(file), size))
+            }
+            Payload::Bytes(bytes) => {
+                let size = bytes.len();
+                Ok((Box::new(std::io::Cursor::new(bytes)), Some(size)))
+            }
+            Payload::Stdin => Ok((Box::new(tokio::io::stdin()), None)),
+            Payload::Reader(reader) => Ok((reader, None)),
+        }
+    }
+}
+
+impl fmt::Debug for Payload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Payload::File(path) => f.debug_tuple("File").field(path).finish(),
+            Payload::Bytes(bytes) => write!(f, "Bytes({} bytes)", bytes.len()),
+            Payload::Stdin => write!(f, "Stdin"),
+            Payload::Reader(_) => write!(f, "Reader"),
+        }
+    }
+}
+
+/// How far a transfer has got, reported after every chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub bytes_sent: usize,
+    pub total: Option<usize>, // Unknown for stdin and readers
+}
+
+impl Progress {
+    /// Fraction sent between 0 and 1, if the total is known
+    pub fn fraction(&self) -> Option<f32> {
+        match self.total {
+            Some(0) => Some(1.0),
+            Some(total) => Some(self.bytes_sent as f32 / total as f32),
+            None => None,
+        }
+    }
+}
+
+type ProgressFn = Arc<dyn Fn(Progress) + Send + Sync>;
+
+/// Sends payloads to targets. Built with the `with_` methods; every setting
+/// has a default, so `Injector::new()` is ready to use.
+#[derive(Clone)]
+pub struct Injector {
+    connect_timeout: Duration,
+    write_timeout: Duration,
+    retries: u32,
+    retry_delay: Duration,
+    crc32: bool,
+    progress: Option<ProgressFn>,
+}
+
+impl Default for Injector {
+    fn default() -> Self {
+        Self {
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            write_timeout: DEFAULT_WRITE_TIMEOUT,
+            retries: 0,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            crc32: false,
+            progress: None,
+        }
+    }
+}
+
+impl fmt::Debug for Injector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Injector")
+            .field("connect_timeout", &self.connect_timeout)
+            .field("write_timeout", &self.write_timeout)
+            .field("retries", &self.retries)
+            .field("retry_delay", &self.retry_delay)
+            .field("crc32", &self.crc32)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+impl Injector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give up on a connection attempt after `timeout`
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Fail when the target stops accepting data for `timeout`
+    pub fn with_write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = timeout;
+        self
+    }
+
+    /// Try to connect up to `retries` more times, waiting `delay` in between.
+    /// Only connecting is retried: once data has been sent the loader may
+    /// already be running part of the payload, so a send is never repeated.
+    pub fn with_retries(mut self, retries: u32, delay: Duration) -> Self {
+        self.retries = retries;
+        self.retry_delay = delay;
+        self
+    }
+
+    /// Also compute a CRC32 of the sent bytes
+    pub fn with_crc32(mut self, crc32: bool) -> Self {
+        self.crc32 = crc32;
+        self
+    }
+
+    /// Call `progress` after every chunk that was sent
+    pub fn with_progress(mut self, progress: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(progress));
+        self
+    }
+
+    /// Send `payload` to `target`, hashing exactly the bytes that were sent
+    pub async fn inject(
+        &self,
+        target: &Target,
+        payload: Payload,
+    ) -> Result<TransferReport, String> {
+        let name = payload.description();
+        let (mut source, total) = payload.open().await?;
+        let mut stream = self.connect(target).await?;
+
+        let mut hasher = ChecksumHasher::new(self.crc32);
+        let mut buffer = vec![0u8; CHUNK_SIZE];
+        let mut bytes_sent = 0;
+        loop {
+            let read = source
+                .read(&mut buffer)
+                .await
+                .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+            if read == 0 {
+                break;
+            }
+
+            tokio::time::timeout(self.write_timeout, stream.write_all(&buffer[..read]))
+                .await
+                .map_err(|_| format!("Timed out sending data to {}", target))?
+                .map_err(|e| format!("Failed to send data: {}", e))?;
+            hasher.update(&buffer[..read]);
+            bytes_sent += read;
+
+            if let Some(progress) = &self.progress {
+                progress(Progress { bytes_sent, total });
+            }
+        }
+
+        stream
+            .flush()
+            .await
+            .map_err(|e| format!("Failed to flush data: {}", e))?;
+
+        Ok(TransferReport {
+            bytes_sent,
+            checksums: hasher.finish(),
+        })
+    }
+
+    /// `inject` for callers without an async runtime. Must not be called from
+    /// within one.
+    pub fn inject_blocking(
+        &self,
+        target: &Target,
+        payload: Payload,
+    ) -> Result<TransferReport, String> {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| format!("Failed to create async runtime: {}", e))?
+            .block_on(self.inject(target, payload))
+    }
+
+    async fn connect(&self, target: &Target) -> Result<TcpStream, String> {
+        let mut attempt = 0;
+        loop {
+            let connected = tokio::time::timeout(
+                self.connect_timeout,
+                TcpStream::connect((target.host.as_str(), target.port)),
+            )
+            .await;
+            let error = match connected {
+                Ok(Ok(stream)) => return Ok(stream),
+                Ok(Err(e)) => format!("Failed to connect to {}: {}", target, e),
+                Err(_) => format!("Connection timeout to {}", target),
+            };
+
+            if attempt >= self.retries {
+                return Err(error);
+            }
+            attempt += 1;
+            tokio::time::sleep(self.retry_delay).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::mock::{self, MockOptions, ReceivedPayload};
+    use std::sync::{mpsc, Mutex};
+
+    fn start_mock() -> (mock::MockLoader, mpsc::Receiver<ReceivedPayload>, Target) {
+        let (sender, receiver) = mpsc::channel();
+        let mock = mock::start_mock(MockOptions::local(), sender).expect("Failed to start mock");
+        let target = Target::new(mock.local_addr().ip().to_string(), mock.local_addr().port());
+        (mock, receiver, target)
+    }
+
+    #[test]
+    fn test_target_parse_and_display() {
+        let target: Target = "192.168.1.4:9021".parse().expect("Failed to parse");
+        assert_eq!(target, Target::new("192.168.1.4", 9021));
+        assert_eq!(target.to_string(), "192.168.1.4:9021");
+
+        let target: Target = "[fe80::1]:9090".parse().expect("Failed to parse");
+        assert_eq!(target.host, "fe80::1");
+        assert_eq!(target.to_string(), "[fe80::1]:9090");
+
+        assert!("ps5.local"
+            .parse::<Target>()
+            .unwrap_err()
+            .contains("Missing port"));
+        assert!(":9021"
+            .parse::<Target>()
+            .unwrap_err()
+            .contains("Missing host"));
+        assert!("ps5.local:http"
+            .parse::<Target>()
+            .unwrap_err()
+            .contains("Invalid port number: http"));
+    }
+
+    #[tokio::test]
+    async fn test_inject_reports_progress() {
+        let (_mock, receiver, target) = start_mock();
+        let updates = Arc::new(Mutex::new(Vec::new()));
+        let injector = Injector::new().with_progress({
+            let updates = updates.clone();
+            move |progress| updates.lock().unwrap().push(progress)
+        });
+
+        let data = vec![7u8; CHUNK_SIZE + 10];
+        let report = injector
+            .inject(&target, Payload::bytes(data.clone()))
+            .await
+            .expect("Failed to inject");
+        assert_eq!(report.bytes_sent, data.len());
+
+        let updates = updates.lock().unwrap();
+        assert_eq!(
+            *updates,
+            [
+                Progress {
+                    bytes_sent: CHUNK_SIZE,
+                    total: Some(data.len())
+                },
+                Progress {
+                    bytes_sent: data.len(),
+                    total: Some(data.len())
+                },
+            ]
+        );
+        assert_eq!(updates[1].fraction(), Some(1.0));
+
+        let received = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Nothing received");
+        assert_eq!(received.data, data);
+    }
+
+    #[tokio::test]
+    async fn test_inject_retries_connecting() {
+        // Reserve a free port, then start the loader on it only after the
+        // first attempt has failed
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .and_then(|listener| listener.local_addr())
+            .expect("Failed to find a free port")
+            .port();
+        let target = Target::new("127.0.0.1", port);
+        let late_start = tokio::task::spawn_blocking(move || {
+            std::thread::sleep(Duration::from_millis(150));
+            let (sender, receiver) = mpsc::channel();
+            let options = MockOptions {
+                bind: format!("127.0.0.1:{}", port),
+                ..MockOptions::default()
+            };
+            (mock::start_mock(options, sender), receiver)
+        });
+
+        let result = Injector::new()
+            .inject(&target, Payload::bytes(&b"abc"[..]))
+            .await;
+        assert!(result
+            .unwrap_err()
+            .contains("Failed to connect to 127.0.0.1:"));
+
+        let report = Injector::new()
+            .with_retries(20, Duration::from_millis(50))
+            .inject(&target, Payload::bytes(&b"abc"[..]))
+            .await
+            .expect("Retries did not reach the loader");
+        assert_eq!(report.bytes_sent, 3);
+
+        let (mock, receiver) = late_start.await.expect("Mock thread failed");
+        let _mock = mock.expect("Failed to start mock");
+        let received = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Nothing received");
+        assert_eq!(received.data, b"abc");
+    }
+
+    #[test]
+    fn test_inject_blocking() {
+        let (_mock, receiver, target) = start_mock();
+        let temp_file = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+        std::fs::write(&temp_file, b"from a file").expect("Failed to write test data");
+
+        let report = Injector::new()
+            .with_crc32(true)
+            .inject_blocking(&target, Payload::file(temp_file.path()))
+            .expect("Failed to inject");
+        assert_eq!(report.bytes_sent, 11);
+        assert!(report.checksums.crc32.is_some());
+        let received = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Nothing received");
+        assert_eq!(received.data, b"from a file");
+
+        let result = Injector::new().inject_blocking(&target, Payload::file("/missing.elf"));
+        assert!(result
+            .unwrap_err()
+            .contains("Failed to open file '/missing.elf'"));
+    }
+}
@@ -0,0 +1,218 @@
+use crate::elf;
+
+/// Bytes shown on each line of a hex dump
+pub const HEX_ROW_BYTES: usize = 16;
+
+/// Lines of a hex dump shown at a time
+pub const HEX_PAGE_ROWS: usize = 32;
+
+pub const HEX_PAGE_BYTES: usize = HEX_ROW_BYTES * HEX_PAGE_ROWS;
+
+/// Shorter runs of printable characters are not treated as strings
+pub const MIN_STRING_LEN: usize = 4;
+
+/// Number of hex dump pages needed for `len` bytes, at least one
+pub fn page_count(len: usize) -> usize {
+    len.div_ceil(HEX_PAGE_BYTES).max(1)
+}
+
+/// Page of the hex dump that shows `offset`
+pub fn page_of(offset: usize) -> usize {
+    offset / HEX_PAGE_BYTES
+}
+
+/// One line of a hex dump in `hexdump -C` style, e.g.
+/// `00000000  7f 45 4c 46 02 01 01 00  00 00 00 00 00 00 00 00  |.ELF............|`
+pub fn hex_line(bytes: &[u8], offset: usize) -> String {
+    let row = bytes
+        .get(offset..(offset + HEX_ROW_BYTES).min(bytes.len()))
+        .unwrap_or_default();
+    format_row(row, offset)
+}
+
+fn format_row(row: &[u8], offset: usize) -> String {
+    let mut hex = String::new();
+    for index in 0..HEX_ROW_BYTES {
+        if index == HEX_ROW_BYTES / 2 {
+            hex.push(' ');
+        }
+        match row.get(index) {
+            Some(byte) => hex.push_str(&format!("{:02x} ", byte)),
+            None => hex.push_str("   "),
+        }
+    }
+
+    let text: String = row
+        .iter()
+        .map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            }
+        })
+        .collect();
+    format!("{:08x}  {} |{}|", offset, hex, text)
+}
+
+/// Lines of hex dump page `page`, empty past the end of the bytes
+pub fn hex_page(bytes: &[u8], page: usize) -> Vec<String> {
+    let start = page.saturating_mul(HEX_PAGE_BYTES);
+    let end = start.saturating_add(HEX_PAGE_BYTES).min(bytes.len());
+    hex_lines(bytes.get(start..end).unwrap_or_default(), start)
+}
+
+/// Hex dump lines of `bytes` read from `offset` in a file
+pub fn hex_lines(bytes: &[u8], offset: usize) -> Vec<String> {
+    bytes
+        .chunks(HEX_ROW_BYTES)
+        .enumerate()
+        .map(|(index, row)| format_row(row, offset + index * HEX_ROW_BYTES))
+        .collect()
+}
+
+/// A run of printable ASCII found in a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoundString {
+    pub offset: usize,
+    pub text: String,
+}
+
+/// Runs of at least `min_len` printable ASCII characters, like `strings`
+pub fn extract_strings(bytes: &[u8], min_len: usize) -> Vec<FoundString> {
+    let mut found = Vec::new();
+    let mut start = None;
+    for (index, &byte) in bytes.iter().enumerate() {
+        let printable = byte.is_ascii_graphic() || byte == b' ' || byte == b'\t';
+        match (printable, start) {
+            (true, None) => start = Some(index),
+            (false, Some(run_start)) => {
+                push_run(&mut found, bytes, run_start, index, min_len);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(run_start) = start {
+        push_run(&mut found, bytes, run_start, bytes.len(), min_len);
+    }
+    found
+}
+
+fn push_run(found: &mut Vec<FoundString>, bytes: &[u8], start: usize, end: usize, min_len: usize) {
+    if end - start >= min_len {
+        found.push(FoundString {
+            offset: start,
+            text: String::from_utf8_lossy(&bytes[start..end]).into_owned(),
+        });
+    }
+}
+
+/// Strings containing `query` ignoring case, or all of them for an empty query
+pub fn search<'a>(
+    strings: &'a [FoundString],
+    query: &str,
+) -> impl Iterator<Item = &'a FoundString> {
+    let query = query.trim().to_lowercase();
+    strings
+        .iter()
+        .filter(move |found| query.is_empty() || found.text.to_lowercase().contains(&query))
+}
+
+/// A place in a file worth jumping to, such as an ELF section
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Landmark {
+    pub label: String,
+    pub offset: usize,
+}
+
+/// Segments and sections of an ELF file with bytes in it, in that order.
+/// Anything that is not an ELF has none.
+pub fn elf_landmarks(bytes: &[u8]) -> Vec<Landmark> {
+    let mut landmarks = Vec::new();
+    for segment in elf::segments(bytes).unwrap_or_default() {
+        if segment.file_size == 0 {
+            continue;
+        }
+        landmarks.push(Landmark {
+            label: format!(
+                "Segment {} [{}] at {:#x}",
+                segment.type_name(),
+                segment.flags_text(),
+                segment.address
+            ),
+            offset: segment.offset as usize,
+        });
+    }
+    for section in elf::sections(bytes).unwrap_or_default() {
+        if !section.has_file_data() || section.size == 0 {
+            continue;
+        }
+        landmarks.push(Landmark {
+            label: format!("Section {} ({} bytes)", section.name, section.size),
+            offset: section.offset as usize,
+        });
+    }
+    landmarks.retain(|landmark| landmark.offset < bytes.len());
+    landmarks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_pages() {
+        let bytes: Vec<u8> = (0..=255u8).cycle().take(HEX_PAGE_BYTES + 20).collect();
+
+        assert_eq!(page_count(0), 1);
+        assert_eq!(page_count(bytes.len()), 2);
+        assert_eq!(page_of(HEX_PAGE_BYTES + 3), 1);
+
+        let first = hex_page(&bytes, 0);
+        assert_eq!(first.len(), HEX_PAGE_ROWS);
+        assert_eq!(
+            first[4],
+            "00000040  40 41 42 43 44 45 46 47  48 49 4a 4b 4c 4d 4e 4f  |@ABCDEFGHIJKLMNO|"
+        );
+
+        // The last line is padded so the text column lines up
+        let last = hex_page(&bytes, 1);
+        assert_eq!(last.len(), 2);
+        assert_eq!(
+            last[1],
+            format!("00000210  10 11 12 13 {}|....|", " ".repeat(3 * 12 + 2))
+        );
+        assert_eq!(last[0].len(), last[1].len() + 12);
+
+        assert!(hex_page(&bytes, 5).is_empty());
+
+        // Lines of bytes read from the middle of a file are numbered from there
+        assert_eq!(hex_lines(&bytes[HEX_PAGE_BYTES..], HEX_PAGE_BYTES), last);
+    }
+
+    #[test]
+    fn test_extract_and_search_strings() {
+        let bytes = b"\x7fELF\x00\x01GoldHEN v2.4b18\x00ab\x00\x02Built by CI\tnightly";
+        let strings = extract_strings(bytes, MIN_STRING_LEN);
+        let texts: Vec<(usize, &str)> = strings
+            .iter()
+            .map(|found| (found.offset, found.text.as_str()))
+            .collect();
+        // "ELF" and "ab" are too short
+        assert_eq!(
+            texts,
+            [(6, "GoldHEN v2.4b18"), (26, "Built by CI\tnightly")]
+        );
+
+        assert_eq!(search(&strings, "goldhen").count(), 1);
+        assert_eq!(search(&strings, "  ").count(), strings.len());
+        assert_eq!(search(&strings, "missing").count(), 0);
+    }
+
+    #[test]
+    fn test_non_elf_has_no_landmarks() {
+        assert!(elf_landmarks(b"raw payload bytes").is_empty());
+        assert!(elf_landmarks(b"").is_empty());
+    }
+}
@@ -1,4 +1,50 @@
+//! Send payloads to console payload loaders.
+//!
+//! Tools embedding the injector only need [`Injector`], [`Target`] and
+//! [`Payload`]:
+//!
+//! ```no_run
+//! use ps_payload_injector::{Injector, Payload, Target};
+//! use std::time::Duration;
+//!
+//! let target: Target = "192.168.1.4:9021".parse()?;
+//! let report = Injector::new()
+//!     .with_retries(3, Duration::from_secs(1))
+//!     .with_progress(|progress| println!("{} bytes sent", progress.bytes_sent))
+//!     .inject_blocking(&target, Payload::file("payload.elf"))?;
+//! println!("sha256 {}", report.checksums.sha256);
+//! # Ok::<(), String>(())
+//! ```
+//!
+//! From async code use [`Injector::inject`] instead of `inject_blocking`.
+
+pub mod activity;
+pub mod api;
+pub mod archive;
+pub mod bundle;
+pub mod checksum;
+pub mod cli;
 pub mod config;
+pub mod elf;
 pub mod handlers;
+pub mod history;
+pub mod injector;
+pub mod inspect;
 pub mod network;
+#[cfg(feature = "gui")]
+pub mod notify;
+pub mod palette;
+pub mod payload;
+pub mod preferences;
+pub mod profiles;
+pub mod project;
+pub mod queue;
+pub mod recent;
+pub mod settings;
+pub mod status;
+#[cfg(feature = "gui")]
 pub mod ui;
+pub mod watch;
+
+pub use injector::{Injector, Payload, Progress, Target};
+pub use network::TransferReport;
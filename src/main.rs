@@ -1,45 +1,142 @@
-#![cfg_attr(windows, windows_subsystem = "windows")]
-
-use eframe::egui;
-use ps_payload_injector::handlers::{
-    create_auto_save_fn, create_auto_save_preference_fn, create_inject_fn, create_load_config_fn,
-    create_reset_fn, create_save_config_fn, load_startup_config,
-};
-
-fn main() -> eframe::Result {
-    let app_name = "PS Payload Injector";
-
-    let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([560.0, 300.0])
-            .with_resizable(false),
-        renderer: eframe::Renderer::Glow,
-        vsync: true,
-        hardware_acceleration: eframe::HardwareAcceleration::Preferred,
-        ..Default::default()
+#![cfg_attr(all(windows, feature = "gui"), windows_subsystem = "windows")]
+
+use ps_payload_injector::cli::{self, CliArgs, USAGE};
+use ps_payload_injector::config::Config;
+use ps_payload_injector::settings::Settings;
+
+fn main() {
+    let args = match CliArgs::from_env() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    if args.help {
+        print!("{}", USAGE);
+        return;
+    }
+
+    // Must happen before anything reads the config
+    Config::set_location(args.config_location());
+    Settings::set_overrides(args.overrides.clone());
+    match Config::migrate_legacy_config() {
+        Ok(Some(legacy)) => eprintln!(
+            "Migrated config from '{}' to '{}'",
+            legacy.display(),
+            Config::default_auto_save_path().display()
+        ),
+        Ok(None) => {}
+        Err(e) => eprintln!("Warning: {}", e),
+    }
+
+    if let Some(command) = &args.command {
+        std::process::exit(cli::run(command));
+    }
+
+    #[cfg(feature = "gui")]
+    if let Err(e) = gui::run(&args) {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+
+    // Without the GUI the control API can still run on its own, e.g. on a relay
+    #[cfg(not(feature = "gui"))]
+    match args.api_options() {
+        Some(api_options) => std::process::exit(cli::serve_api(api_options)),
+        None => {
+            eprintln!(
+                "Error: This build has no GUI, give a command or --api\n\n{}",
+                USAGE
+            );
+            std::process::exit(2);
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+mod gui {
+    use eframe::egui;
+    use ps_payload_injector::api;
+    use ps_payload_injector::cli::CliArgs;
+    use ps_payload_injector::config::DataFile;
+    use ps_payload_injector::handlers::{
+        create_auto_save_fn, create_auto_save_preference_fn, create_inject_fn,
+        create_load_config_fn, create_reset_fn, create_save_config_fn,
     };
+    use ps_payload_injector::preferences::{Preferences, MIN_WINDOW_SIZE};
+    use ps_payload_injector::settings::Settings;
+    use ps_payload_injector::ui::InjectionStatus;
+
+    pub fn run(args: &CliArgs) -> eframe::Result {
+        let app_name = "PS Payload Injector";
+
+        // Reopen the window where it was last closed
+        let preferences = Preferences::load_or_default();
+        let mut viewport = egui::ViewportBuilder::default()
+            .with_inner_size(preferences.window_size())
+            .with_min_inner_size(MIN_WINDOW_SIZE);
+        if let Some(position) = preferences.window.and_then(|window| window.position) {
+            viewport = viewport.with_position(position);
+        }
+
+        let options = eframe::NativeOptions {
+            viewport,
+            renderer: eframe::Renderer::Glow,
+            vsync: true,
+            hardware_acceleration: eframe::HardwareAcceleration::Preferred,
+            ..Default::default()
+        };
+
+        let inject_fn = create_inject_fn();
+        let save_config_fn = create_save_config_fn();
+        let load_config_fn = create_load_config_fn();
+        let auto_save_fn = create_auto_save_fn();
+        let auto_save_preference_fn = create_auto_save_preference_fn();
+        let reset_fn = create_reset_fn();
+        let settings = Settings::load();
+        let mut startup_error = settings.startup_error();
+
+        // The control API runs for as long as the GUI does
+        let mut remote_api = None;
+        let mut api_handle = None;
+        if let Some(api_options) = args.api_options() {
+            let (sender, receiver) = std::sync::mpsc::channel();
+            match api::start(api_options, Some(sender)) {
+                Ok(handle) => {
+                    remote_api = Some((handle.url(), receiver));
+                    api_handle = Some(handle);
+                }
+                Err(e) => startup_error = Some(e),
+            }
+        }
+
+        let result = eframe::run_native(
+            app_name,
+            options,
+            Box::new(|_cc| {
+                let app = ps_payload_injector::ui::App::new(
+                    inject_fn,
+                    save_config_fn,
+                    load_config_fn,
+                    auto_save_fn,
+                    auto_save_preference_fn,
+                    reset_fn,
+                    settings,
+                );
+                let app = match remote_api {
+                    Some((url, receiver)) => app.with_remote_api(url, receiver),
+                    None => app,
+                };
+                Ok(Box::new(match startup_error {
+                    Some(e) => app.with_status(InjectionStatus::Error(e)),
+                    None => app,
+                }))
+            }),
+        );
 
-    let inject_fn = create_inject_fn();
-    let save_config_fn = create_save_config_fn();
-    let load_config_fn = create_load_config_fn();
-    let auto_save_fn = create_auto_save_fn();
-    let auto_save_preference_fn = create_auto_save_preference_fn();
-    let reset_fn = create_reset_fn();
-    let startup_config = load_startup_config();
-
-    eframe::run_native(
-        app_name,
-        options,
-        Box::new(|_cc| {
-            Ok(Box::new(ps_payload_injector::ui::App::new(
-                inject_fn,
-                save_config_fn,
-                load_config_fn,
-                auto_save_fn,
-                auto_save_preference_fn,
-                reset_fn,
-                startup_config,
-            )))
-        }),
-    )
+        drop(api_handle);
+        result
+    }
 }
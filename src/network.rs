@@ -1,13 +1,32 @@
-use std::fs::File;
-use std::io::Read;
-use std::time::Duration;
-use tokio::io::AsyncWriteExt;
+pub mod host;
+pub mod http;
+pub mod mock;
+
+use crate::checksum::Checksums;
+use crate::injector::{Injector, Payload, Target};
+use std::future::Future;
+use std::net::SocketAddr;
+use tokio::io::AsyncRead;
 use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+
+/// File path that makes a transfer read its payload from standard input
+pub const STDIN_PATH: &str = "-";
 
 pub struct FileTransfer {
     pub ip: String,
     pub port: String,
+    /// May point into an archive, e.g. `release.zip!/payload.elf`, or be
+    /// `STDIN_PATH`
     pub file_path: String,
+    pub crc32: bool, // Also compute a CRC32 of the sent bytes
+}
+
+/// What a completed transfer sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferReport {
+    pub bytes_sent: usize,
+    pub checksums: Checksums,
 }
 
 impl FileTransfer {
@@ -16,47 +35,131 @@ impl FileTransfer {
             ip,
             port,
             file_path,
+            crc32: false,
         }
     }
 
-    pub async fn send_file(&self) -> Result<usize, String> {
-        let address = format!("{}:{}", self.ip, self.port);
+    /// A transfer to `ip:port` without a file, for `send_reader` and `send_bytes`
+    pub fn to(ip: &str, port: &str) -> Self {
+        Self::new(ip.to_string(), port.to_string(), String::new())
+    }
 
-        let mut file = File::open(&self.file_path)
-            .map_err(|e| format!("Failed to open file '{}': {}", self.file_path, e))?;
+    pub fn with_crc32(mut self, crc32: bool) -> Self {
+        self.crc32 = crc32;
+        self
+    }
 
-        let mut buffer = Vec::new();
+    pub async fn send_file(&self) -> Result<usize, String> {
+        self.send_file_with_checksums()
+            .await
+            .map(|report| report.bytes_sent)
+    }
 
-        file.read_to_end(&mut buffer)
-            .map_err(|e| format!("Failed to read file '{}': {}", self.file_path, e))?;
+    /// Stream the file to the target, hashing exactly the bytes that were sent
+    pub async fn send_file_with_checksums(&self) -> Result<TransferReport, String> {
+        self.send(Payload::from_arg(&self.file_path)).await
+    }
 
-        let mut stream =
-            tokio::time::timeout(Duration::from_secs(10), TcpStream::connect(&address))
-                .await
-                .map_err(|_| format!("Connection timeout to {}", address))?
-                .map_err(|e| format!("Failed to connect to {}: {}", address, e))?;
+    /// Send everything `reader` produces instead of the file
+    pub async fn send_reader<R: AsyncRead + Unpin + Send + 'static>(
+        &self,
+        reader: R,
+    ) -> Result<TransferReport, String> {
+        self.send(Payload::reader(reader)).await
+    }
 
-        stream
-            .write_all(&buffer)
-            .await
-            .map_err(|e| format!("Failed to send data: {}", e))?;
+    /// Send an in-memory payload instead of the file
+    pub async fn send_bytes(&self, bytes: &[u8]) -> Result<TransferReport, String> {
+        self.send(Payload::bytes(bytes)).await
+    }
 
-        stream
-            .flush()
+    async fn send(&self, payload: Payload) -> Result<TransferReport, String> {
+        let target = Target::from_parts(&self.ip, &self.port)?;
+        Injector::new()
+            .with_crc32(self.crc32)
+            .inject(&target, payload)
             .await
-            .map_err(|e| format!("Failed to flush data: {}", e))?;
+    }
+}
+
+/// A server running on a background thread; dropping it stops the server
+#[derive(Debug)]
+pub struct ServerHandle {
+    pub local_addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+}
 
-        Ok(buffer.len())
+impl ServerHandle {
+    pub fn url(&self) -> String {
+        format!("http://{}", self.local_addr)
+    }
+}
+
+impl Drop for ServerHandle {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
     }
 }
 
+/// Bind `bind` and hand every accepted connection to `handler` on a background
+/// thread with its own runtime. Binding happens before returning, so address
+/// errors are reported to the caller. `what` names the server in messages.
+pub fn spawn_server<H, Fut>(bind: &str, what: &str, handler: H) -> Result<ServerHandle, String>
+where
+    H: Fn(TcpStream, SocketAddr) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let listener = std::net::TcpListener::bind(bind)
+        .map_err(|e| format!("Failed to bind {} to {}: {}", what, bind, e))?;
+    let local_addr = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to get {} address: {}", what, e))?;
+    listener
+        .set_nonblocking(true)
+        .map_err(|e| format!("Failed to configure {} socket: {}", what, e))?;
+
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|e| format!("Failed to create async runtime: {}", e))?;
+    // Registering the socket needs the runtime it will be polled on
+    let listener = {
+        let _runtime = runtime.enter();
+        tokio::net::TcpListener::from_std(listener)
+            .map_err(|e| format!("Failed to start {}: {}", what, e))?
+    };
+    let (shutdown, mut shutdown_signal) = oneshot::channel();
+
+    std::thread::spawn(move || {
+        runtime.block_on(async move {
+            loop {
+                tokio::select! {
+                    _ = &mut shutdown_signal => break,
+                    accepted = listener.accept() => {
+                        if let Ok((stream, client)) = accepted {
+                            tokio::spawn(handler(stream, client));
+                        }
+                    }
+                }
+            }
+        });
+    });
+
+    Ok(ServerHandle {
+        local_addr,
+        shutdown: Some(shutdown),
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use mock::MockOptions;
     use std::fs;
+    use std::sync::mpsc;
+    use std::time::Duration;
     use tempfile::NamedTempFile;
-    use tokio::io::AsyncReadExt;
-    use tokio::net::TcpListener;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
     #[test]
     fn test_file_transfer_new() {
@@ -125,25 +228,11 @@ mod tests {
         let test_data = b"Hello, this is test file content for network transfer!";
         fs::write(&temp_file, test_data).expect("Failed to write test data");
 
-        // Start a test TCP server
-        let listener = TcpListener::bind("127.0.0.1:0")
-            .await
-            .expect("Failed to bind listener");
-        let local_addr = listener.local_addr().expect("Failed to get local address");
-
-        // Spawn server task
-        let server_handle = tokio::spawn(async move {
-            let (mut socket, _) = listener
-                .accept()
-                .await
-                .expect("Failed to accept connection");
-            let mut buffer = Vec::new();
-            socket
-                .read_to_end(&mut buffer)
-                .await
-                .expect("Failed to read data");
-            buffer
-        });
+        // Start a mock loader
+        let (sender, receiver) = mpsc::channel();
+        let mock =
+            mock::start_mock(MockOptions::local(), sender).expect("Failed to start mock loader");
+        let local_addr = mock.local_addr();
 
         // Create file transfer and send
         let transfer = FileTransfer::new(
@@ -157,34 +246,123 @@ mod tests {
         assert_eq!(result.unwrap(), test_data.len());
 
         // Verify server received the data
-        let received_data = server_handle.await.expect("Server task failed");
+        let received_data = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Nothing received")
+            .data;
         assert_eq!(received_data, test_data);
     }
 
     #[tokio::test]
-    async fn test_send_empty_file() {
-        // Create an empty temporary file
+    async fn test_send_file_with_checksums() {
         let temp_file = NamedTempFile::new().expect("Failed to create temp file");
+        let test_data: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        fs::write(&temp_file, &test_data).expect("Failed to write test data");
+
+        let (sender, receiver) = mpsc::channel();
+        let mock = mock::start_mock(MockOptions::local(), sender).expect("Failed to start mock");
+        let transfer = FileTransfer::new(
+            mock.local_addr().ip().to_string(),
+            mock.local_addr().port().to_string(),
+            temp_file.path().to_str().unwrap().to_string(),
+        )
+        .with_crc32(true);
 
-        // Start a test TCP server
-        let listener = TcpListener::bind("127.0.0.1:0")
+        let report = transfer
+            .send_file_with_checksums()
             .await
-            .expect("Failed to bind listener");
-        let local_addr = listener.local_addr().expect("Failed to get local address");
-
-        // Spawn server task
-        let server_handle = tokio::spawn(async move {
-            let (mut socket, _) = listener
-                .accept()
-                .await
-                .expect("Failed to accept connection");
-            let mut buffer = Vec::new();
-            socket
-                .read_to_end(&mut buffer)
-                .await
-                .expect("Failed to read data");
-            buffer
+            .expect("Failed to send file");
+        assert_eq!(report.bytes_sent, test_data.len());
+        assert_eq!(report.checksums.crc32, Some(crc32fast::hash(&test_data)));
+
+        // The loader saw exactly the bytes that were hashed
+        let received = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Nothing received");
+        assert_eq!(report.checksums.sha256, received.sha256);
+    }
+
+    #[tokio::test]
+    async fn test_send_file_from_archive() {
+        use std::io::Write;
+
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let archive = temp_dir.path().join("release.zip");
+        let test_data: Vec<u8> = (0..150_000u32).map(|i| (i % 7) as u8).collect();
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive).expect("Failed to create"));
+        zip.start_file("bin/payload.elf", zip::write::SimpleFileOptions::default())
+            .expect("Failed to add file");
+        zip.write_all(&test_data).expect("Failed to write file");
+        zip.finish().expect("Failed to finish archive");
+
+        let (sender, receiver) = mpsc::channel();
+        let mock = mock::start_mock(MockOptions::local(), sender).expect("Failed to start mock");
+        let transfer = |entry: &str| {
+            FileTransfer::new(
+                mock.local_addr().ip().to_string(),
+                mock.local_addr().port().to_string(),
+                format!("{}!/{}", archive.display(), entry),
+            )
+        };
+
+        let report = transfer("bin/payload.elf")
+            .send_file_with_checksums()
+            .await
+            .expect("Failed to send entry");
+        assert_eq!(report.bytes_sent, test_data.len());
+        let received = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Nothing received");
+        assert_eq!(received.data, test_data);
+
+        // A missing entry fails before connecting
+        let result = transfer("missing.elf").send_file().await;
+        assert!(result.unwrap_err().contains("No entry 'missing.elf'"));
+        assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_send_bytes_and_reader() {
+        let (sender, receiver) = mpsc::channel();
+        let mock = mock::start_mock(MockOptions::local(), sender).expect("Failed to start mock");
+        let transfer = FileTransfer::to(
+            &mock.local_addr().ip().to_string(),
+            &mock.local_addr().port().to_string(),
+        );
+
+        let report = transfer.send_bytes(b"abc").await.expect("Failed to send");
+        assert_eq!(report.bytes_sent, 3);
+        let received = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Nothing received");
+        assert_eq!(received.data, b"abc");
+        assert_eq!(received.sha256, report.checksums.sha256);
+
+        // Any async reader works, e.g. one end of a pipe another task writes to
+        let (mut writer, reader) = tokio::io::duplex(64);
+        tokio::spawn(async move {
+            for chunk in [&b"generated "[..], b"payload"] {
+                writer.write_all(chunk).await.expect("Failed to write");
+            }
         });
+        let report = transfer.send_reader(reader).await.expect("Failed to send");
+        assert_eq!(report.bytes_sent, 17);
+        let received = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Nothing received");
+        assert_eq!(received.data, b"generated payload");
+    }
+
+    #[tokio::test]
+    async fn test_send_empty_file() {
+        // Create an empty temporary file
+        let temp_file = NamedTempFile::new().expect("Failed to create temp file");
+
+        // Start a mock loader
+        let (sender, receiver) = mpsc::channel();
+        let mock =
+            mock::start_mock(MockOptions::local(), sender).expect("Failed to start mock loader");
+        let local_addr = mock.local_addr();
 
         // Create file transfer and send
         let transfer = FileTransfer::new(
@@ -198,7 +376,10 @@ mod tests {
         assert_eq!(result.unwrap(), 0);
 
         // Verify server received empty data
-        let received_data = server_handle.await.expect("Server task failed");
+        let received_data = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Nothing received")
+            .data;
         assert_eq!(received_data.len(), 0);
     }
 
@@ -209,25 +390,11 @@ mod tests {
         let test_data = vec![0xAB; 1024 * 1024]; // 1MB of data
         fs::write(&temp_file, &test_data).expect("Failed to write test data");
 
-        // Start a test TCP server
-        let listener = TcpListener::bind("127.0.0.1:0")
-            .await
-            .expect("Failed to bind listener");
-        let local_addr = listener.local_addr().expect("Failed to get local address");
-
-        // Spawn server task
-        let server_handle = tokio::spawn(async move {
-            let (mut socket, _) = listener
-                .accept()
-                .await
-                .expect("Failed to accept connection");
-            let mut buffer = Vec::new();
-            socket
-                .read_to_end(&mut buffer)
-                .await
-                .expect("Failed to read data");
-            buffer
-        });
+        // Start a mock loader
+        let (sender, receiver) = mpsc::channel();
+        let mock =
+            mock::start_mock(MockOptions::local(), sender).expect("Failed to start mock loader");
+        let local_addr = mock.local_addr();
 
         // Create file transfer and send
         let transfer = FileTransfer::new(
@@ -241,7 +408,10 @@ mod tests {
         assert_eq!(result.unwrap(), test_data.len());
 
         // Verify server received all the data
-        let received_data = server_handle.await.expect("Server task failed");
+        let received_data = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("Nothing received")
+            .data;
         assert_eq!(received_data.len(), test_data.len());
         assert_eq!(received_data, test_data);
     }
@@ -268,4 +438,28 @@ mod tests {
 
         assert_eq!(transfer.file_path, "/path with spaces/file-name_test.txt");
     }
+
+    #[tokio::test]
+    async fn test_spawn_server_stops_on_drop() {
+        let server = spawn_server("127.0.0.1:0", "test server", |mut stream, _| async move {
+            let _ = stream.write_all(b"hello").await;
+        })
+        .expect("Failed to start server");
+        let addr = server.local_addr;
+
+        let mut stream = TcpStream::connect(addr).await.expect("Failed to connect");
+        let mut text = String::new();
+        stream
+            .read_to_string(&mut text)
+            .await
+            .expect("Failed to read");
+        assert!(text.ends_with("hello"));
+
+        drop(server);
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(TcpStream::connect(addr).await.is_err());
+
+        let result = spawn_server("not an address", "test server", |_, _| async {});
+        assert!(result.unwrap_err().contains("Failed to bind test server"));
+    }
 }
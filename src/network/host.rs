@@ -0,0 +1,455 @@
+use super::http::{self, Request, Response};
+use super::{spawn_server, ServerHandle};
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use tokio::net::TcpStream;
+
+pub const DEFAULT_HOST_PORT: u16 = 8080;
+
+/// Served for directory requests when present
+const INDEX_FILE_NAME: &str = "index.html";
+
+/// Settings of the static file server
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostOptions {
+    pub root: PathBuf,
+    pub bind: String,
+}
+
+impl HostOptions {
+    /// Serve `root` to the whole LAN on `port`
+    pub fn new(root: PathBuf, port: u16) -> Self {
+        Self {
+            root,
+            bind: format!("0.0.0.0:{}", port),
+        }
+    }
+}
+
+/// One request handled by the host, for the request log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestLogEntry {
+    pub time: String, // Local time, e.g. `18:03:27`
+    pub client: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub bytes: u64,
+}
+
+impl RequestLogEntry {
+    pub fn summary(&self) -> String {
+        format!(
+            "{} {} {} {} {} ({} bytes)",
+            self.time, self.client, self.method, self.path, self.status, self.bytes
+        )
+    }
+}
+
+/// A running payload host; dropping it stops the server
+pub struct PayloadHost {
+    pub root: PathBuf,
+    server: ServerHandle,
+}
+
+impl PayloadHost {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server.local_addr
+    }
+
+    /// Address to open on the console. When listening on all interfaces this
+    /// uses the LAN address of this machine.
+    pub fn url(&self) -> String {
+        let mut addr = self.server.local_addr;
+        if addr.ip().is_unspecified() {
+            if let Some(ip) = lan_ip() {
+                addr.set_ip(ip);
+            }
+        }
+        format!("http://{}/", addr)
+    }
+}
+
+/// Serve the files under `options.root` on a background thread. Every handled
+/// request is reported through `log`.
+pub fn start_host(
+    options: HostOptions,
+    log: mpsc::Sender<RequestLogEntry>,
+) -> Result<PayloadHost, String> {
+    if !options.root.is_dir() {
+        return Err(format!(
+            "Host root '{}' is not a directory",
+            options.root.display()
+        ));
+    }
+
+    let root = options.root.clone();
+    let server = spawn_server(&options.bind, "payload host", move |stream, client| {
+        handle_connection(stream, client, root.clone(), log.clone())
+    })?;
+
+    Ok(PayloadHost {
+        root: options.root,
+        server,
+    })
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    client: SocketAddr,
+    root: PathBuf,
+    log: mpsc::Sender<RequestLogEntry>,
+) {
+    let (method, path, status, bytes) = match http::read_request(&mut stream).await {
+        Ok(request) => {
+            let (status, bytes) = serve(&mut stream, &root, &request).await;
+            (request.method, request.path, status, bytes)
+        }
+        Err(e) => {
+            let response = Response::new(400, "text/plain", e.into_bytes());
+            let _ = http::write_response(&mut stream, &response).await;
+            (
+                "-".to_string(),
+                "-".to_string(),
+                400,
+                response.body.len() as u64,
+            )
+        }
+    };
+
+    let _ = log.send(RequestLogEntry {
+        time: chrono::Local::now().format("%H:%M:%S").to_string(),
+        client: client.ip().to_string(),
+        method,
+        path,
+        status,
+        bytes,
+    });
+}
+
+/// Answer one request, returning the status and the number of body bytes sent
+async fn serve(stream: &mut TcpStream, root: &Path, request: &Request) -> (u16, u64) {
+    if request.method != "GET" && request.method != "HEAD" {
+        return send(stream, request, text_response(405, "Method not allowed")).await;
+    }
+
+    let Some(path) = resolve_path(root, &request.path) else {
+        return send(stream, request, text_response(403, "Forbidden")).await;
+    };
+
+    if path.is_dir() {
+        // Relative links in an index page only work with a trailing slash
+        if !request.path.ends_with('/') {
+            let location = format!("{}/", request.path);
+            let response = text_response(301, "Moved").with_header("Location", &location);
+            return send(stream, request, response).await;
+        }
+
+        let index = path.join(INDEX_FILE_NAME);
+        if !index.is_file() {
+            let listing = directory_listing(&path, &request.path);
+            let response = Response::new(200, "text/html; charset=utf-8", listing.into_bytes());
+            return send(stream, request, response).await;
+        }
+        return send_file(stream, request, &index).await;
+    }
+
+    if path.is_file() {
+        return send_file(stream, request, &path).await;
+    }
+
+    send(stream, request, text_response(404, "Not found")).await
+}
+
+async fn send(stream: &mut TcpStream, request: &Request, mut response: Response) -> (u16, u64) {
+    if request.method == "HEAD" {
+        // Keep the length of the body that a GET would have returned
+        let length = response.body.len();
+        response.body.clear();
+        let mut headers = response.headers;
+        headers.push(("Content-Length".to_string(), length.to_string()));
+        let _ = http::write_head(stream, response.status, &headers).await;
+        return (response.status, 0);
+    }
+
+    let bytes = response.body.len() as u64;
+    match http::write_response(stream, &response).await {
+        Ok(()) => (response.status, bytes),
+        Err(_) => (response.status, 0),
+    }
+}
+
+/// Stream a file, which may be a large payload, without loading it into memory
+async fn send_file(stream: &mut TcpStream, request: &Request, path: &Path) -> (u16, u64) {
+    let mut file = match tokio::fs::File::open(path).await {
+        Ok(file) => file,
+        Err(e) => {
+            let message = format!("Failed to open file: {}", e);
+            return send(stream, request, text_response(500, &message)).await;
+        }
+    };
+    let length = match file.metadata().await {
+        Ok(metadata) => metadata.len(),
+        Err(e) => {
+            let message = format!("Failed to read file: {}", e);
+            return send(stream, request, text_response(500, &message)).await;
+        }
+    };
+
+    let headers = [
+        ("Content-Type".to_string(), content_type(path).to_string()),
+        ("Content-Length".to_string(), length.to_string()),
+        // Exploit pages and payloads are rebuilt often
+        ("Cache-Control".to_string(), "no-store".to_string()),
+    ];
+    if http::write_head(stream, 200, &headers).await.is_err() || request.method == "HEAD" {
+        return (200, 0);
+    }
+
+    let sent = tokio::io::copy(&mut file, stream).await.unwrap_or(0);
+    (200, sent)
+}
+
+fn text_response(status: u16, message: &str) -> Response {
+    Response::new(
+        status,
+        "text/plain; charset=utf-8",
+        message.as_bytes().to_vec(),
+    )
+}
+
+/// Map a request path onto a file under `root`. Returns `None` for paths that
+/// would leave the root.
+pub fn resolve_path(root: &Path, request_path: &str) -> Option<PathBuf> {
+    let mut path = root.to_path_buf();
+    for segment in request_path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => return None,
+            // Drive letters and backslashes could escape the root on Windows
+            _ if segment.contains(['\\', ':', '\0']) => return None,
+            _ => path.push(segment),
+        }
+    }
+    Some(path)
+}
+
+pub fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "json" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        "wasm" => "application/wasm",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "ico" => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+fn directory_listing(dir: &Path, request_path: &str) -> String {
+    let mut names: Vec<(String, bool)> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| {
+                    let is_dir = entry.path().is_dir();
+                    (entry.file_name().to_string_lossy().into_owned(), is_dir)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+
+    let title = html_escape(request_path);
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title></head>\n<body><h1>{0}</h1><ul>\n",
+        title
+    );
+    if request_path != "/" {
+        html.push_str("<li><a href=\"../\">../</a></li>\n");
+    }
+    for (name, is_dir) in names {
+        let suffix = if is_dir { "/" } else { "" };
+        html.push_str(&format!(
+            "<li><a href=\"{}{}\">{}{}</a></li>\n",
+            http::percent_encode(&name),
+            suffix,
+            html_escape(&name),
+            suffix
+        ));
+    }
+    html.push_str("</ul></body></html>\n");
+    html
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Best guess at this machine's LAN address. Connecting a UDP socket sends
+/// nothing, it only selects the outgoing interface.
+fn lan_ip() -> Option<IpAddr> {
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("192.0.2.1:9").ok()?;
+    let ip = socket.local_addr().ok()?.ip();
+    (!ip.is_unspecified()).then_some(ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn http_get(addr: SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.expect("Failed to connect");
+        stream
+            .write_all(request.as_bytes())
+            .await
+            .expect("Failed to send request");
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .await
+            .expect("Failed to read response");
+        response
+    }
+
+    fn test_host() -> (
+        tempfile::TempDir,
+        PayloadHost,
+        mpsc::Receiver<RequestLogEntry>,
+    ) {
+        let root = tempfile::tempdir().expect("Failed to create temp dir");
+        std::fs::write(root.path().join("index.html"), "<h1>exploit</h1>").expect("write");
+        std::fs::create_dir(root.path().join("payloads")).expect("mkdir");
+        std::fs::write(
+            root.path().join("payloads").join("my payload.bin"),
+            b"\x7fELF",
+        )
+        .expect("write");
+
+        let (sender, receiver) = mpsc::channel();
+        let options = HostOptions {
+            root: root.path().to_path_buf(),
+            bind: "127.0.0.1:0".to_string(),
+        };
+        let host = start_host(options, sender).expect("Failed to start host");
+        (root, host, receiver)
+    }
+
+    #[test]
+    fn test_resolve_path_stays_in_root() {
+        let root = Path::new("/srv/host");
+        assert_eq!(
+            resolve_path(root, "/payloads/a.bin"),
+            Some(PathBuf::from("/srv/host/payloads/a.bin"))
+        );
+        assert_eq!(resolve_path(root, "/"), Some(PathBuf::from("/srv/host")));
+        assert_eq!(
+            resolve_path(root, "//./a.bin"),
+            Some(PathBuf::from("/srv/host/a.bin"))
+        );
+        assert_eq!(resolve_path(root, "/../etc/passwd"), None);
+        assert_eq!(resolve_path(root, "/payloads/../../x"), None);
+        assert_eq!(resolve_path(root, "/C:/Windows"), None);
+        assert_eq!(resolve_path(root, "/..\\x"), None);
+    }
+
+    #[test]
+    fn test_content_type() {
+        assert_eq!(
+            content_type(Path::new("index.HTML")),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(
+            content_type(Path::new("exploit.js")),
+            "text/javascript; charset=utf-8"
+        );
+        assert_eq!(
+            content_type(Path::new("payload.bin")),
+            "application/octet-stream"
+        );
+        assert_eq!(content_type(Path::new("noext")), "application/octet-stream");
+    }
+
+    #[tokio::test]
+    async fn test_serves_index_and_files() {
+        let (_root, host, log) = test_host();
+
+        let response = http_get(host.local_addr(), "GET / HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("Content-Type: text/html"));
+        assert!(response.ends_with("<h1>exploit</h1>"));
+
+        let response = http_get(
+            host.local_addr(),
+            "GET /payloads/my%20payload.bin HTTP/1.1\r\n\r\n",
+        )
+        .await;
+        assert!(response.contains("Content-Type: application/octet-stream"));
+        assert!(response.contains("Content-Length: 4\r\n"));
+        assert!(response.ends_with("\x7fELF"));
+
+        let response = http_get(host.local_addr(), "HEAD /index.html HTTP/1.1\r\n\r\n").await;
+        assert!(response.contains("Content-Length: 16\r\n"));
+        assert!(response.ends_with("\r\n\r\n"));
+
+        let entry = log.recv().expect("Request not logged");
+        assert_eq!(entry.client, "127.0.0.1");
+        assert_eq!((entry.method.as_str(), entry.path.as_str()), ("GET", "/"));
+        assert_eq!((entry.status, entry.bytes), (200, 16));
+        let entry = log.recv().expect("Request not logged");
+        assert_eq!(entry.path, "/payloads/my payload.bin");
+        assert!(entry
+            .summary()
+            .contains("GET /payloads/my payload.bin 200 (4 bytes)"));
+    }
+
+    #[tokio::test]
+    async fn test_directory_listing_and_errors() {
+        let (_root, host, log) = test_host();
+
+        let response = http_get(host.local_addr(), "GET /payloads HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 301"));
+        assert!(response.contains("Location: /payloads/\r\n"));
+
+        let response = http_get(host.local_addr(), "GET /payloads/ HTTP/1.1\r\n\r\n").await;
+        assert!(response.contains("<a href=\"my%20payload.bin\">my payload.bin</a>"));
+        assert!(response.contains("<a href=\"../\">"));
+
+        let response = http_get(host.local_addr(), "GET /missing.js HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 404"));
+
+        let response = http_get(host.local_addr(), "GET /../secret HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 403"));
+
+        let response = http_get(host.local_addr(), "POST / HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 405"));
+
+        let statuses: Vec<u16> = log.try_iter().map(|entry| entry.status).collect();
+        assert_eq!(statuses, vec![301, 200, 404, 403, 405]);
+    }
+
+    #[test]
+    fn test_start_host_requires_directory() {
+        let (sender, _receiver) = mpsc::channel();
+        let options = HostOptions::new(PathBuf::from("/path/that/does/not/exist"), 0);
+        let result = start_host(options, sender);
+        assert!(result.err().unwrap().contains("is not a directory"));
+    }
+}
@@ -0,0 +1,380 @@
+use serde::Serialize;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+/// Largest request head (request line and headers) accepted
+pub const MAX_HEAD_SIZE: usize = 16 * 1024;
+
+/// Largest request body accepted
+pub const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// Longest wait for the request head, and again for the body, before the
+/// connection is given up
+pub const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A parsed HTTP/1.1 request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,                   // Percent-decoded, without the query string
+    pub query: Vec<(String, String)>,   // Percent-decoded
+    pub headers: Vec<(String, String)>, // Names are lowercase
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A complete response, sent with `Connection: close`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16, content_type: &str, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type".to_string(), content_type.to_string())],
+            body,
+        }
+    }
+
+    pub fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        match serde_json::to_vec_pretty(value) {
+            Ok(body) => Self::new(status, "application/json", body),
+            Err(e) => Self::error(500, &format!("Failed to serialize response: {}", e)),
+        }
+    }
+
+    /// JSON error body of the form `{"error": "..."}`
+    pub fn error(status: u16, message: &str) -> Self {
+        let body = serde_json::json!({ "error": message }).to_string();
+        Self::new(status, "application/json", body.into_bytes())
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+pub fn status_reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
+
+/// Read one request from `stream`, waiting at most `READ_TIMEOUT` for each of
+/// the head and the body
+pub async fn read_request<S: AsyncReadExt + Unpin>(stream: &mut S) -> Result<Request, String> {
+    read_request_with_timeout(stream, READ_TIMEOUT).await
+}
+
+async fn read_request_with_timeout<S: AsyncReadExt + Unpin>(
+    stream: &mut S,
+    read_timeout: Duration,
+) -> Result<Request, String> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    let read_head = async {
+        loop {
+            if let Some(end) = find_head_end(&buffer) {
+                return Ok(end);
+            }
+            if buffer.len() > MAX_HEAD_SIZE {
+                return Err("Request head too large".to_string());
+            }
+            let read = stream
+                .read(&mut chunk)
+                .await
+                .map_err(|e| format!("Failed to read request: {}", e))?;
+            if read == 0 {
+                return Err("Connection closed before end of request".to_string());
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+        }
+    };
+    let head_end = tokio::time::timeout(read_timeout, read_head)
+        .await
+        .map_err(|_| "Timed out reading request".to_string())??;
+
+    let head = std::str::from_utf8(&buffer[..head_end])
+        .map_err(|_| "Request head is not valid UTF-8".to_string())?;
+    let mut lines = head.split("\r\n");
+
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(format!("Malformed request line '{}'", request_line));
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(format!("Unsupported HTTP version '{}'", version));
+    }
+
+    let mut headers = Vec::new();
+    for line in lines.filter(|line| !line.is_empty()) {
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| format!("Malformed header '{}'", line))?;
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+
+    let (raw_path, raw_query) = target.split_once('?').unwrap_or((target, ""));
+    let mut request = Request {
+        method: method.to_string(),
+        path: percent_decode(raw_path),
+        query: parse_query(raw_query),
+        headers,
+        body: Vec::new(),
+    };
+
+    let content_length = match request.header("content-length") {
+        Some(value) => value
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid Content-Length '{}'", value))?,
+        None => 0,
+    };
+    if content_length > MAX_BODY_SIZE {
+        return Err("Request body too large".to_string());
+    }
+
+    let mut body = buffer[head_end + 4..].to_vec();
+    let read_body = async {
+        while body.len() < content_length {
+            let read = stream
+                .read(&mut chunk)
+                .await
+                .map_err(|e| format!("Failed to read request body: {}", e))?;
+            if read == 0 {
+                return Err("Connection closed before end of request body".to_string());
+            }
+            body.extend_from_slice(&chunk[..read]);
+        }
+        Ok(())
+    };
+    tokio::time::timeout(read_timeout, read_body)
+        .await
+        .map_err(|_| "Timed out reading request body".to_string())??;
+    body.truncate(content_length);
+    request.body = body;
+
+    Ok(request)
+}
+
+/// Write the status line and headers of a response. Used directly for
+/// streamed responses; `write_response` sends complete ones.
+pub async fn write_head<S: AsyncWriteExt + Unpin>(
+    stream: &mut S,
+    status: u16,
+    headers: &[(String, String)],
+) -> Result<(), String> {
+    let mut head = format!("HTTP/1.1 {} {}\r\n", status, status_reason(status));
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("Connection: close\r\n\r\n");
+
+    stream
+        .write_all(head.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to write response: {}", e))
+}
+
+pub async fn write_response<S: AsyncWriteExt + Unpin>(
+    stream: &mut S,
+    response: &Response,
+) -> Result<(), String> {
+    let mut headers = response.headers.clone();
+    headers.push((
+        "Content-Length".to_string(),
+        response.body.len().to_string(),
+    ));
+    write_head(stream, response.status, &headers).await?;
+
+    stream
+        .write_all(&response.body)
+        .await
+        .map_err(|e| format!("Failed to write response: {}", e))?;
+    stream
+        .flush()
+        .await
+        .map_err(|e| format!("Failed to write response: {}", e))
+}
+
+/// Decode `%XX` escapes; `+` is left as is since it is only special in forms
+pub fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = match bytes.get(i..i + 3) {
+            Some([b'%', hex @ ..]) if hex.iter().all(u8::is_ascii_hexdigit) => {
+                std::str::from_utf8(hex)
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            }
+            _ => None,
+        };
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Escape everything but unreserved characters, for use in a URL path segment
+pub fn percent_encode(input: &str) -> String {
+    let mut encoded = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (
+                percent_decode(&key.replace('+', " ")),
+                percent_decode(&value.replace('+', " ")),
+            )
+        })
+        .collect()
+}
+
+fn find_head_end(buffer: &[u8]) -> Option<usize> {
+    buffer.windows(4).position(|window| window == b"\r\n\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_request_with_body() {
+        let raw = b"POST /inject?token=a%20b&x HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\nContent-Length: 13\r\n\r\n{\"ip\": \"1.2\"}";
+        let mut stream = &raw[..];
+
+        let request = read_request(&mut stream).await.expect("Failed to parse");
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/inject");
+        assert_eq!(request.query_param("token"), Some("a b"));
+        assert_eq!(request.query_param("x"), Some(""));
+        assert_eq!(request.header("Content-Type"), Some("application/json"));
+        assert_eq!(request.body, b"{\"ip\": \"1.2\"}");
+    }
+
+    #[tokio::test]
+    async fn test_read_request_errors() {
+        let mut stream = &b"GET /\r\n\r\n"[..];
+        let result = read_request(&mut stream).await;
+        assert!(result.unwrap_err().contains("Malformed request line"));
+
+        let mut stream = &b"GET / HTTP/1.1\r\nHost: x"[..];
+        let result = read_request(&mut stream).await;
+        assert!(result.unwrap_err().contains("Connection closed"));
+
+        let mut stream = &b"POST / HTTP/1.1\r\nContent-Length: 99999999\r\n\r\n"[..];
+        let result = read_request(&mut stream).await;
+        assert!(result.unwrap_err().contains("too large"));
+    }
+
+    #[tokio::test]
+    async fn test_read_request_times_out() {
+        let timeout = Duration::from_millis(50);
+
+        // A client that never finishes the head
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        client.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+        let result = read_request_with_timeout(&mut server, timeout).await;
+        assert_eq!(result.unwrap_err(), "Timed out reading request");
+
+        // Or never sends the body it announced
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        client
+            .write_all(b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nab")
+            .await
+            .unwrap();
+        let result = read_request_with_timeout(&mut server, timeout).await;
+        assert_eq!(result.unwrap_err(), "Timed out reading request body");
+    }
+
+    #[tokio::test]
+    async fn test_write_response() {
+        let mut output = Vec::new();
+        let response = Response::error(404, "No such profile");
+        write_response(&mut output, &response)
+            .await
+            .expect("Failed to write");
+
+        let text = String::from_utf8(output).expect("Not UTF-8");
+        assert!(text.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        assert!(text.contains("Content-Type: application/json\r\n"));
+        assert!(text.contains("Connection: close\r\n"));
+        assert!(text.ends_with("{\"error\":\"No such profile\"}"));
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(
+            percent_decode("/payloads/my%20file.elf"),
+            "/payloads/my file.elf"
+        );
+        assert_eq!(percent_decode("%E2%9C%93"), "✓");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(percent_encode("my file.elf"), "my%20file.elf");
+        assert_eq!(percent_encode("a/b?c"), "a%2Fb%3Fc");
+        assert_eq!(percent_decode(&percent_encode("✓ ok")), "✓ ok");
+    }
+}
@@ -0,0 +1,362 @@
+use super::{spawn_server, ServerHandle};
+use crate::checksum::{sha256_hex, short_hash};
+use crate::config::write_file_atomic;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+pub const DEFAULT_MOCK_BIND: &str = "127.0.0.1:9021";
+
+/// How the mock loader behaves. The default reads every payload to EOF, like
+/// a real loader.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockOptions {
+    pub bind: String,
+    /// Received payloads are saved here when set
+    pub output_dir: Option<PathBuf>,
+    /// Wait this long before handling a new connection, like a busy loader
+    pub accept_delay: Duration,
+    /// Read at most this many bytes at a time, 0 for no limit
+    pub read_chunk: usize,
+    /// Pause between reads, to simulate a slow link
+    pub read_delay: Duration,
+    /// Reset the connection after this many bytes
+    pub reset_after: Option<usize>,
+    /// Sent to the client as soon as it connects
+    pub banner: Option<String>,
+    /// Reset every connection without reading anything
+    pub refuse: bool,
+}
+
+impl Default for MockOptions {
+    fn default() -> Self {
+        Self {
+            bind: DEFAULT_MOCK_BIND.to_string(),
+            output_dir: None,
+            accept_delay: Duration::ZERO,
+            read_chunk: 0,
+            read_delay: Duration::ZERO,
+            reset_after: None,
+            banner: None,
+            refuse: false,
+        }
+    }
+}
+
+impl MockOptions {
+    /// Listen on a free localhost port, e.g. for tests
+    pub fn local() -> Self {
+        Self {
+            bind: "127.0.0.1:0".to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+/// A payload, or part of one, received by the mock loader
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReceivedPayload {
+    pub time: String, // Local time, e.g. `18:03:27`
+    pub client: String,
+    pub data: Vec<u8>,
+    pub sha256: String,
+    /// False when the connection was reset or failed before EOF
+    pub complete: bool,
+    pub saved_to: Option<PathBuf>,
+    pub error: Option<String>,
+}
+
+impl ReceivedPayload {
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "{} {} bytes from {}, sha256 {}",
+            self.time,
+            self.data.len(),
+            self.client,
+            self.sha256
+        );
+        if !self.complete {
+            summary.push_str(" (incomplete)");
+        }
+        if let Some(path) = &self.saved_to {
+            summary.push_str(&format!(", saved to '{}'", path.display()));
+        }
+        if let Some(error) = &self.error {
+            summary.push_str(&format!(", {}", error));
+        }
+        summary
+    }
+}
+
+/// A running mock loader; dropping it stops the server
+pub struct MockLoader {
+    server: ServerHandle,
+}
+
+impl MockLoader {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.server.local_addr
+    }
+}
+
+/// Start a fake payload loader on a background thread. Every connection that
+/// is read from is reported through `received`.
+pub fn start_mock(
+    options: MockOptions,
+    received: mpsc::Sender<ReceivedPayload>,
+) -> Result<MockLoader, String> {
+    if let Some(dir) = &options.output_dir {
+        std::fs::create_dir_all(dir).map_err(|e| {
+            format!(
+                "Failed to create output directory '{}': {}",
+                dir.display(),
+                e
+            )
+        })?;
+    }
+
+    let options = Arc::new(options);
+    let counter = Arc::new(AtomicU64::new(0));
+    let bind = options.bind.clone();
+    let server = spawn_server(&bind, "mock loader", move |stream, client| {
+        let options = options.clone();
+        let received = received.clone();
+        let sequence = counter.fetch_add(1, Ordering::SeqCst) + 1;
+        async move {
+            if let Some(payload) = handle_connection(stream, client, &options, sequence).await {
+                let _ = received.send(payload);
+            }
+        }
+    })?;
+
+    Ok(MockLoader { server })
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    client: SocketAddr,
+    options: &MockOptions,
+    sequence: u64,
+) -> Option<ReceivedPayload> {
+    tokio::time::sleep(options.accept_delay).await;
+
+    if options.refuse {
+        reset(stream);
+        return None;
+    }
+
+    if let Some(banner) = &options.banner {
+        let _ = stream.write_all(banner.as_bytes()).await;
+    }
+
+    let (data, complete, error) = read_payload(&mut stream, options).await;
+    if !complete && error.is_none() {
+        reset(stream);
+    }
+
+    let mut payload = ReceivedPayload {
+        time: chrono::Local::now().format("%H:%M:%S").to_string(),
+        client: client.ip().to_string(),
+        sha256: sha256_hex(&data),
+        data,
+        complete,
+        saved_to: None,
+        error,
+    };
+
+    if let Some(dir) = &options.output_dir {
+        let name = format!(
+            "payload-{}-{}-{}.bin",
+            chrono::Local::now().format("%Y%m%d-%H%M%S"),
+            sequence,
+            short_hash(&payload.sha256)
+        );
+        let path = dir.join(name);
+        match write_file_atomic(&path, &payload.data, "received payload") {
+            Ok(()) => payload.saved_to = Some(path),
+            Err(e) => payload.error = Some(e),
+        }
+    }
+
+    Some(payload)
+}
+
+/// Read until EOF or until `reset_after` bytes, returning the data, whether
+/// the client finished sending, and any read error
+async fn read_payload(
+    stream: &mut TcpStream,
+    options: &MockOptions,
+) -> (Vec<u8>, bool, Option<String>) {
+    let chunk_size = match options.read_chunk {
+        0 => 64 * 1024,
+        size => size,
+    };
+    let mut buffer = vec![0u8; chunk_size];
+    let mut data = Vec::new();
+
+    loop {
+        let mut wanted = buffer.len();
+        if let Some(limit) = options.reset_after {
+            if data.len() >= limit {
+                return (data, false, None);
+            }
+            wanted = wanted.min(limit - data.len());
+        }
+
+        match stream.read(&mut buffer[..wanted]).await {
+            Ok(0) => return (data, true, None),
+            Ok(read) => data.extend_from_slice(&buffer[..read]),
+            Err(e) => return (data, false, Some(format!("Read failed: {}", e))),
+        }
+
+        tokio::time::sleep(options.read_delay).await;
+    }
+}
+
+/// Close with a TCP reset instead of an orderly shutdown
+fn reset(stream: TcpStream) {
+    let _ = stream.set_linger(Some(Duration::ZERO));
+    drop(stream);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::FileTransfer;
+
+    const RECEIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn test_mock(options: MockOptions) -> (MockLoader, mpsc::Receiver<ReceivedPayload>) {
+        let (sender, receiver) = mpsc::channel();
+        let options = MockOptions {
+            bind: MockOptions::local().bind,
+            ..options
+        };
+        let mock = start_mock(options, sender).expect("Failed to start mock loader");
+        (mock, receiver)
+    }
+
+    async fn send_bytes(mock: &MockLoader, data: &[u8]) -> Result<usize, String> {
+        let temp_file = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+        std::fs::write(&temp_file, data).expect("Failed to write test data");
+        FileTransfer::new(
+            mock.local_addr().ip().to_string(),
+            mock.local_addr().port().to_string(),
+            temp_file.path().to_str().unwrap().to_string(),
+        )
+        .send_file()
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_records_payload_with_hash() {
+        let output_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let (mock, receiver) = test_mock(MockOptions {
+            output_dir: Some(output_dir.path().to_path_buf()),
+            ..MockOptions::default()
+        });
+
+        assert_eq!(send_bytes(&mock, b"abc").await, Ok(3));
+
+        let payload = receiver
+            .recv_timeout(RECEIVE_TIMEOUT)
+            .expect("Nothing received");
+        assert_eq!(payload.data, b"abc");
+        assert!(payload.complete);
+        assert_eq!(
+            payload.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(payload.client, "127.0.0.1");
+
+        let saved_to = payload.saved_to.clone().expect("Payload not saved");
+        assert!(saved_to.starts_with(output_dir.path()));
+        assert!(saved_to.to_str().unwrap().ends_with("-1-ba7816bf8f01.bin"));
+        assert_eq!(std::fs::read(&saved_to).expect("Failed to read"), b"abc");
+        assert!(payload
+            .summary()
+            .contains("3 bytes from 127.0.0.1, sha256 ba7816bf"));
+    }
+
+    #[tokio::test]
+    async fn test_slow_reads_receive_everything() {
+        let (mock, receiver) = test_mock(MockOptions {
+            read_chunk: 16,
+            read_delay: Duration::from_millis(1),
+            ..MockOptions::default()
+        });
+
+        let data = vec![0x5a; 1000];
+        assert_eq!(send_bytes(&mock, &data).await, Ok(1000));
+
+        let payload = receiver
+            .recv_timeout(RECEIVE_TIMEOUT)
+            .expect("Nothing received");
+        assert!(payload.complete);
+        assert_eq!(payload.data, data);
+    }
+
+    #[tokio::test]
+    async fn test_reset_mid_transfer() {
+        let (mock, receiver) = test_mock(MockOptions {
+            reset_after: Some(100),
+            ..MockOptions::default()
+        });
+
+        // The send may or may not notice the reset, depending on buffering
+        let _ = send_bytes(&mock, &[1u8; 100_000]).await;
+
+        let payload = receiver
+            .recv_timeout(RECEIVE_TIMEOUT)
+            .expect("Nothing received");
+        assert!(!payload.complete);
+        assert_eq!(payload.data.len(), 100);
+        assert!(payload.summary().contains("(incomplete)"));
+    }
+
+    #[tokio::test]
+    async fn test_banner_and_refuse() {
+        let (mock, receiver) = test_mock(MockOptions {
+            banner: Some("loader ready\n".to_string()),
+            ..MockOptions::default()
+        });
+        let mut stream = TcpStream::connect(mock.local_addr())
+            .await
+            .expect("Failed to connect");
+        let mut banner = [0u8; 13];
+        stream
+            .read_exact(&mut banner)
+            .await
+            .expect("No banner received");
+        assert_eq!(&banner, b"loader ready\n");
+        drop(stream);
+        assert!(receiver.recv_timeout(RECEIVE_TIMEOUT).is_ok());
+
+        let (mock, receiver) = test_mock(MockOptions {
+            refuse: true,
+            ..MockOptions::default()
+        });
+        let mut stream = TcpStream::connect(mock.local_addr())
+            .await
+            .expect("Failed to connect");
+        let mut buffer = [0u8; 1];
+        // Refused connections are reset without being read or recorded
+        assert!(!matches!(stream.read(&mut buffer).await, Ok(n) if n > 0));
+        assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn test_start_mock_errors() {
+        let (sender, _receiver) = mpsc::channel();
+        let options = MockOptions {
+            bind: "not an address".to_string(),
+            ..MockOptions::default()
+        };
+        let result = start_mock(options, sender);
+        assert!(result.err().unwrap().contains("Failed to bind mock loader"));
+    }
+}
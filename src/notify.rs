@@ -0,0 +1,260 @@
+use crate::status::InjectionStatus;
+use std::path::Path;
+
+/// Name notifications are shown under
+pub const APP_NAME: &str = "PS Payload Injector";
+
+/// Whether this platform can show desktop notifications
+pub const SUPPORTED: bool = cfg!(target_os = "linux");
+
+/// A finished injection or queue run, as shown in a desktop notification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub summary: String,
+    pub body: String,
+    pub is_error: bool,
+}
+
+impl Notification {
+    /// Result of sending `file_path` to `target`, none while it is still running
+    pub fn injection(target: &str, file_path: &str, status: &InjectionStatus) -> Option<Self> {
+        let file_name = Path::new(file_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(file_path);
+        match status {
+            InjectionStatus::Success(bytes_sent, _) => Some(Self {
+                summary: "Payload sent".to_string(),
+                body: format!("Sent {} bytes of {} to {}", bytes_sent, file_name, target),
+                is_error: false,
+            }),
+            InjectionStatus::Error(e) => Some(Self {
+                summary: "Injection failed".to_string(),
+                body: format!("{} to {}: {}", file_name, target, e),
+                is_error: true,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Summary of a queue run once every job has run
+    pub fn queue_finished(succeeded: usize, failed: usize, bytes_sent: usize) -> Self {
+        Self {
+            summary: "Queue finished".to_string(),
+            body: format!(
+                "{} sent, {} failed, {} bytes in total",
+                succeeded, failed, bytes_sent
+            ),
+            is_error: failed > 0,
+        }
+    }
+}
+
+/// Show a notification on the desktop. Waits for the notification server to
+/// answer, so call it off the UI thread.
+pub fn send(notification: &Notification) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    {
+        let connection = zbus::blocking::Connection::session()
+            .map_err(|e| format!("Failed to connect to the session bus: {}", e))?;
+        freedesktop::notify(&connection, notification).map(|_| ())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = notification;
+        Err("Desktop notifications are only supported on Linux".to_string())
+    }
+}
+
+/// The `org.freedesktop.Notifications` D-Bus interface
+#[cfg(target_os = "linux")]
+pub mod freedesktop {
+    use super::{Notification, APP_NAME};
+    use std::collections::HashMap;
+    use zbus::zvariant::Value;
+
+    pub const SERVICE: &str = "org.freedesktop.Notifications";
+    pub const PATH: &str = "/org/freedesktop/Notifications";
+
+    /// Urgency hint values
+    const URGENCY_NORMAL: u8 = 1;
+    const URGENCY_CRITICAL: u8 = 2;
+
+    /// Show `notification` through the notification server on `connection`.
+    /// Returns the id the server gave it.
+    pub fn notify(
+        connection: &zbus::blocking::Connection,
+        notification: &Notification,
+    ) -> Result<u32, String> {
+        let urgency = if notification.is_error {
+            URGENCY_CRITICAL
+        } else {
+            URGENCY_NORMAL
+        };
+        let mut hints: HashMap<&str, Value> = HashMap::new();
+        hints.insert("urgency", Value::U8(urgency));
+
+        let reply = connection
+            .call_method(
+                Some(SERVICE),
+                PATH,
+                Some(SERVICE),
+                "Notify",
+                &(
+                    APP_NAME,
+                    0u32, // Not replacing an earlier notification
+                    "",   // No icon
+                    notification.summary.as_str(),
+                    notification.body.as_str(),
+                    Vec::<&str>::new(), // No actions
+                    hints,
+                    -1i32, // Server's default timeout
+                ),
+            )
+            .map_err(|e| format!("Failed to show notification: {}", e))?;
+        reply
+            .body()
+            .deserialize()
+            .map_err(|e| format!("Unexpected reply from notification server: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum::Checksums;
+
+    #[test]
+    fn test_injection_notifications() {
+        let sent = Notification::injection(
+            "10.0.0.2:9021",
+            "/payloads/goldhen.bin",
+            &InjectionStatus::Success(
+                4096,
+                Some(Checksums {
+                    sha256: "ab12".to_string(),
+                    crc32: None,
+                }),
+            ),
+        )
+        .expect("No notification");
+        assert_eq!(sent.summary, "Payload sent");
+        assert_eq!(sent.body, "Sent 4096 bytes of goldhen.bin to 10.0.0.2:9021");
+        assert!(!sent.is_error);
+
+        let failed = Notification::injection(
+            "10.0.0.2:9021",
+            "release.zip!/ps5.elf",
+            &InjectionStatus::Error("Connection refused".to_string()),
+        )
+        .expect("No notification");
+        assert_eq!(failed.body, "ps5.elf to 10.0.0.2:9021: Connection refused");
+        assert!(failed.is_error);
+
+        let progress = InjectionStatus::InProgress("Sending...".to_string());
+        assert!(Notification::injection("10.0.0.2:9021", "/a.bin", &progress).is_none());
+
+        let queue = Notification::queue_finished(2, 1, 300);
+        assert_eq!(queue.body, "2 sent, 1 failed, 300 bytes in total");
+        assert!(queue.is_error);
+    }
+
+    /// Runs a private session bus with a notification server that records
+    /// what it is sent. Skipped when `dbus-daemon` is not installed.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_notify_over_session_bus() {
+        use std::collections::HashMap;
+        use std::io::{BufRead, BufReader};
+        use std::process::{Command, Stdio};
+        use std::sync::{Arc, Mutex};
+        use zbus::zvariant::OwnedValue;
+
+        type Received = Arc<Mutex<Vec<(String, String, String, u8)>>>;
+
+        struct Server {
+            received: Received,
+        }
+
+        #[zbus::interface(name = "org.freedesktop.Notifications")]
+        impl Server {
+            #[allow(clippy::too_many_arguments)]
+            fn notify(
+                &self,
+                app_name: String,
+                _replaces_id: u32,
+                _app_icon: String,
+                summary: String,
+                body: String,
+                _actions: Vec<String>,
+                hints: HashMap<String, OwnedValue>,
+                _expire_timeout: i32,
+            ) -> u32 {
+                let urgency = hints
+                    .get("urgency")
+                    .and_then(|value| u8::try_from(value).ok())
+                    .unwrap_or_default();
+                let mut received = self.received.lock().unwrap();
+                received.push((app_name, summary, body, urgency));
+                received.len() as u32
+            }
+        }
+
+        let Ok(mut daemon) = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address=1"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            eprintln!("dbus-daemon not found, skipping");
+            return;
+        };
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .expect("Failed to read bus address");
+
+        let received = Received::default();
+        let connect = || zbus::blocking::connection::Builder::address(address.trim()).unwrap();
+        let _server = connect()
+            .name(freedesktop::SERVICE)
+            .unwrap()
+            .serve_at(
+                freedesktop::PATH,
+                Server {
+                    received: received.clone(),
+                },
+            )
+            .unwrap()
+            .build()
+            .expect("Failed to start notification server");
+        let client = connect().build().expect("Failed to connect to bus");
+
+        let sent = Notification::injection(
+            "10.0.0.2:9021",
+            "/payloads/goldhen.bin",
+            &InjectionStatus::Success(4096, None),
+        )
+        .unwrap();
+        assert_eq!(freedesktop::notify(&client, &sent), Ok(1));
+        let failed = Notification::queue_finished(0, 1, 0);
+        assert_eq!(freedesktop::notify(&client, &failed), Ok(2));
+
+        let _ = daemon.kill();
+        let _ = daemon.wait();
+
+        let received = received.lock().unwrap();
+        assert_eq!(
+            received[0],
+            (
+                APP_NAME.to_string(),
+                "Payload sent".to_string(),
+                "Sent 4096 bytes of goldhen.bin to 10.0.0.2:9021".to_string(),
+                1
+            )
+        );
+        assert_eq!(received[1].1, "Queue finished");
+        assert_eq!(received[1].3, 2);
+    }
+}
@@ -0,0 +1,98 @@
+/// Score how well `query` matches `text`, or `None` if it does not.
+///
+/// Every character of the query has to appear in the text in order, ignoring
+/// case. Matches at the start of words and runs of consecutive characters
+/// score higher, gaps between matched characters lower.
+pub fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+
+    let text: Vec<char> = text.chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous_match: Option<usize> = None;
+    for (index, c) in text.iter().enumerate() {
+        if next == query.len() {
+            break;
+        }
+        if !c.to_lowercase().eq(std::iter::once(query[next])) {
+            continue;
+        }
+
+        let word_start = index == 0 || !text[index - 1].is_alphanumeric();
+        score += 1;
+        if word_start {
+            score += 8;
+        }
+        match previous_match {
+            Some(previous) if previous + 1 == index => score += 5,
+            Some(previous) => score -= (index - previous - 1).min(5) as i64,
+            None => score -= index.min(10) as i64,
+        }
+        previous_match = Some(index);
+        next += 1;
+    }
+
+    (next == query.len()).then_some(score)
+}
+
+/// Items matching `query`, best match first. Items that score the same keep
+/// their order.
+pub fn rank<'a, T>(items: &'a [T], query: &str, text: impl Fn(&T) -> &str) -> Vec<&'a T> {
+    let mut matches: Vec<(i64, &T)> = items
+        .iter()
+        .filter_map(|item| fuzzy_score(query, text(item)).map(|score| (score, item)))
+        .collect();
+    matches.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    matches.into_iter().map(|(_, item)| item).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("", "Inject Payload"), Some(0));
+        assert!(fuzzy_score("inj", "Inject Payload").is_some());
+        assert!(fuzzy_score("IP", "Inject Payload").is_some());
+        assert!(fuzzy_score("load cfg", "Load Config").is_some());
+        assert!(fuzzy_score("pi", "Inject Payload").is_none());
+        assert!(fuzzy_score("xyz", "Inject Payload").is_none());
+
+        // Word starts and runs beat scattered matches
+        let word_starts = fuzzy_score("lc", "Load Config").unwrap();
+        let scattered = fuzzy_score("lc", "Toggle Watch").unwrap();
+        assert!(word_starts > scattered);
+        let run = fuzzy_score("save", "Save Config").unwrap();
+        let spread = fuzzy_score("save", "Stop After Video Export").unwrap();
+        assert!(run > spread);
+    }
+
+    #[test]
+    fn test_rank() {
+        let items = [
+            "Reset",
+            "Profile: ps5-lab",
+            "Payload: /payloads/etaHEN.bin",
+            "Profile: ps4",
+        ];
+
+        let ranked = rank(&items, "ps4", |item| item);
+        assert_eq!(ranked, [&"Profile: ps4"]);
+
+        let ranked = rank(&items, "prof", |item| item);
+        assert_eq!(ranked, [&"Profile: ps5-lab", &"Profile: ps4"]);
+
+        let ranked = rank(&items, "eta", |item| item);
+        assert_eq!(ranked, [&"Payload: /payloads/etaHEN.bin"]);
+
+        assert_eq!(rank(&items, "", |item| item).len(), items.len());
+    }
+}
@@ -0,0 +1,261 @@
+use crate::checksum;
+use crate::config::DataFile;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The imported manifest is kept alongside the auto-save config
+pub const MANIFEST_FILE_NAME: &str = "payload_manifest.json";
+
+/// A vetted payload build
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct KnownPayload {
+    pub name: String,
+    pub version: String,
+    pub sha256: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform: Option<String>, // e.g. `ps4` or `ps5`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_port: Option<u16>,
+    /// File name the payload is released as, used to spot corrupted copies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_name: Option<String>,
+}
+
+impl KnownPayload {
+    pub fn title(&self) -> String {
+        format!("{} {}", self.name, self.version)
+    }
+}
+
+/// List of known payloads, shared as a JSON or TOML file
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Manifest {
+    #[serde(default)]
+    pub payloads: Vec<KnownPayload>,
+}
+
+/// Result of checking a file against the manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PayloadCheck {
+    Recognised(KnownPayload),
+    /// The file is named like a known payload but its hash does not match
+    HashMismatch(KnownPayload),
+    Unknown,
+}
+
+impl PayloadCheck {
+    pub fn message(&self) -> Option<String> {
+        match self {
+            PayloadCheck::Recognised(payload) => Some(format!("Recognised: {}", payload.title())),
+            PayloadCheck::HashMismatch(payload) => {
+                Some(format!("Hash mismatch for {}", payload.name))
+            }
+            PayloadCheck::Unknown => None,
+        }
+    }
+}
+
+impl DataFile for Manifest {
+    const FILE_NAME: &'static str = MANIFEST_FILE_NAME;
+    const WHAT: &'static str = "manifest";
+
+    /// Parse a manifest, as TOML if `path` ends in `.toml` and as JSON otherwise
+    fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read manifest '{}': {}", path.display(), e))?;
+
+        let is_toml = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"));
+        let manifest: Self = if is_toml {
+            toml::from_str(&content).map_err(|e| e.to_string())
+        } else {
+            serde_json::from_str(&content).map_err(|e| e.to_string())
+        }
+        .map_err(|e| format!("Failed to parse manifest '{}': {}", path.display(), e))?;
+
+        manifest
+            .validated()
+            .map_err(|e| format!("Invalid manifest '{}': {}", path.display(), e))
+    }
+}
+
+impl Manifest {
+    /// Load and validate a manifest and make it the one used for checks
+    pub fn import<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let manifest = Self::load_from_file(path)?;
+        manifest.save()?;
+        Ok(manifest)
+    }
+
+    /// Check hashes and normalise them to lowercase
+    pub(crate) fn validated(mut self) -> Result<Self, String> {
+        for payload in &mut self.payloads {
+            let sha256 = payload.sha256.trim().to_ascii_lowercase();
+            if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!(
+                    "'{}' does not have a valid SHA-256 ('{}')",
+                    payload.title(),
+                    payload.sha256
+                ));
+            }
+            payload.sha256 = sha256;
+        }
+        Ok(self)
+    }
+
+    /// Look a payload up by the SHA-256 of its contents, falling back to its
+    /// file name to report copies that do not match
+    pub fn check(&self, file_name: &str, sha256: &str) -> PayloadCheck {
+        if let Some(payload) = self
+            .payloads
+            .iter()
+            .find(|payload| payload.sha256.eq_ignore_ascii_case(sha256))
+        {
+            return PayloadCheck::Recognised(payload.clone());
+        }
+
+        let named = self.payloads.iter().find(|payload| {
+            payload
+                .file_name
+                .as_deref()
+                .is_some_and(|name| name.eq_ignore_ascii_case(file_name))
+        });
+        match named {
+            Some(payload) => PayloadCheck::HashMismatch(payload.clone()),
+            None => PayloadCheck::Unknown,
+        }
+    }
+
+    /// Hash a file and check it
+    pub fn check_file<P: AsRef<Path>>(&self, path: P) -> Result<PayloadCheck, String> {
+        let path = path.as_ref();
+        let checksums = checksum::file_checksums(path, false)?;
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        Ok(self.check(file_name, &checksums.sha256))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    fn test_manifest() -> Manifest {
+        Manifest {
+            payloads: vec![KnownPayload {
+                name: "GoldHEN".to_string(),
+                version: "2.4b18".to_string(),
+                sha256: ABC_SHA256.to_string(),
+                platform: Some("ps4".to_string()),
+                default_port: Some(9090),
+                file_name: Some("goldhen.bin".to_string()),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let manifest = test_manifest();
+
+        let check = manifest.check("renamed.bin", &ABC_SHA256.to_uppercase());
+        assert_eq!(
+            check.message().as_deref(),
+            Some("Recognised: GoldHEN 2.4b18")
+        );
+
+        let check = manifest.check("GoldHEN.BIN", &"0".repeat(64));
+        assert!(matches!(check, PayloadCheck::HashMismatch(_)));
+        assert_eq!(
+            check.message().as_deref(),
+            Some("Hash mismatch for GoldHEN")
+        );
+
+        let check = manifest.check("other.elf", &"0".repeat(64));
+        assert_eq!(check, PayloadCheck::Unknown);
+        assert_eq!(check.message(), None);
+    }
+
+    #[test]
+    fn test_load_json_and_toml() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+
+        let json_path = temp_dir.path().join("manifest.json");
+        std::fs::write(
+            &json_path,
+            format!(
+                r#"{{"payloads": [{{"name": "GoldHEN", "version": "2.4b18",
+                    "sha256": "{}", "platform": "ps4", "default_port": 9090,
+                    "file_name": "goldhen.bin"}}]}}"#,
+                ABC_SHA256.to_uppercase()
+            ),
+        )
+        .expect("Failed to write manifest");
+        let manifest = Manifest::load_from_file(&json_path).expect("Failed to load JSON");
+        assert_eq!(manifest, test_manifest());
+
+        let toml_path = temp_dir.path().join("manifest.toml");
+        std::fs::write(
+            &toml_path,
+            format!(
+                "[[payloads]]\nname = \"GoldHEN\"\nversion = \"2.4b18\"\nsha256 = \"{}\"\n\
+                 platform = \"ps4\"\ndefault_port = 9090\nfile_name = \"goldhen.bin\"\n",
+                ABC_SHA256
+            ),
+        )
+        .expect("Failed to write manifest");
+        let manifest = Manifest::load_from_file(&toml_path).expect("Failed to load TOML");
+        assert_eq!(manifest, test_manifest());
+    }
+
+    #[test]
+    fn test_load_rejects_bad_manifests() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("manifest.json");
+
+        std::fs::write(
+            &path,
+            r#"{"payloads": [{"name": "X", "version": "1", "sha256": "abc"}]}"#,
+        )
+        .expect("Failed to write manifest");
+        let result = Manifest::load_from_file(&path);
+        assert!(result
+            .unwrap_err()
+            .contains("'X 1' does not have a valid SHA-256"));
+
+        std::fs::write(&path, "not json").expect("Failed to write manifest");
+        let result = Manifest::load_from_file(&path);
+        assert!(result.unwrap_err().contains("Failed to parse manifest"));
+
+        let result = Manifest::load_from_file(temp_dir.path().join("missing.toml"));
+        assert!(result.unwrap_err().contains("Failed to read manifest"));
+    }
+
+    #[test]
+    fn test_import_and_check_file() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let source = temp_dir.path().join("team.json");
+        test_manifest()
+            .save_to_file(&source)
+            .expect("Failed to save manifest");
+
+        let imported = Manifest::import(&source).expect("Failed to import");
+        assert_eq!(imported, test_manifest());
+        assert_eq!(Manifest::load_or_default(), test_manifest());
+        let _ = std::fs::remove_file(Manifest::default_path());
+
+        let payload = temp_dir.path().join("goldhen.bin");
+        std::fs::write(&payload, b"abc").expect("Failed to write payload");
+        let check = imported.check_file(&payload).expect("Failed to check");
+        assert!(matches!(check, PayloadCheck::Recognised(_)));
+
+        std::fs::write(&payload, b"corrupted").expect("Failed to write payload");
+        let check = imported.check_file(&payload).expect("Failed to check");
+        assert!(matches!(check, PayloadCheck::HashMismatch(_)));
+    }
+}
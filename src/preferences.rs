@@ -0,0 +1,170 @@
+use crate::config::DataFile;
+use serde::{Deserialize, Serialize};
+
+pub const PREFERENCES_FILE_NAME: &str = "preferences.json";
+
+/// Window size used when none was saved
+pub const DEFAULT_WINDOW_SIZE: [f32; 2] = [760.0, 520.0];
+
+/// The window cannot be made smaller than this
+pub const MIN_WINDOW_SIZE: [f32; 2] = [480.0, 320.0];
+
+/// Smallest and largest UI scale offered
+pub const MIN_UI_SCALE: f32 = 0.75;
+pub const MAX_UI_SCALE: f32 = 2.0;
+
+/// Colour scheme of the GUI
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    System, // Follow the OS setting
+    Dark,
+    Light,
+}
+
+/// Position and size of the main window, in logical points
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct WindowGeometry {
+    /// Not known on every platform, e.g. Wayland does not report it
+    #[serde(default)]
+    pub position: Option<[f32; 2]>,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl WindowGeometry {
+    /// Size to open the window at, no smaller than `MIN_WINDOW_SIZE`
+    pub fn size(&self) -> [f32; 2] {
+        [
+            self.width.max(MIN_WINDOW_SIZE[0]),
+            self.height.max(MIN_WINDOW_SIZE[1]),
+        ]
+    }
+}
+
+/// How the GUI looked when it was last closed. Kept apart from the config so
+/// that loading or resetting a config does not move the window.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Preferences {
+    #[serde(default)]
+    pub window: Option<WindowGeometry>,
+    #[serde(default = "default_true")]
+    pub side_panel_open: bool, // Queue and payload host
+    #[serde(default = "default_true")]
+    pub log_panel_open: bool, // History below the status line
+    #[serde(default)]
+    pub theme: Theme,
+    /// Size of text and spacing relative to the default
+    #[serde(default = "default_ui_scale")]
+    pub ui_scale: f32,
+    /// Stronger status colours that stay readable on either theme
+    #[serde(default)]
+    pub high_contrast: bool,
+    /// Show a desktop notification when an injection or the queue finishes
+    #[serde(default)]
+    pub notifications: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_ui_scale() -> f32 {
+    1.0
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            window: None,
+            side_panel_open: true,
+            log_panel_open: true,
+            theme: Theme::default(),
+            ui_scale: default_ui_scale(),
+            high_contrast: false,
+            notifications: false,
+        }
+    }
+}
+
+impl DataFile for Preferences {
+    const FILE_NAME: &'static str = PREFERENCES_FILE_NAME;
+    const WHAT: &'static str = "preferences";
+}
+
+impl Preferences {
+    /// UI scale within the supported range, whatever the file says
+    pub fn ui_scale(&self) -> f32 {
+        if self.ui_scale.is_finite() {
+            self.ui_scale.clamp(MIN_UI_SCALE, MAX_UI_SCALE)
+        } else {
+            default_ui_scale()
+        }
+    }
+
+    /// Window size to open with
+    pub fn window_size(&self) -> [f32; 2] {
+        self.window
+            .map(|window| window.size())
+            .unwrap_or(DEFAULT_WINDOW_SIZE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip_and_defaults() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join(PREFERENCES_FILE_NAME);
+
+        let preferences = Preferences {
+            window: Some(WindowGeometry {
+                position: Some([40.0, 60.0]),
+                width: 900.0,
+                height: 600.0,
+            }),
+            side_panel_open: false,
+            log_panel_open: true,
+            theme: Theme::Light,
+            ui_scale: 1.25,
+            high_contrast: true,
+            notifications: true,
+        };
+        preferences
+            .save_to_file(&path)
+            .expect("Failed to save preferences");
+        let loaded = Preferences::load_from_file(&path).expect("Failed to load preferences");
+        assert_eq!(loaded, preferences);
+        assert_eq!(loaded.window_size(), [900.0, 600.0]);
+
+        // Missing fields take defaults
+        std::fs::write(&path, "{}").expect("Failed to write preferences");
+        let loaded = Preferences::load_from_file(&path).expect("Failed to load preferences");
+        assert_eq!(loaded, Preferences::default());
+        assert_eq!(loaded.window_size(), DEFAULT_WINDOW_SIZE);
+        assert_eq!(loaded.theme, Theme::System);
+        assert_eq!(loaded.ui_scale(), 1.0);
+
+        std::fs::write(&path, r#"{"theme": "dark", "ui_scale": 9.0}"#)
+            .expect("Failed to write preferences");
+        let loaded = Preferences::load_from_file(&path).expect("Failed to load preferences");
+        assert_eq!(loaded.theme, Theme::Dark);
+        assert_eq!(loaded.ui_scale(), MAX_UI_SCALE);
+
+        // A window saved tiny opens at the minimum size
+        let tiny = WindowGeometry {
+            position: None,
+            width: 10.0,
+            height: 10.0,
+        };
+        assert_eq!(tiny.size(), MIN_WINDOW_SIZE);
+
+        std::fs::write(&path, "not json").expect("Failed to write preferences");
+        assert!(Preferences::load_from_file(&path)
+            .unwrap_err()
+            .contains("Failed to parse preferences"));
+    }
+}
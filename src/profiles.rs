@@ -0,0 +1,101 @@
+use crate::config::DataFile;
+use serde::{Deserialize, Serialize};
+
+pub const PROFILES_FILE_NAME: &str = "profiles.json";
+
+/// A named target and payload
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Profile {
+    pub name: String,
+    pub ip: String,
+    pub port: String,
+    pub file_path: String,
+}
+
+impl Profile {
+    pub fn new(name: &str, ip: &str, port: &str, file_path: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ip: ip.to_string(),
+            port: port.to_string(),
+            file_path: file_path.to_string(),
+        }
+    }
+}
+
+/// Saved profiles, kept in their own file so they survive turning auto-save off
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Profiles {
+    pub profiles: Vec<Profile>,
+}
+
+impl DataFile for Profiles {
+    const FILE_NAME: &'static str = PROFILES_FILE_NAME;
+    const WHAT: &'static str = "profiles file";
+}
+
+impl Profiles {
+    pub fn find(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// Add a profile, replacing any existing one with the same name
+    pub fn upsert(&mut self, profile: Profile) {
+        match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    /// Remove a profile by name, returning whether it existed
+    pub fn remove(&mut self, name: &str) -> bool {
+        let count = self.profiles.len();
+        self.profiles.retain(|profile| profile.name != name);
+        self.profiles.len() != count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upsert_find_remove() {
+        let mut profiles = Profiles::default();
+        profiles.upsert(Profile::new("PS4", "192.168.1.4", "9020", "/a.bin"));
+        profiles.upsert(Profile::new("PS5", "192.168.1.5", "9021", "/b.elf"));
+        profiles.upsert(Profile::new("PS4", "192.168.1.40", "9020", "/c.bin"));
+
+        assert_eq!(profiles.profiles.len(), 2);
+        assert_eq!(profiles.find("PS4").unwrap().ip, "192.168.1.40");
+        assert_eq!(profiles.profiles[0].name, "PS4"); // Replaced in place
+
+        assert!(profiles.remove("PS4"));
+        assert!(!profiles.remove("PS4"));
+        assert!(profiles.find("PS4").is_none());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join(PROFILES_FILE_NAME);
+
+        let mut profiles = Profiles::default();
+        profiles.upsert(Profile::new("Living room", "10.0.0.2", "9021", "/x y.elf"));
+        profiles.save_to_file(&path).expect("Failed to save");
+
+        let loaded = Profiles::load_from_file(&path).expect("Failed to load");
+        assert_eq!(loaded, profiles);
+    }
+
+    #[test]
+    fn test_load_invalid_profiles() {
+        let temp_file = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+        std::fs::write(&temp_file, "[1, 2]").expect("Failed to write");
+
+        let result = Profiles::load_from_file(temp_file.path());
+        assert!(result
+            .unwrap_err()
+            .contains("Failed to parse profiles file"));
+    }
+}
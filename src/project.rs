@@ -0,0 +1,143 @@
+use crate::config;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Looked for in the current directory and every parent, like `Cargo.toml`
+pub const PROJECT_FILE_NAME: &str = ".ps-injector.toml";
+
+/// Values a project file can set, e.g.
+///
+/// ```toml
+/// ip = "192.168.1.20"
+/// port = "9021"
+/// artifact = "build/payload.elf"
+/// ```
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectFile {
+    pub ip: Option<String>,
+    #[serde(default, deserialize_with = "config::optional_port_text")]
+    pub port: Option<String>,
+    /// Payload the project builds, relative to the project file's directory
+    #[serde(alias = "file_path")]
+    pub artifact: Option<PathBuf>,
+}
+
+/// A project file found for the working directory
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Project {
+    pub path: PathBuf,
+    pub file: ProjectFile,
+}
+
+impl Project {
+    /// Nearest project file in `start` or one of its parents
+    pub fn find(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|directory| directory.join(PROJECT_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    /// Project for the current directory, if there is one
+    pub fn discover() -> Result<Option<Self>, String> {
+        let Ok(current_dir) = std::env::current_dir() else {
+            return Ok(None);
+        };
+        Self::find(&current_dir).map(Self::load).transpose()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read project file '{}': {}", path.display(), e))?;
+
+        let file = toml::from_str(&content).map_err(|e| {
+            format!(
+                "Failed to parse project file '{}': {}",
+                path.display(),
+                config::toml_error(&content, &e)
+            )
+        })?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    /// The artifact path, resolved against the project file's directory
+    pub fn artifact_path(&self) -> Option<String> {
+        let artifact = self.file.artifact.as_ref()?;
+        let directory = self.path.parent().unwrap_or(Path::new(""));
+        Some(directory.join(artifact).display().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_found_in_parent_directories() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let nested = temp_dir.path().join("src").join("loader");
+        fs::create_dir_all(&nested).expect("Failed to create directories");
+        assert_eq!(Project::find(&nested), None);
+
+        let path = temp_dir.path().join(PROJECT_FILE_NAME);
+        fs::write(
+            &path,
+            "# Bench console\nip = \"10.0.0.2\"\nartifact = \"build/payload.elf\"\n",
+        )
+        .expect("Failed to write project file");
+        assert_eq!(Project::find(&nested), Some(path.clone()));
+
+        let project = Project::load(&path).expect("Failed to load project");
+        assert_eq!(project.file.ip.as_deref(), Some("10.0.0.2"));
+        assert_eq!(project.file.port, None);
+        assert_eq!(
+            project.artifact_path(),
+            Some(
+                temp_dir
+                    .path()
+                    .join("build/payload.elf")
+                    .display()
+                    .to_string()
+            )
+        );
+
+        // The nearest file wins
+        let inner = nested.join(PROJECT_FILE_NAME);
+        fs::write(&inner, "port = \"9020\"\n").expect("Failed to write project file");
+        assert_eq!(Project::find(&nested), Some(inner));
+    }
+
+    #[test]
+    fn test_invalid_project_files() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join(PROJECT_FILE_NAME);
+
+        fs::write(&path, "ip = \"10.0.0.2\"\ntarget = \"ps5\"\n").expect("Failed to write");
+        let error = Project::load(&path).unwrap_err();
+        assert!(error.contains("unknown field `target`"), "{}", error);
+        assert!(error.contains("at line 2 column 1"), "{}", error);
+
+        fs::write(&path, "port = true\n").expect("Failed to write");
+        let error = Project::load(&path).unwrap_err();
+        assert!(
+            error.starts_with("Failed to parse project file"),
+            "{}",
+            error
+        );
+        assert!(
+            error.contains("expected a port number or string at line 1 column 8"),
+            "{}",
+            error
+        );
+
+        fs::write(&path, "port = 9021\n").expect("Failed to write");
+        let project = Project::load(&path).expect("Failed to load project");
+        assert_eq!(project.file.port.as_deref(), Some("9021"));
+    }
+}
@@ -0,0 +1,254 @@
+use crate::config::DataFile;
+use crate::status::InjectionStatus;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub const QUEUE_FILE_NAME: &str = "queue.json";
+
+/// One payload waiting to be, or already, sent to a target
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct QueueJob {
+    pub id: u64,
+    pub ip: String,
+    pub port: String,
+    pub file_path: String,
+    /// Paused jobs are skipped until resumed
+    #[serde(default)]
+    pub paused: bool,
+    pub status: InjectionStatus, // Idle until the job has run
+}
+
+impl QueueJob {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.status,
+            InjectionStatus::Success(_, _) | InjectionStatus::Error(_)
+        )
+    }
+
+    pub fn is_running(&self) -> bool {
+        matches!(self.status, InjectionStatus::InProgress(_))
+    }
+
+    /// Waiting to run and not paused
+    pub fn is_runnable(&self) -> bool {
+        !self.paused && matches!(self.status, InjectionStatus::Idle)
+    }
+
+    pub fn title(&self) -> String {
+        let filename = Path::new(&self.file_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or(&self.file_path);
+        format!("{} -> {}:{}", filename, self.ip, self.port)
+    }
+}
+
+/// Jobs in the order they are sent, kept in `queue.json` next to the config
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Queue {
+    pub jobs: Vec<QueueJob>,
+    #[serde(default)]
+    next_id: u64,
+}
+
+impl DataFile for Queue {
+    const FILE_NAME: &'static str = QUEUE_FILE_NAME;
+    const WHAT: &'static str = "queue";
+
+    /// Load the saved queue, or an empty one if there is none or it is
+    /// unreadable. Jobs that were running when the app stopped are set back
+    /// to waiting, so an interrupted batch can be continued.
+    fn load_or_default() -> Self {
+        let mut queue = Self::load_from_file(Self::default_path()).unwrap_or_default();
+        for job in queue.jobs.iter_mut().filter(|job| job.is_running()) {
+            job.status = InjectionStatus::Idle;
+        }
+        queue
+    }
+}
+
+impl Queue {
+    /// Append a job and return its id
+    pub fn add(&mut self, ip: &str, port: &str, file_path: &str) -> u64 {
+        self.next_id = self
+            .next_id
+            .max(self.jobs.iter().map(|job| job.id).max().unwrap_or(0))
+            + 1;
+        self.jobs.push(QueueJob {
+            id: self.next_id,
+            ip: ip.to_string(),
+            port: port.to_string(),
+            file_path: file_path.to_string(),
+            paused: false,
+            status: InjectionStatus::Idle,
+        });
+        self.next_id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&QueueJob> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    fn get_mut(&mut self, id: u64) -> Option<&mut QueueJob> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    /// Remove a job; running jobs cannot be removed
+    pub fn remove(&mut self, id: u64) -> bool {
+        let before = self.jobs.len();
+        self.jobs.retain(|job| job.id != id || job.is_running());
+        self.jobs.len() != before
+    }
+
+    /// Move a job one place towards the front (`-1`) or back (`1`)
+    pub fn move_job(&mut self, id: u64, offset: isize) -> bool {
+        let Some(index) = self.jobs.iter().position(|job| job.id == id) else {
+            return false;
+        };
+        let Some(target) = index
+            .checked_add_signed(offset)
+            .filter(|target| *target < self.jobs.len())
+        else {
+            return false;
+        };
+        self.jobs.swap(index, target);
+        true
+    }
+
+    pub fn set_paused(&mut self, id: u64, paused: bool) -> bool {
+        match self.get_mut(id) {
+            Some(job) => {
+                job.paused = paused;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Queue a finished job to run again
+    pub fn retry(&mut self, id: u64) -> bool {
+        match self.get_mut(id) {
+            Some(job) if job.is_finished() => {
+                job.status = InjectionStatus::Idle;
+                job.paused = false;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn set_status(&mut self, id: u64, status: InjectionStatus) {
+        if let Some(job) = self.get_mut(id) {
+            job.status = status;
+        }
+    }
+
+    /// Drop every job that succeeded
+    pub fn clear_succeeded(&mut self) {
+        self.jobs
+            .retain(|job| !matches!(job.status, InjectionStatus::Success(_, _)));
+    }
+
+    /// The job to send next
+    pub fn next_runnable(&self) -> Option<&QueueJob> {
+        self.jobs.iter().find(|job| job.is_runnable())
+    }
+
+    pub fn running(&self) -> Option<&QueueJob> {
+        self.jobs.iter().find(|job| job.is_running())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checksum::Checksums;
+
+    fn ids(queue: &Queue) -> Vec<u64> {
+        queue.jobs.iter().map(|job| job.id).collect()
+    }
+
+    #[test]
+    fn test_reorder_pause_and_remove() {
+        let mut queue = Queue::default();
+        let first = queue.add("10.0.0.2", "9021", "/a.elf");
+        let second = queue.add("10.0.0.2", "9021", "/b.elf");
+        let third = queue.add("10.0.0.3", "9090", "/c.bin");
+        assert_eq!(ids(&queue), [first, second, third]);
+
+        assert!(queue.move_job(third, -1));
+        assert_eq!(ids(&queue), [first, third, second]);
+        assert!(!queue.move_job(first, -1));
+        assert!(!queue.move_job(second, 1));
+
+        assert!(queue.set_paused(first, true));
+        assert_eq!(queue.next_runnable().map(|job| job.id), Some(third));
+        queue.set_status(third, InjectionStatus::InProgress("Sending...".to_string()));
+        assert_eq!(queue.running().map(|job| job.id), Some(third));
+        assert_eq!(queue.next_runnable().map(|job| job.id), Some(second));
+
+        // A running job stays
+        assert!(!queue.remove(third));
+        assert!(queue.remove(second));
+        assert!(!queue.remove(second));
+        assert_eq!(ids(&queue), [first, third]);
+
+        // Ids are not reused after removal
+        let fourth = queue.add("10.0.0.2", "9021", "/d.elf");
+        assert!(fourth > third);
+        assert_eq!(queue.get(fourth).unwrap().title(), "d.elf -> 10.0.0.2:9021");
+    }
+
+    #[test]
+    fn test_retry_and_clear() {
+        let mut queue = Queue::default();
+        let ok = queue.add("10.0.0.2", "9021", "/a.elf");
+        let failed = queue.add("10.0.0.2", "9021", "/b.elf");
+        queue.set_status(ok, InjectionStatus::Success(3, None));
+        queue.set_status(failed, InjectionStatus::Error("refused".to_string()));
+        queue.set_paused(failed, true);
+        assert!(queue.next_runnable().is_none());
+
+        assert!(queue.retry(failed));
+        let job = queue.get(failed).unwrap();
+        assert!(job.is_runnable());
+
+        // Only finished jobs can be retried
+        assert!(!queue.retry(failed));
+
+        queue.clear_succeeded();
+        assert_eq!(ids(&queue), [failed]);
+    }
+
+    #[test]
+    fn test_interrupted_jobs_resume_after_restart() {
+        let mut queue = Queue::default();
+        let done = queue.add("10.0.0.2", "9021", "/a.elf");
+        let interrupted = queue.add("10.0.0.2", "9021", "/b.elf");
+        queue.set_status(
+            done,
+            InjectionStatus::Success(
+                3,
+                Some(Checksums {
+                    sha256: "ab12".to_string(),
+                    crc32: None,
+                }),
+            ),
+        );
+        queue.set_status(
+            interrupted,
+            InjectionStatus::InProgress("Sending...".to_string()),
+        );
+        queue.save().expect("Failed to save queue");
+
+        let loaded = Queue::load_or_default();
+        let _ = std::fs::remove_file(Queue::default_path());
+        assert_eq!(ids(&loaded), [done, interrupted]);
+        assert!(matches!(
+            &loaded.get(done).unwrap().status,
+            InjectionStatus::Success(3, Some(checksums)) if checksums.sha256 == "ab12"
+        ));
+        assert_eq!(loaded.next_runnable().map(|job| job.id), Some(interrupted));
+    }
+}
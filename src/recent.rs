@@ -0,0 +1,136 @@
+use crate::config::DataFile;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub const RECENT_FILE_NAME: &str = "recent.json";
+
+/// Entries kept in each most-recently-used list
+pub const MAX_RECENT: usize = 10;
+
+/// An address payloads were sent to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RecentTarget {
+    pub ip: String,
+    pub port: String,
+}
+
+impl fmt::Display for RecentTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.ip, self.port)
+    }
+}
+
+/// Recently used payloads and targets, most recent first, and the folder the
+/// Browse dialog was last in. Kept in their own file so they survive turning
+/// auto-save off and are not replaced by loading a config.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recent {
+    #[serde(default)]
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub targets: Vec<RecentTarget>,
+    #[serde(default)]
+    pub last_directory: Option<PathBuf>,
+}
+
+impl DataFile for Recent {
+    const FILE_NAME: &'static str = RECENT_FILE_NAME;
+    const WHAT: &'static str = "recent lists file";
+}
+
+impl Recent {
+    /// Move a payload to the top of the list. Returns whether the list changed.
+    pub fn add_file(&mut self, file_path: &str) -> bool {
+        let file_path = file_path.trim();
+        if file_path.is_empty() {
+            return false;
+        }
+        push_front(&mut self.files, file_path.to_string())
+    }
+
+    /// Move a target to the top of the list. Returns whether the list changed.
+    pub fn add_target(&mut self, ip: &str, port: &str) -> bool {
+        let target = RecentTarget {
+            ip: ip.trim().to_string(),
+            port: port.trim().to_string(),
+        };
+        if target.ip.is_empty() || target.port.is_empty() {
+            return false;
+        }
+        push_front(&mut self.targets, target)
+    }
+
+    /// Remember the folder a file was picked from. Returns whether it changed.
+    pub fn set_directory_of(&mut self, file: &Path) -> bool {
+        let directory = file.parent().map(Path::to_path_buf);
+        if directory.is_none() || directory == self.last_directory {
+            return false;
+        }
+        self.last_directory = directory;
+        true
+    }
+}
+
+/// Put `item` first, dropping an older copy and anything past `MAX_RECENT`
+fn push_front<T: PartialEq>(list: &mut Vec<T>, item: T) -> bool {
+    if list.first() == Some(&item) {
+        return false;
+    }
+    list.retain(|existing| *existing != item);
+    list.insert(0, item);
+    list.truncate(MAX_RECENT);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_most_recent_first() {
+        let mut recent = Recent::default();
+        assert!(recent.add_file("/a.elf"));
+        assert!(recent.add_file("/b.elf"));
+        assert!(recent.add_file("/a.elf"));
+        assert!(!recent.add_file("/a.elf"));
+        assert!(!recent.add_file("  "));
+        assert_eq!(recent.files, ["/a.elf", "/b.elf"]);
+
+        for i in 0..MAX_RECENT + 3 {
+            recent.add_target(&format!("10.0.0.{}", i), "9021");
+        }
+        assert!(!recent.add_target("10.0.0.1", ""));
+        assert_eq!(recent.targets.len(), MAX_RECENT);
+        assert_eq!(recent.targets[0].to_string(), "10.0.0.12:9021");
+
+        assert!(recent.set_directory_of(Path::new("/payloads/a.elf")));
+        assert!(!recent.set_directory_of(Path::new("/payloads/b.elf")));
+        assert_eq!(recent.last_directory, Some(PathBuf::from("/payloads")));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join(RECENT_FILE_NAME);
+
+        let mut recent = Recent::default();
+        recent.add_file("/x y.elf");
+        recent.add_target("192.168.1.4", "9020");
+        recent.set_directory_of(Path::new("/payloads/x y.elf"));
+        recent.save_to_file(&path).expect("Failed to save");
+
+        let loaded = Recent::load_from_file(&path).expect("Failed to load");
+        assert_eq!(loaded, recent);
+
+        // Missing lists are empty
+        std::fs::write(&path, "{}").expect("Failed to write");
+        let loaded = Recent::load_from_file(&path).expect("Failed to load");
+        assert_eq!(loaded, Recent::default());
+
+        std::fs::write(&path, "[1, 2]").expect("Failed to write");
+        assert!(Recent::load_from_file(&path)
+            .unwrap_err()
+            .contains("Failed to parse recent lists file"));
+    }
+}
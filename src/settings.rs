@@ -0,0 +1,398 @@
+use crate::config::{
+    Config, ConfigLayer, DEFAULT_AUTO_SAVE_ENABLED, DEFAULT_FILE_PATH, DEFAULT_IP, DEFAULT_PORT,
+};
+use crate::project::Project;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Team-wide config read before the user config, unless another is given
+pub const SHARED_CONFIG_FILE_NAME: &str = "shared_config.json";
+
+// Environment variables that override the config files
+pub const ENV_IP: &str = "PS_INJECTOR_IP";
+pub const ENV_PORT: &str = "PS_INJECTOR_PORT";
+pub const ENV_FILE_PATH: &str = "PS_INJECTOR_FILE";
+pub const ENV_SHARED_CONFIG: &str = "PS_INJECTOR_SHARED_CONFIG";
+
+static OVERRIDES: OnceLock<Overrides> = OnceLock::new();
+
+/// Where an effective setting came from, lowest precedence first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    SharedFile(PathBuf),
+    UserFile(PathBuf),
+    /// `.ps-injector.toml` found from the working directory
+    ProjectFile(PathBuf),
+    /// Named environment variable
+    Env(&'static str),
+    /// Named command-line flag
+    Cli(&'static str),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "built-in default"),
+            Source::SharedFile(path) => write!(f, "shared config '{}'", path.display()),
+            Source::UserFile(path) => write!(f, "user config '{}'", path.display()),
+            Source::ProjectFile(path) => write!(f, "project file '{}'", path.display()),
+            Source::Env(name) => write!(f, "environment variable {}", name),
+            Source::Cli(flag) => write!(f, "command-line flag {}", flag),
+        }
+    }
+}
+
+/// An effective value and the layer that set it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting<T> {
+    pub value: T,
+    pub source: Source,
+}
+
+impl<T> Setting<T> {
+    fn new(value: T) -> Self {
+        Self {
+            value,
+            source: Source::Default,
+        }
+    }
+
+    /// Replace the value if a higher layer sets one
+    fn apply(&mut self, value: Option<T>, source: impl FnOnce() -> Source) {
+        if let Some(value) = value {
+            self.value = value;
+            self.source = source();
+        }
+    }
+}
+
+/// Layers given on the command line
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Overrides {
+    /// Shared config file to use instead of `SHARED_CONFIG_FILE_NAME`
+    pub shared_config: Option<PathBuf>,
+    pub ip: Option<String>,
+    pub port: Option<String>,
+    pub file_path: Option<String>,
+}
+
+/// Startup values resolved from built-in defaults, the shared config, the
+/// user config, the project file, environment variables and command-line
+/// flags, in that order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Settings {
+    pub ip: Setting<String>,
+    pub port: Setting<String>,
+    pub file_path: Setting<String>,
+    /// Only read from config files
+    pub auto_save_enabled: Setting<bool>,
+    /// Project file in use, if one was found
+    pub project: Option<PathBuf>,
+    /// Config files that could not be read; their values are skipped
+    pub errors: Vec<String>,
+    /// Problems that did not keep a file's values out, like a user config
+    /// that could not be upgraded in place
+    pub warnings: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            ip: Setting::new(DEFAULT_IP.to_string()),
+            port: Setting::new(DEFAULT_PORT.to_string()),
+            file_path: Setting::new(DEFAULT_FILE_PATH.to_string()),
+            auto_save_enabled: Setting::new(DEFAULT_AUTO_SAVE_ENABLED),
+            project: None,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Set the command-line layer. Must be called before settings are first
+    /// loaded; later calls are ignored.
+    pub fn set_overrides(overrides: Overrides) {
+        let _ = OVERRIDES.set(overrides);
+    }
+
+    /// The active command-line layer
+    pub fn overrides() -> Overrides {
+        OVERRIDES.get().cloned().unwrap_or_default()
+    }
+
+    /// Shared config file in use: `--shared-config`, then `PS_INJECTOR_SHARED_CONFIG`,
+    /// then `shared_config.json` beside the user config if it exists
+    pub fn shared_config_path(overrides: &Overrides) -> Option<PathBuf> {
+        overrides
+            .shared_config
+            .clone()
+            .or_else(|| std::env::var_os(ENV_SHARED_CONFIG).map(PathBuf::from))
+            .or_else(|| {
+                let path = Config::data_file_path(SHARED_CONFIG_FILE_NAME);
+                path.exists().then_some(path)
+            })
+    }
+
+    /// Resolve every layer for this process
+    pub fn load() -> Self {
+        let overrides = Self::overrides();
+        // Read errors are reported when the user config is layered below
+        let upgrade = Config::upgrade_auto_save();
+        let project = Project::discover();
+
+        let mut settings = Self::resolve(
+            Self::shared_config_path(&overrides).as_deref(),
+            &Config::default_auto_save_path(),
+            project.as_ref().ok().and_then(Option::as_ref),
+            |name| std::env::var(name).ok(),
+            &overrides,
+        );
+        if let Err(e) = project {
+            settings.errors.push(e);
+        }
+        if let Err(e) = upgrade {
+            settings.warnings.push(e);
+        }
+        settings
+    }
+
+    /// Resolve the layers from the given files, environment and flags.
+    /// A missing user config is skipped; a missing shared config is an error.
+    pub fn resolve(
+        shared_config: Option<&Path>,
+        user_config: &Path,
+        project: Option<&Project>,
+        env: impl Fn(&str) -> Option<String>,
+        overrides: &Overrides,
+    ) -> Self {
+        let mut settings = Self::default();
+
+        if let Some(path) = shared_config {
+            settings.apply_file(path, Source::SharedFile(path.to_path_buf()));
+        }
+        if user_config.exists() {
+            settings.apply_file(user_config, Source::UserFile(user_config.to_path_buf()));
+        }
+        if let Some(project) = project {
+            let source = || Source::ProjectFile(project.path.clone());
+            settings.ip.apply(project.file.ip.clone(), source);
+            settings.port.apply(project.file.port.clone(), source);
+            settings.file_path.apply(project.artifact_path(), source);
+            settings.project = Some(project.path.clone());
+        }
+
+        settings.ip.apply(env(ENV_IP), || Source::Env(ENV_IP));
+        settings.port.apply(env(ENV_PORT), || Source::Env(ENV_PORT));
+        settings
+            .file_path
+            .apply(env(ENV_FILE_PATH), || Source::Env(ENV_FILE_PATH));
+
+        settings
+            .ip
+            .apply(overrides.ip.clone(), || Source::Cli("--ip"));
+        settings
+            .port
+            .apply(overrides.port.clone(), || Source::Cli("--port"));
+        settings
+            .file_path
+            .apply(overrides.file_path.clone(), || Source::Cli("--file"));
+
+        settings
+    }
+
+    fn apply_file(&mut self, path: &Path, source: Source) {
+        let layer = match Config::load_layer(path) {
+            Ok(layer) => layer,
+            Err(e) => {
+                self.errors.push(e);
+                return;
+            }
+        };
+        let ConfigLayer {
+            ip,
+            port,
+            file_path,
+            auto_save_enabled,
+            version: _,
+        } = layer;
+        self.ip.apply(ip, || source.clone());
+        self.port.apply(port, || source.clone());
+        self.file_path.apply(file_path, || source.clone());
+        self.auto_save_enabled
+            .apply(auto_save_enabled, || source.clone());
+    }
+
+    /// Error to show at startup, if a config file could not be read or upgraded
+    pub fn startup_error(&self) -> Option<String> {
+        let mut problems = Vec::new();
+        if !self.errors.is_empty() {
+            problems.push(format!(
+                "Using default settings: {}",
+                self.errors.join("; ")
+            ));
+        }
+        problems.extend(self.warnings.iter().cloned());
+        (!problems.is_empty()).then(|| problems.join("; "))
+    }
+
+    /// Each setting's name, effective value and source, for display
+    pub fn describe(&self) -> Vec<(&'static str, String, &Source)> {
+        vec![
+            ("ip", self.ip.value.clone(), &self.ip.source),
+            ("port", self.port.value.clone(), &self.port.source),
+            (
+                "file_path",
+                self.file_path.value.clone(),
+                &self.file_path.source,
+            ),
+            (
+                "auto_save_enabled",
+                self.auto_save_enabled.value.to_string(),
+                &self.auto_save_enabled.source,
+            ),
+        ]
+    }
+
+    /// Settings with the given values, as if they were the built-in defaults
+    #[cfg(test)]
+    pub fn with_values(ip: &str, port: &str, file_path: &str, auto_save_enabled: bool) -> Self {
+        Self {
+            ip: Setting::new(ip.to_string()),
+            port: Setting::new(port.to_string()),
+            file_path: Setting::new(file_path.to_string()),
+            auto_save_enabled: Setting::new(auto_save_enabled),
+            project: None,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+
+    #[test]
+    fn test_layers_in_order() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let shared = temp_dir.path().join(SHARED_CONFIG_FILE_NAME);
+        let user = temp_dir.path().join("app_config.json");
+        fs::write(
+            &shared,
+            r#"{"ip": "10.0.0.2", "port": "9021", "file_path": "/team/goldhen.bin"}"#,
+        )
+        .expect("Failed to write shared config");
+        fs::write(&user, r#"{"port": "9020", "auto_save_enabled": true}"#)
+            .expect("Failed to write user config");
+
+        let env = HashMap::from([(ENV_FILE_PATH, "/env/payload.elf")]);
+        let overrides = Overrides {
+            ip: Some("10.0.0.9".to_string()),
+            ..Overrides::default()
+        };
+        let settings = Settings::resolve(
+            Some(&shared),
+            &user,
+            None,
+            |name| env.get(name).map(|value| value.to_string()),
+            &overrides,
+        );
+
+        assert_eq!(settings.ip.value, "10.0.0.9");
+        assert_eq!(settings.ip.source, Source::Cli("--ip"));
+        assert_eq!(settings.port.value, "9020");
+        assert_eq!(settings.port.source, Source::UserFile(user.clone()));
+        assert_eq!(settings.file_path.value, "/env/payload.elf");
+        assert_eq!(
+            settings.file_path.source.to_string(),
+            "environment variable PS_INJECTOR_FILE"
+        );
+        assert!(settings.auto_save_enabled.value);
+        assert_eq!(settings.startup_error(), None);
+
+        // Without the higher layers the shared file shows through
+        let settings =
+            Settings::resolve(Some(&shared), &user, None, |_| None, &Overrides::default());
+        assert_eq!(settings.ip.source, Source::SharedFile(shared.clone()));
+        assert_eq!(
+            settings.describe()[2],
+            (
+                "file_path",
+                "/team/goldhen.bin".to_string(),
+                &Source::SharedFile(shared)
+            )
+        );
+    }
+
+    #[test]
+    fn test_project_file_over_user_config() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let user = temp_dir.path().join("app_config.json");
+        fs::write(
+            &user,
+            r#"{"ip": "10.0.0.2", "port": "9020", "file_path": "/a.bin"}"#,
+        )
+        .expect("Failed to write user config");
+        let project_path = temp_dir.path().join(crate::project::PROJECT_FILE_NAME);
+        fs::write(
+            &project_path,
+            "port = 9021\nartifact = \"build/payload.elf\"\n",
+        )
+        .expect("Failed to write project file");
+        let project = Project::load(&project_path).expect("Failed to load project");
+
+        let env = |name: &str| (name == ENV_PORT).then(|| "9090".to_string());
+        let settings = Settings::resolve(None, &user, Some(&project), env, &Overrides::default());
+
+        assert_eq!(settings.project, Some(project_path.clone()));
+        assert_eq!(settings.ip.source, Source::UserFile(user));
+        // The environment still overrides the project
+        assert_eq!(settings.port.value, "9090");
+        assert_eq!(
+            settings.file_path.value,
+            temp_dir
+                .path()
+                .join("build/payload.elf")
+                .display()
+                .to_string()
+        );
+        assert_eq!(
+            settings.file_path.source.to_string(),
+            format!("project file '{}'", project_path.display())
+        );
+    }
+
+    #[test]
+    fn test_unreadable_layers_are_skipped() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let missing_user = temp_dir.path().join("app_config.json");
+
+        let settings =
+            Settings::resolve(None, &missing_user, None, |_| None, &Overrides::default());
+        assert_eq!(settings, Settings::default());
+
+        // A shared config that was asked for but is missing is reported
+        let missing_shared = temp_dir.path().join(SHARED_CONFIG_FILE_NAME);
+        let broken_user = temp_dir.path().join("broken.json");
+        fs::write(&broken_user, "{ not json").expect("Failed to write user config");
+        let settings = Settings::resolve(
+            Some(&missing_shared),
+            &broken_user,
+            None,
+            |name| (name == ENV_PORT).then(|| "9090".to_string()),
+            &Overrides::default(),
+        );
+
+        assert_eq!(settings.ip.value, DEFAULT_IP);
+        assert_eq!(settings.port.source, Source::Env(ENV_PORT));
+        assert_eq!(settings.errors.len(), 2);
+        let error = settings.startup_error().expect("Expected a startup error");
+        assert!(error.starts_with("Using default settings: Failed to read config file"));
+        assert!(error.contains("Failed to parse config file"));
+    }
+}
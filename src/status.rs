@@ -0,0 +1,38 @@
+use crate::checksum::Checksums;
+use serde::{Deserialize, Serialize};
+
+/// Progress and results reported by handlers, watch mode and the control API
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum InjectionStatus {
+    Idle,
+    InProgress(String),
+    Success(usize, Option<Checksums>), // bytes sent, checksums of the sent bytes
+    Error(String),
+    ConfigLoaded(String, String, String), // ip, port, file_path
+    ConfigSaved(String),                  // message
+}
+
+impl InjectionStatus {
+    /// SHA-256 of the sent bytes, for a success that has one
+    pub fn sha256(&self) -> Option<&str> {
+        match self {
+            InjectionStatus::Success(_, Some(checksums)) => Some(&checksums.sha256),
+            _ => None,
+        }
+    }
+
+    /// Text shown for the status
+    pub fn message(&self) -> String {
+        match self {
+            InjectionStatus::Idle => "Idle".to_string(),
+            InjectionStatus::InProgress(msg) => msg.clone(),
+            InjectionStatus::Success(bytes, None) => format!("Success! Sent {} bytes", bytes),
+            InjectionStatus::Success(bytes, Some(checksums)) => {
+                format!("Success! Sent {} bytes ({})", bytes, checksums.summary())
+            }
+            InjectionStatus::Error(msg) => format!("Error: {}", msg),
+            InjectionStatus::ConfigLoaded(_, _, _) => "Config loaded successfully".to_string(),
+            InjectionStatus::ConfigSaved(msg) => msg.clone(),
+        }
+    }
+}
@@ -1,24 +1,96 @@
+use crate::activity::{self, ActivityLog, Severity, Source};
+use crate::archive::{self, ArchiveEntry, ArchivePath};
+use crate::bundle::{Bundle, ImportMode, ImportReport, Section as BundleSection};
+use crate::checksum::{self, Checksums};
+use crate::config::DataFile;
 use crate::config::{DEFAULT_FILE_PATH, DEFAULT_IP, DEFAULT_PORT};
+use crate::history::{History, HistoryEntry, Trigger};
+use crate::inspect::{self, FoundString, Landmark};
+use crate::network::host::{self, HostOptions, PayloadHost, RequestLogEntry, DEFAULT_HOST_PORT};
+use crate::notify::{self, Notification};
+use crate::palette;
+use crate::payload::{Manifest, PayloadCheck};
+use crate::preferences::{Preferences, Theme, WindowGeometry, MAX_UI_SCALE, MIN_UI_SCALE};
+use crate::profiles::{Profile, Profiles};
+use crate::queue::Queue;
+use crate::recent::Recent;
+use crate::settings::{Setting, Settings, Source as SettingSource};
+pub use crate::status::InjectionStatus;
+use crate::watch::{self, FileStamp, WatchHandle, WatchOptions};
 use eframe::egui;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
-
-#[derive(Debug)]
-pub enum InjectionStatus {
-    Idle,
-    InProgress(String),
-    Success(usize),
-    Error(String),
-    ConfigLoaded(String, String, String), // ip, port, file_path
-    ConfigSaved(String),                  // message
-}
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+/// How long to wait after the last edit before auto-saving, so typing in a
+/// field does not write the config file on every key press
+pub const AUTO_SAVE_DEBOUNCE: Duration = Duration::from_millis(750);
+
+/// How often to check for watch results while watch mode is on
+const WATCH_REPAINT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Number of history entries shown in the history section
+const HISTORY_ROWS: usize = 10;
+
+/// Oldest payload host requests are dropped from the log beyond this many
+const HOST_LOG_ROWS: usize = 100;
+
+/// Text sizes at a UI scale of 1
+const BODY_TEXT_SIZE: f32 = 16.0;
+const HEADING_TEXT_SIZE: f32 = 20.0;
+const SMALL_TEXT_SIZE: f32 = 14.0;
+
+/// Matches shown in the command palette
+const PALETTE_ROWS: usize = 10;
+
+const INJECT_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::Enter);
+const BROWSE_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::O);
+const SAVE_CONFIG_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::S);
+const LOAD_CONFIG_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::L);
+const PALETTE_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::COMMAND, egui::Key::K);
+const CANCEL_SHORTCUT: egui::KeyboardShortcut =
+    egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::Escape);
+
+/// Ctrl+1..9 apply the first nine profiles
+const PROFILE_KEYS: [egui::Key; 9] = [
+    egui::Key::Num1,
+    egui::Key::Num2,
+    egui::Key::Num3,
+    egui::Key::Num4,
+    egui::Key::Num5,
+    egui::Key::Num6,
+    egui::Key::Num7,
+    egui::Key::Num8,
+    egui::Key::Num9,
+];
+
+/// Actions offered by the command palette
+const PALETTE_ACTIONS: [(&str, Command); 11] = [
+    ("Inject Payload", Command::Inject),
+    ("Browse for Payload...", Command::Browse),
+    ("Save Config", Command::SaveConfig),
+    ("Load Config", Command::LoadConfig),
+    ("Cancel Injection", Command::Cancel),
+    ("Reset", Command::Reset),
+    ("Toggle Watch File", Command::ToggleWatch),
+    ("Add Current to Queue", Command::QueueCurrent),
+    ("Start or Pause Queue", Command::ToggleQueue),
+    ("Import Manifest...", Command::ImportManifest),
+    ("Toggle Payload Viewer", Command::ToggleViewer),
+];
 
 pub struct App<F, G, H, I, J, K>
 where
-    F: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>) + Send + 'static,
+    F: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>, oneshot::Receiver<()>) + Send + 'static,
     G: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>) + Send + 'static,
     H: Fn(mpsc::Sender<InjectionStatus>) + Send + 'static,
-    I: Fn(&str, &str, &str) + Send + 'static,
+    I: Fn(Option<&str>, Option<&str>, Option<&str>) + Send + 'static,
     J: Fn(bool) + Send + 'static,
     K: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>) + Send + 'static,
 {
@@ -32,17 +104,257 @@ where
     auto_save_fn: I,
     auto_save_preference_fn: J,
     reset_fn: K,
-    receiver: Option<mpsc::Receiver<InjectionStatus>>,
+    receiver: Option<mpsc::Receiver<InjectionStatus>>, // Statuses of the manual injection
+    config_receiver: Option<mpsc::Receiver<InjectionStatus>>, // Of saving, loading and resetting the config
+    cancel: Option<oneshot::Sender<()>>,                      // Stops the manual injection
     values_changed: bool,    // Track if values have changed since last save
     auto_save_enabled: bool, // Track if auto-save is enabled
+    auto_save_due: Option<Instant>, // When the pending auto-save should run
+    watch_enabled: bool,     // Re-inject whenever the payload file changes
+    watch_require_elf: bool, // Only auto-send files that are valid ELFs
+    watch: Option<WatchHandle>,
+    watch_receiver: Option<mpsc::Receiver<InjectionStatus>>,
+    history: History,
+    pending_injection: Option<HistoryEntry>, // Manual injection awaiting its result
+    profiles: Profiles,
+    profile_name: String, // Name of the selected profile, or of one about to be saved
+    api_url: Option<String>, // Address of the control API, if running
+    api_receiver: Option<mpsc::Receiver<InjectionStatus>>, // Statuses of API-triggered injections
+    host_root: String,
+    host_port: String,
+    host: Option<PayloadHost>,
+    host_receiver: Option<mpsc::Receiver<RequestLogEntry>>,
+    host_log: Vec<RequestLogEntry>,
+    hashed_file: Option<(String, FileStamp)>, // File the checksums below are for
+    file_checksums: Option<Result<Checksums, String>>,
+    checksum_receiver: Option<mpsc::Receiver<Result<Checksums, String>>>,
+    manifest: Manifest, // Known payloads the selected file is checked against
+    archive_entries: Option<ArchiveListing>, // Payloads in the selected archive
+    queue: Queue,
+    queue_running: bool, // Start the next waiting job once the current one is done
+    queue_receiver: Option<mpsc::Receiver<InjectionStatus>>, // Statuses of the running job
+    queue_job: Option<(u64, HistoryEntry)>, // Running job and its history entry
+    queue_cancel: Option<oneshot::Sender<()>>, // Stops the running job
+    palette: Option<Palette>, // Open command palette
+    preferences: Preferences, // Window geometry, open panels and appearance
+    notify_errors: mpsc::Receiver<(Source, String)>, // Notifications that could not be shown
+    notify_error_sender: mpsc::Sender<(Source, String)>,
+    applied_appearance: Option<(Theme, f32)>, // Theme and UI scale the style was set up for
+    dark_mode: bool,                          // Whether the theme in use is dark
+    activity: ActivityLog, // Every status shown, with when and where it came from
+    activity_severity: Option<Severity>, // Only show entries of this severity
+    activity_search: String,
+    log_tab: LogTab,
+    viewer_open: bool,                 // Show the hex and strings viewer
+    payload_view: Option<PayloadView>, // Contents of the selected file, read while the viewer is open
+    viewed_file: Option<(String, Option<FileStamp>)>, // File the view is, or is being, read from
+    payload_view_receiver: Option<mpsc::Receiver<PayloadView>>,
+    hex_lines: Option<HexPage>, // The page of the hex dump last read
+    hex_page_receiver: Option<mpsc::Receiver<HexPage>>,
+    viewer_tab: ViewerTab,
+    hex_page: usize,
+    strings_search: String,
+    recent: Recent, // Recently used payloads and targets, and the last Browse folder
+    startup_settings: Settings, // Where the values the app started with came from
+    queue_results: QueueResults, // Jobs finished since the queue last ran out
+    bundle_import: Option<BundleImport>, // Settings bundle being imported
+}
+
+/// A settings bundle picked for import and how to import each of its sections
+struct BundleImport {
+    path: PathBuf,
+    choices: Vec<(BundleSection, Option<ImportMode>)>, // `None` skips the section
+    report: Option<ImportReport>,                      // Set once imported
+}
+
+/// Jobs finished in a queue run, for the notification at its end
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct QueueResults {
+    succeeded: usize,
+    failed: usize,
+    bytes_sent: usize,
+}
+
+/// What the payload viewer shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ViewerTab {
+    Hex,
+    Strings,
+}
+
+/// Strings and ELF landmarks are looked for in this much of the start of a payload
+const VIEWER_SCAN_BYTES: usize = 16 * 1024 * 1024;
+
+/// The selected file as scanned for the payload viewer. The hex dump is read
+/// a page at a time, so large payloads are never held in memory.
+struct PayloadView {
+    file_path: String,
+    size: Result<u64, String>,
+    strings: Vec<FoundString>,
+    landmarks: Vec<Landmark>, // ELF segments and sections
+}
+
+impl PayloadView {
+    fn read(file_path: &str) -> Self {
+        let (size, strings, landmarks) =
+            match archive::read_payload_range(file_path, 0, VIEWER_SCAN_BYTES) {
+                Ok((head, size)) => (
+                    Ok(size),
+                    inspect::extract_strings(&head, inspect::MIN_STRING_LEN),
+                    inspect::elf_landmarks(&head),
+                ),
+                Err(e) => (Err(e), Vec::new(), Vec::new()),
+            };
+        Self {
+            file_path: file_path.to_string(),
+            size,
+            strings,
+            landmarks,
+        }
+    }
+
+    /// Whether strings and landmarks only cover the start of the file
+    fn is_partly_scanned(&self) -> bool {
+        self.size
+            .as_ref()
+            .is_ok_and(|&size| size > VIEWER_SCAN_BYTES as u64)
+    }
+}
+
+/// One page of the hex dump, read from the viewed file
+struct HexPage {
+    file: (String, Option<FileStamp>), // File and version it was read from
+    page: usize,
+    lines: Result<Vec<String>, String>,
+}
+
+impl HexPage {
+    fn read(file: (String, Option<FileStamp>), page: usize) -> Self {
+        let offset = page * inspect::HEX_PAGE_BYTES;
+        let lines = archive::read_payload_range(&file.0, offset as u64, inspect::HEX_PAGE_BYTES)
+            .map(|(bytes, _)| inspect::hex_lines(&bytes, offset));
+        Self { file, page, lines }
+    }
+}
+
+/// What the panel below the form shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogTab {
+    History,
+    Activity,
+}
+
+/// Colours of statuses and results
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct StatusColors {
+    error: egui::Color32,
+    success: egui::Color32,
+    progress: egui::Color32,
+    idle: egui::Color32,
+}
+
+impl StatusColors {
+    const STANDARD: Self = Self {
+        error: egui::Color32::from_rgb(220, 80, 80),
+        success: egui::Color32::from_rgb(80, 180, 80),
+        progress: egui::Color32::from_rgb(255, 165, 0), // Orange
+        idle: egui::Color32::from_rgb(120, 120, 120),
+    };
+
+    const HIGH_CONTRAST_DARK: Self = Self {
+        error: egui::Color32::from_rgb(255, 110, 110),
+        success: egui::Color32::from_rgb(110, 255, 110),
+        progress: egui::Color32::from_rgb(255, 215, 0),
+        idle: egui::Color32::from_rgb(230, 230, 230),
+    };
+
+    const HIGH_CONTRAST_LIGHT: Self = Self {
+        error: egui::Color32::from_rgb(170, 0, 0),
+        success: egui::Color32::from_rgb(0, 110, 0),
+        progress: egui::Color32::from_rgb(150, 75, 0),
+        idle: egui::Color32::from_rgb(40, 40, 40),
+    };
+
+    fn new(high_contrast: bool, dark_mode: bool) -> Self {
+        match (high_contrast, dark_mode) {
+            (false, _) => Self::STANDARD,
+            (true, true) => Self::HIGH_CONTRAST_DARK,
+            (true, false) => Self::HIGH_CONTRAST_LIGHT,
+        }
+    }
+
+    fn of(&self, status: &InjectionStatus) -> egui::Color32 {
+        match status {
+            InjectionStatus::Error(_) => self.error,
+            InjectionStatus::InProgress(_) => self.progress,
+            InjectionStatus::Idle => self.idle,
+            // Config messages are shown like a success
+            InjectionStatus::Success(_, _)
+            | InjectionStatus::ConfigLoaded(_, _, _)
+            | InjectionStatus::ConfigSaved(_) => self.success,
+        }
+    }
+}
+
+/// Something the user can do from a shortcut or the command palette
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    Inject,
+    Browse,
+    SaveConfig,
+    LoadConfig,
+    Cancel,
+    Reset,
+    ToggleWatch,
+    QueueCurrent,
+    ToggleQueue,
+    ImportManifest,
+    ToggleViewer,
+    ApplyProfile(String),
+    SelectPayload(String),
+    TogglePalette,
+}
+
+impl Command {
+    fn shortcut(&self) -> Option<egui::KeyboardShortcut> {
+        match self {
+            Command::Inject => Some(INJECT_SHORTCUT),
+            Command::Browse => Some(BROWSE_SHORTCUT),
+            Command::SaveConfig => Some(SAVE_CONFIG_SHORTCUT),
+            Command::LoadConfig => Some(LOAD_CONFIG_SHORTCUT),
+            Command::Cancel => Some(CANCEL_SHORTCUT),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Palette {
+    query: String,
+    selected: usize, // Index into the matches shown
+}
+
+/// Change to a queued job requested from the queue section
+enum QueueAction {
+    Move(u64, isize),
+    SetPaused(u64, bool),
+    Retry(u64),
+    Remove(u64),
+}
+
+/// Payload candidates of an archive, listed when it was last changed
+struct ArchiveListing {
+    archive: PathBuf,
+    stamp: Option<FileStamp>,
+    entries: Result<Vec<ArchiveEntry>, String>,
 }
 
 impl<F, G, H, I, J, K> App<F, G, H, I, J, K>
 where
-    F: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>) + Send + 'static,
+    F: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>, oneshot::Receiver<()>) + Send + 'static,
     G: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>) + Send + 'static,
     H: Fn(mpsc::Sender<InjectionStatus>) + Send + 'static,
-    I: Fn(&str, &str, &str) + Send + 'static,
+    I: Fn(Option<&str>, Option<&str>, Option<&str>) + Send + 'static,
     J: Fn(bool) + Send + 'static,
     K: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>) + Send + 'static,
 {
@@ -53,12 +365,13 @@ where
         auto_save_fn: I,
         auto_save_preference_fn: J,
         reset_fn: K,
-        startup_config: (String, String, String, bool),
+        startup_settings: Settings,
     ) -> Self {
+        let (notify_error_sender, notify_errors) = mpsc::channel();
         Self {
-            ip: startup_config.0,
-            port: startup_config.1,
-            file_path: startup_config.2,
+            ip: startup_settings.ip.value.clone(),
+            port: startup_settings.port.value.clone(),
+            file_path: startup_settings.file_path.value.clone(),
             status: InjectionStatus::Idle,
             inject_fn,
             save_config_fn,
@@ -67,563 +380,3443 @@ where
             auto_save_preference_fn,
             reset_fn,
             receiver: None,
+            config_receiver: None,
+            cancel: None,
             values_changed: false,
-            auto_save_enabled: startup_config.3,
+            auto_save_enabled: startup_settings.auto_save_enabled.value,
+            auto_save_due: None,
+            watch_enabled: false,
+            watch_require_elf: false,
+            watch: None,
+            watch_receiver: None,
+            history: History::load_or_default(),
+            pending_injection: None,
+            profiles: Profiles::load_or_default(),
+            profile_name: String::new(),
+            api_url: None,
+            api_receiver: None,
+            host_root: String::new(),
+            host_port: DEFAULT_HOST_PORT.to_string(),
+            host: None,
+            host_receiver: None,
+            host_log: Vec::new(),
+            hashed_file: None,
+            file_checksums: None,
+            checksum_receiver: None,
+            manifest: Manifest::load_or_default(),
+            archive_entries: None,
+            queue: Queue::load_or_default(),
+            queue_running: false,
+            queue_receiver: None,
+            queue_job: None,
+            queue_cancel: None,
+            palette: None,
+            preferences: Preferences::load_or_default(),
+            notify_errors,
+            notify_error_sender,
+            applied_appearance: None,
+            dark_mode: true,
+            activity: ActivityLog::default(),
+            activity_severity: None,
+            activity_search: String::new(),
+            log_tab: LogTab::History,
+            viewer_open: false,
+            payload_view: None,
+            viewed_file: None,
+            payload_view_receiver: None,
+            hex_lines: None,
+            hex_page_receiver: None,
+            viewer_tab: ViewerTab::Hex,
+            hex_page: 0,
+            strings_search: String::new(),
+            recent: Recent::load_or_default(),
+            queue_results: QueueResults::default(),
+            startup_settings,
+            bundle_import: None,
         }
     }
+
+    /// Show a status and record it in the activity log
+    fn set_status(&mut self, source: Source, status: InjectionStatus) {
+        self.activity.record(source, &status);
+        self.status = status;
+    }
+
+    /// Log a problem that must not replace the status line, like a file that
+    /// could not be written once an injection had finished
+    fn log_error(&mut self, source: Source, error: String) {
+        self.activity.record(source, &InjectionStatus::Error(error));
+    }
+
+    /// Start with the given status instead of Idle, e.g. to report a startup error
+    pub fn with_status(mut self, status: InjectionStatus) -> Self {
+        self.set_status(Source::Manual, status);
+        self
+    }
+
+    /// Show the control API address and the statuses of injections it starts
+    pub fn with_remote_api(
+        mut self,
+        url: String,
+        receiver: mpsc::Receiver<InjectionStatus>,
+    ) -> Self {
+        self.api_url = Some(url);
+        self.api_receiver = Some(receiver);
+        self
+    }
 }
 
 impl<F, G, H, I, J, K> eframe::App for App<F, G, H, I, J, K>
 where
-    F: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>) + Send + 'static,
+    F: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>, oneshot::Receiver<()>) + Send + 'static,
     G: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>) + Send + 'static,
     H: Fn(mpsc::Sender<InjectionStatus>) + Send + 'static,
-    I: Fn(&str, &str, &str) + Send + 'static,
+    I: Fn(Option<&str>, Option<&str>, Option<&str>) + Send + 'static,
     J: Fn(bool) + Send + 'static,
     K: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>) + Send + 'static,
 {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // Check for status updates from the async task
-        if let Some(receiver) = &self.receiver {
-            if let Ok(new_status) = receiver.try_recv() {
-                // Handle config loading to populate fields
-                if let InjectionStatus::ConfigLoaded(ip, port, file_path) = &new_status {
-                    self.ip = ip.clone();
-                    self.port = port.clone();
-                    self.file_path = file_path.clone();
-                    self.values_changed = true; // Mark as changed for auto-save
-                }
-                self.status = new_status;
-                ctx.request_repaint(); // Request UI update
+        if let Some(command) = self.shortcut_command(ctx) {
+            self.run_command(command);
+        }
+
+        // Check for status updates from the async tasks
+        if self.receive_statuses() {
+            ctx.request_repaint(); // Request UI update
+        }
+
+        let notify_errors: Vec<(Source, String)> = self.notify_errors.try_iter().collect();
+        for (source, e) in notify_errors {
+            self.log_error(source, e);
+        }
+
+        // Results of automatic sends from watch mode
+        self.sync_watch();
+        if let Some(receiver) = &self.watch_receiver {
+            let statuses: Vec<InjectionStatus> = receiver.try_iter().collect();
+            let mut sent = false;
+            for new_status in statuses {
+                sent |= matches!(
+                    new_status,
+                    InjectionStatus::Success(_, _) | InjectionStatus::Error(_)
+                );
+                self.set_status(Source::Watch, new_status);
             }
+            if sent {
+                // The watcher records its sends in the history file
+                self.history = History::load_or_default();
+            }
+            ctx.request_repaint_after(WATCH_REPAINT_INTERVAL);
         }
 
-        // Request continuous updates if we're in an in-progress state
-        if matches!(self.status, InjectionStatus::InProgress(_)) {
-            ctx.request_repaint();
+        // Results of injections started through the control API
+        if let Some(receiver) = &self.api_receiver {
+            let statuses: Vec<InjectionStatus> = receiver.try_iter().collect();
+            let mut finished = false;
+            for new_status in statuses {
+                finished |= matches!(
+                    new_status,
+                    InjectionStatus::Success(_, _) | InjectionStatus::Error(_)
+                );
+                self.set_status(Source::Api, new_status);
+            }
+            if finished {
+                // The API records its injections in the history file
+                self.history = History::load_or_default();
+            }
+            ctx.request_repaint_after(WATCH_REPAINT_INTERVAL);
         }
 
-        // Auto-save config when values change
-        if self.values_changed {
-            if self.auto_save_enabled {
-                (self.auto_save_fn)(&self.ip, &self.port, &self.file_path);
+        // Jobs of the injection queue, sent one at a time
+        self.process_queue();
+        if self.queue_receiver.is_some() {
+            ctx.request_repaint_after(WATCH_REPAINT_INTERVAL);
+        }
+
+        // Requests served by the payload host
+        if let Some(receiver) = &self.host_receiver {
+            self.host_log.extend(receiver.try_iter());
+            if self.host_log.len() > HOST_LOG_ROWS {
+                let excess = self.host_log.len() - HOST_LOG_ROWS;
+                self.host_log.drain(..excess);
             }
-            self.values_changed = false;
+            ctx.request_repaint_after(WATCH_REPAINT_INTERVAL);
         }
 
-        // Increase font sizes for all text elements and add padding
-        ctx.style_mut(|style| {
-            style
-                .text_styles
-                .get_mut(&egui::TextStyle::Body)
-                .unwrap()
-                .size = 16.0;
-            style
-                .text_styles
-                .get_mut(&egui::TextStyle::Button)
-                .unwrap()
-                .size = 16.0;
-            style
-                .text_styles
-                .get_mut(&egui::TextStyle::Heading)
-                .unwrap()
-                .size = 20.0;
-            style
-                .text_styles
-                .get_mut(&egui::TextStyle::Monospace)
-                .unwrap()
-                .size = 16.0;
-            style
-                .text_styles
-                .get_mut(&egui::TextStyle::Small)
-                .unwrap()
-                .size = 14.0;
+        // Checksums of the selected file, recomputed when it changes
+        self.sync_archive_entries();
+        self.sync_file_checksums();
+        if self.checksum_receiver.is_some() {
+            ctx.request_repaint_after(WATCH_REPAINT_INTERVAL);
+        }
 
-            // Add padding to buttons and text inputs
-            style.spacing.button_padding = egui::Vec2::new(12.0, 5.0);
-            style.spacing.indent = 20.0;
-            style.spacing.item_spacing = egui::Vec2::new(8.0, 8.0);
-            style.spacing.interact_size.y = 30.0;
-        });
+        // Request continuous updates if we're in an in-progress state
+        if matches!(self.status, InjectionStatus::InProgress(_)) {
+            ctx.request_repaint();
+        }
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.add_space(10.0);
+        self.sync_appearance(ctx);
+        self.track_window_geometry(ctx);
+
+        egui::TopBottomPanel::top("toolbar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.toggle_value(&mut self.preferences.side_panel_open, "Queue & Host");
+                ui.toggle_value(&mut self.preferences.log_panel_open, "History & Activity");
+                ui.toggle_value(&mut self.viewer_open, "Viewer");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui
+                        .button("Commands")
+                        .on_hover_text(ui.ctx().format_shortcut(&PALETTE_SHORTCUT))
+                        .clicked()
+                    {
+                        self.run_command(Command::TogglePalette);
+                    }
+                });
+            });
+        });
 
-            // IP Address and Port rows - 2 columns
-            egui::Grid::new("basic_input_grid")
+        // Outermost bottom panel first, so the status line stays below the log
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.add_space(4.0);
+            egui::Grid::new("status_grid")
                 .num_columns(2)
-                .spacing([15.0, 15.0])
+                .spacing([15.0, 4.0])
                 .show(ui, |ui| {
-                    // IP Address row
-                    ui.add_sized([80.0, 20.0], egui::Label::new("IP Address:"));
-                    let ip_response = ui.add(
-                        egui::TextEdit::singleline(&mut self.ip)
-                            .desired_width(ui.available_width() - 20.0)
-                            .margin(egui::Vec2::new(8.0, 6.0)),
+                    ui.add_sized([80.0, 20.0], egui::Label::new("Status:"));
+                    let status_label = ui.add(
+                        egui::Label::new(
+                            egui::RichText::new(self.status_text()).color(self.status_color()),
+                        )
+                        .truncate(),
                     );
-                    if ip_response.changed() {
-                        self.values_changed = true;
+                    if let InjectionStatus::Success(_, Some(checksums)) = &self.status {
+                        let mut hover = format!("SHA-256 of the sent bytes: {}", checksums.sha256);
+                        if let Some(crc32) = checksums.crc32_text() {
+                            hover.push_str(&format!("\nCRC32: {}", crc32));
+                        }
+                        status_label.on_hover_text(hover);
                     }
                     ui.end_row();
 
-                    // Port row
-                    ui.add_sized([80.0, 20.0], egui::Label::new("Port:"));
-                    let port_response = ui.add(
-                        egui::TextEdit::singleline(&mut self.port)
-                            .desired_width(ui.available_width() - 20.0)
-                            .margin(egui::Vec2::new(8.0, 6.0)),
-                    );
-                    if port_response.changed() {
-                        self.values_changed = true;
+                    if let Some(url) = &self.api_url {
+                        ui.add_sized([80.0, 20.0], egui::Label::new("Remote API:"));
+                        ui.label(egui::RichText::new(url).small());
+                        ui.end_row();
                     }
-                    ui.end_row();
 
-                    // File Path row
-                    ui.add_sized([80.0, 20.0], egui::Label::new("File Path:"));
-                    ui.horizontal(|ui| {
-                        let file_path_response = ui.add(
-                            egui::TextEdit::singleline(&mut self.file_path)
-                                .desired_width(ui.available_width() - 123.0) // Leave more space for button + margin
+                    if let Some(project) = &self.startup_settings.project {
+                        ui.add_sized([80.0, 20.0], egui::Label::new("Project:"));
+                        ui.label(egui::RichText::new(project.display().to_string()).small())
+                            .on_hover_text(
+                                "Values set in this project file override the user config",
+                            );
+                        ui.end_row();
+                    }
+                });
+        });
+
+        egui::TopBottomPanel::bottom("log_panel")
+            .resizable(true)
+            .default_height(140.0)
+            .show_animated(ctx, self.preferences.log_panel_open, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.log_tab, LogTab::History, "History");
+                    ui.selectable_value(&mut self.log_tab, LogTab::Activity, "Activity");
+                });
+                match self.log_tab {
+                    LogTab::History => self.history_section(ui),
+                    LogTab::Activity => self.activity_section(ui),
+                }
+            });
+
+        egui::SidePanel::right("side_panel")
+            .resizable(true)
+            .default_width(320.0)
+            .width_range(220.0..=640.0)
+            .show_animated(ctx, self.preferences.side_panel_open, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::CollapsingHeader::new("Queue")
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            self.queue_section(ui);
+                        });
+
+                    egui::CollapsingHeader::new("Payload Host").show(ui, |ui| {
+                        self.host_section(ui);
+                    });
+
+                    egui::CollapsingHeader::new("Appearance").show(ui, |ui| {
+                        self.appearance_section(ui);
+                    });
+
+                    egui::CollapsingHeader::new("Settings Bundle").show(ui, |ui| {
+                        self.bundle_section(ui);
+                    });
+                });
+            });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            // The form scrolls when the window is too small for it
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                ui.add_space(10.0);
+
+                egui::Grid::new("basic_input_grid")
+                    .num_columns(2)
+                    .spacing([15.0, 15.0])
+                    .show(ui, |ui| {
+                        // Profile row
+                        ui.add_sized([80.0, 20.0], egui::Label::new("Profile:"));
+                        ui.horizontal(|ui| {
+                            let mut selected = None;
+                            egui::ComboBox::from_id_salt("profile_select")
+                                .selected_text("Apply...")
+                                .width(100.0)
+                                .show_ui(ui, |ui| {
+                                    for (index, profile) in
+                                        self.profiles.profiles.iter().enumerate()
+                                    {
+                                        let mut response =
+                                            ui.selectable_label(false, &profile.name);
+                                        if let Some(key) = PROFILE_KEYS.get(index) {
+                                            let shortcut = egui::KeyboardShortcut::new(
+                                                egui::Modifiers::COMMAND,
+                                                *key,
+                                            );
+                                            response = response
+                                                .on_hover_text(ui.ctx().format_shortcut(&shortcut));
+                                        }
+                                        if response.clicked() {
+                                            selected = Some(profile.name.clone());
+                                        }
+                                    }
+                                });
+                            if let Some(name) = selected {
+                                self.apply_profile(&name);
+                            }
+
+                            // Buttons are laid out from the right so the name fills the rest
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    let name = self.profile_name.trim();
+                                    let can_save = !name.is_empty();
+                                    let can_delete = self.profiles.find(name).is_some();
+                                    if ui
+                                        .add_enabled(can_delete, egui::Button::new("Delete"))
+                                        .clicked()
+                                    {
+                                        self.delete_profile();
+                                    }
+                                    if ui
+                                        .add_enabled(can_save, egui::Button::new("Save"))
+                                        .clicked()
+                                    {
+                                        self.save_profile();
+                                    }
+
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut self.profile_name)
+                                            .hint_text("Profile name")
+                                            .desired_width(f32::INFINITY)
+                                            .margin(egui::Vec2::new(8.0, 6.0)),
+                                    );
+                                },
+                            );
+                        });
+                        ui.end_row();
+
+                        // IP Address row
+                        ui.add_sized([80.0, 20.0], egui::Label::new("IP Address:"))
+                            .on_hover_text(source_hint(&self.startup_settings.ip));
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            self.recent_targets_menu(ui);
+                            let ip_response = ui.add(
+                                egui::TextEdit::singleline(&mut self.ip)
+                                    .desired_width(f32::INFINITY)
+                                    .margin(egui::Vec2::new(8.0, 6.0)),
+                            );
+                            if ip_response.changed() {
+                                self.values_changed = true;
+                            }
+                        });
+                        ui.end_row();
+
+                        // Port row
+                        ui.add_sized([80.0, 20.0], egui::Label::new("Port:"))
+                            .on_hover_text(source_hint(&self.startup_settings.port));
+                        let port_response = ui.add(
+                            egui::TextEdit::singleline(&mut self.port)
+                                .desired_width(f32::INFINITY)
                                 .margin(egui::Vec2::new(8.0, 6.0)),
                         );
-                        if file_path_response.changed() {
+                        if port_response.changed() {
                             self.values_changed = true;
                         }
-                        ui.add_space(5.0);
-                        if ui.button("Browse...").clicked() {
-                            if let Some(path) = rfd::FileDialog::new().pick_file() {
-                                self.file_path = path.display().to_string();
+                        ui.end_row();
+
+                        // File Path row
+                        ui.add_sized([80.0, 20.0], egui::Label::new("File Path:"))
+                            .on_hover_text(source_hint(&self.startup_settings.file_path));
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui
+                                .button("Browse...")
+                                .on_hover_text(ui.ctx().format_shortcut(&BROWSE_SHORTCUT))
+                                .clicked()
+                            {
+                                self.browse();
+                            }
+                            self.recent_files_menu(ui);
+                            let file_path_response = ui.add(
+                                egui::TextEdit::singleline(&mut self.file_path)
+                                    .desired_width(f32::INFINITY)
+                                    .margin(egui::Vec2::new(8.0, 6.0)),
+                            );
+                            if file_path_response.changed() {
                                 self.values_changed = true;
                             }
+                        });
+                        ui.end_row();
+
+                        // Payload inside the selected archive
+                        if self.archive_entries.is_some() {
+                            ui.add_sized([80.0, 20.0], egui::Label::new("Entry:"));
+                            ui.horizontal(|ui| self.archive_entry_row(ui));
+                            ui.end_row();
                         }
-                    });
-                    ui.end_row();
 
-                    ui.add_sized([80.0, 20.0], egui::Label::new("")); // Empty first column
+                        // Checksums of the selected file
+                        ui.add_sized([80.0, 20.0], egui::Label::new("SHA-256:"));
+                        ui.horizontal(|ui| self.checksum_row(ui));
+                        ui.end_row();
+
+                        // Known-payload check of the selected file
+                        ui.add_sized([80.0, 20.0], egui::Label::new("Payload:"));
+                        ui.horizontal(|ui| self.payload_check_row(ui));
+                        ui.end_row();
+
+                        ui.add_sized([80.0, 20.0], egui::Label::new("")); // Empty first column
+
+                        ui.horizontal(|ui| {
+                            let inject_button = ui
+                                .add_enabled(self.can_inject(), egui::Button::new("Inject Payload"))
+                                .on_disabled_hover_text(if self.queue_job.is_some() {
+                                    "Waiting for the queued job that is sending"
+                                } else {
+                                    "Needs an IP, a port and a file, and no injection running"
+                                });
+                            let inject_button = with_shortcut_hint(inject_button, &INJECT_SHORTCUT);
+
+                            if inject_button.clicked() {
+                                self.inject_payload();
+                            }
+
+                            ui.add_space(5.0);
 
-                    ui.horizontal(|ui| {
-                        let inject_button = ui.add_enabled(
-                            self.is_input_valid()
-                                && !matches!(self.status, InjectionStatus::InProgress(_)),
-                            egui::Button::new("Inject Payload"),
-                        );
+                            let save_config_button = ui.add_enabled(
+                                self.is_input_valid(),
+                                egui::Button::new("Save Config"),
+                            );
+                            let save_config_button =
+                                with_shortcut_hint(save_config_button, &SAVE_CONFIG_SHORTCUT);
 
-                        if inject_button.clicked() {
-                            self.inject_payload();
-                        }
+                            if save_config_button.clicked() {
+                                self.save_config();
+                            }
 
-                        ui.add_space(5.0);
+                            ui.add_space(5.0);
 
-                        let save_config_button =
-                            ui.add_enabled(self.is_input_valid(), egui::Button::new("Save Config"));
+                            let load_config_button =
+                                with_shortcut_hint(ui.button("Load Config"), &LOAD_CONFIG_SHORTCUT);
 
-                        if save_config_button.clicked() {
-                            self.save_config();
-                        }
+                            if load_config_button.clicked() {
+                                self.load_config();
+                            }
 
-                        ui.add_space(5.0);
+                            ui.add_space(5.0);
 
-                        let load_config_button = ui.button("Load Config");
+                            let reset_button = ui.button("Reset");
 
-                        if load_config_button.clicked() {
-                            self.load_config();
-                        }
+                            if reset_button.clicked() {
+                                self.reset();
+                            }
+                        });
 
-                        ui.add_space(5.0);
+                        ui.end_row();
 
-                        let reset_button = ui.button("Reset");
+                        ui.add_sized([80.0, 20.0], egui::Label::new("")); // Empty first column
 
-                        if reset_button.clicked() {
-                            self.reset();
-                        }
-                    });
+                        ui.horizontal(|ui| {
+                            let auto_save_response =
+                                ui.checkbox(&mut self.auto_save_enabled, "Autosave Config");
 
-                    ui.end_row();
+                            if auto_save_response.changed() {
+                                // Always save the auto-save preference itself
+                                (self.auto_save_preference_fn)(self.auto_save_enabled);
+                            }
 
-                    ui.add_sized([80.0, 20.0], egui::Label::new("")); // Empty first column
+                            ui.add_space(5.0);
+
+                            let notifications_response = ui
+                                .add_enabled(
+                                    notify::SUPPORTED,
+                                    egui::Checkbox::new(
+                                        &mut self.preferences.notifications,
+                                        "Notify When Done",
+                                    ),
+                                )
+                                .on_hover_text(
+                                    "Show a desktop notification when an injection or the queue finishes",
+                                )
+                                .on_disabled_hover_text(
+                                    "Desktop notifications are only supported on Linux",
+                                );
+                            if notifications_response.changed() {
+                                if let Err(e) = self.preferences.save() {
+                                    self.set_status(Source::Manual, InjectionStatus::Error(e));
+                                }
+                            }
+                        });
 
-                    let auto_save_response =
-                        ui.checkbox(&mut self.auto_save_enabled, "Autosave Config");
+                        ui.end_row();
 
-                    if auto_save_response.changed() {
-                        // Always save the auto-save preference itself
-                        (self.auto_save_preference_fn)(self.auto_save_enabled);
-                    }
+                        ui.add_sized([80.0, 20.0], egui::Label::new("")); // Empty first column
 
-                    ui.end_row();
-                });
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut self.watch_enabled, "Watch File")
+                                .on_hover_text("Re-inject automatically whenever the file changes");
 
-            ui.add_space(10.0);
-            ui.separator();
+                            ui.add_space(5.0);
 
-            egui::Grid::new("status_grid")
-                .num_columns(2)
-                .spacing([15.0, 15.0])
-                .show(ui, |ui| {
-                    ui.add_sized([80.0, 20.0], egui::Label::new("Status:"));
-                    ui.add(
-                        egui::Label::new(
-                            egui::RichText::new(&self.status_text()).color(self.status_color()),
-                        )
-                        .wrap(),
-                    );
-                    ui.end_row();
-                });
+                            let require_elf_response =
+                                ui.checkbox(&mut self.watch_require_elf, "Only Valid ELF");
+                            if require_elf_response.changed() {
+                                // Restart the watcher with the new option
+                                self.watch = None;
+                            }
+                        });
+
+                        ui.end_row();
+                    });
+            });
         });
+
+        self.sync_payload_view();
+        self.sync_hex_page();
+        if self.payload_view_receiver.is_some() || self.hex_page_receiver.is_some() {
+            ctx.request_repaint_after(WATCH_REPAINT_INTERVAL);
+        }
+        self.show_viewer(ctx);
+        self.show_bundle_import(ctx);
+        self.show_palette(ctx);
+
+        // Auto-save config once values have stopped changing
+        if let Some(wait) = self.process_auto_save(Instant::now()) {
+            ctx.request_repaint_after(wait);
+        }
     }
 
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // The window is closing, so a failure has nowhere left to be shown
+        let _ = self.preferences.save();
+
         // Auto-save config on app exit (only if auto-save is enabled and config file exists)
         if self.auto_save_enabled {
-            (self.auto_save_fn)(&self.ip, &self.port, &self.file_path);
+            self.auto_save();
         }
     }
 }
 
 impl<F, G, H, I, J, K> App<F, G, H, I, J, K>
 where
-    F: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>) + Send + 'static,
+    F: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>, oneshot::Receiver<()>) + Send + 'static,
     G: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>) + Send + 'static,
     H: Fn(mpsc::Sender<InjectionStatus>) + Send + 'static,
-    I: Fn(&str, &str, &str) + Send + 'static,
+    I: Fn(Option<&str>, Option<&str>, Option<&str>) + Send + 'static,
     J: Fn(bool) + Send + 'static,
     K: Fn(&str, &str, &str, mpsc::Sender<InjectionStatus>) + Send + 'static,
 {
-    fn is_input_valid(&self) -> bool {
-        // Check if IP address is not empty and not just whitespace
-        if self.ip.trim().is_empty() {
-            return false;
+    /// Debounce auto-save: every change pushes the save back by `AUTO_SAVE_DEBOUNCE`.
+    /// Returns how long to wait before calling again while a save is pending.
+    fn process_auto_save(&mut self, now: Instant) -> Option<Duration> {
+        if self.values_changed {
+            self.values_changed = false;
+            self.auto_save_due = Some(now + AUTO_SAVE_DEBOUNCE);
         }
 
-        // Check if port is not empty, not just whitespace, and is a valid u16
-        if self.port.trim().is_empty() || self.port.parse::<u16>().is_err() {
-            return false;
+        let due = self.auto_save_due?;
+        if now < due {
+            return Some(due - now);
         }
 
-        // Check if file path is not empty and file exists
-        if self.file_path.trim().is_empty() || !Path::new(&self.file_path).exists() {
-            return false;
+        self.auto_save_due = None;
+        if self.auto_save_enabled {
+            self.auto_save();
         }
-
-        true
+        None
     }
 
-    fn status_text(&self) -> String {
-        match &self.status {
-            InjectionStatus::Idle => "Idle".to_string(),
-            InjectionStatus::InProgress(msg) => msg.clone(),
-            InjectionStatus::Success(bytes) => format!("Success! Sent {} bytes", bytes),
-            InjectionStatus::Error(msg) => format!("Error: {}", msg),
-            InjectionStatus::ConfigLoaded(_, _, _) => "Config loaded successfully".to_string(),
-            InjectionStatus::ConfigSaved(msg) => msg.clone(),
-        }
+    /// Auto-save the values that belong in the user config: those that came
+    /// from the defaults or the user config, and any changed in the app. The
+    /// rest are left to the shared config, project file, environment or flags
+    /// they came from, so they do not shadow those layers on the next start.
+    fn auto_save(&self) {
+        (self.auto_save_fn)(
+            user_value(&self.ip, &self.startup_settings.ip),
+            user_value(&self.port, &self.startup_settings.port),
+            user_value(&self.file_path, &self.startup_settings.file_path),
+        );
     }
 
-    fn status_color(&self) -> egui::Color32 {
-        match &self.status {
-            InjectionStatus::Error(_) => egui::Color32::from_rgb(220, 80, 80),
-            InjectionStatus::Success(_) => egui::Color32::from_rgb(80, 180, 80),
-            InjectionStatus::InProgress(_) => egui::Color32::from_rgb(255, 165, 0), // Orange
-            InjectionStatus::Idle => egui::Color32::from_rgb(120, 120, 120),
-            InjectionStatus::ConfigLoaded(_, _, _) => egui::Color32::from_rgb(80, 180, 80), // Green like success
-            InjectionStatus::ConfigSaved(_) => egui::Color32::from_rgb(80, 180, 80), // Green like success
-        }
+    fn history_section(&self, ui: &mut egui::Ui) {
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                if self.history.entries.is_empty() {
+                    ui.label("No injections yet");
+                }
+                for entry in self.history.entries.iter().rev().take(HISTORY_ROWS) {
+                    let color = if entry.error.is_some() {
+                        self.status_colors().error
+                    } else {
+                        ui.visuals().text_color()
+                    };
+                    ui.label(egui::RichText::new(entry.summary()).small().color(color));
+                }
+            });
     }
 
-    fn inject_payload(&mut self) {
-        self.status = InjectionStatus::InProgress("Preparing injection...".to_string());
+    fn activity_section(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("activity_severity")
+                .width(80.0)
+                .selected_text(match self.activity_severity {
+                    None => "All".to_string(),
+                    Some(severity) => severity.to_string(),
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.activity_severity, None, "All");
+                    for severity in [Severity::Info, Severity::Success, Severity::Error] {
+                        ui.selectable_value(
+                            &mut self.activity_severity,
+                            Some(severity),
+                            severity.to_string(),
+                        );
+                    }
+                });
 
-        if self.file_path.is_empty() {
-            self.status = InjectionStatus::Error("No file selected".to_string());
-            return;
-        }
+            // Buttons are laid out from the right so the search fills the rest
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui
+                    .add_enabled(!self.activity.is_empty(), egui::Button::new("Clear"))
+                    .clicked()
+                {
+                    self.activity.clear();
+                }
+                if ui
+                    .add_enabled(!self.activity.is_empty(), egui::Button::new("Export..."))
+                    .on_hover_text("Save the entries shown to a text file")
+                    .clicked()
+                {
+                    self.export_activity();
+                }
+                if ui
+                    .add_enabled(!self.activity.is_empty(), egui::Button::new("Copy"))
+                    .on_hover_text("Copy the entries shown")
+                    .clicked()
+                {
+                    let text = activity::to_text(
+                        self.activity
+                            .filtered(self.activity_severity, &self.activity_search),
+                    );
+                    ui.ctx().copy_text(text);
+                }
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.activity_search)
+                        .hint_text("Filter")
+                        .desired_width(f32::INFINITY),
+                );
+            });
+        });
 
-        if !Path::new(&self.file_path).exists() {
-            self.status =
-                InjectionStatus::Error(format!("File does not exist: {}", self.file_path));
-            return;
-        }
+        let colors = self.status_colors();
+        egui::ScrollArea::vertical()
+            .id_salt("activity_log")
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                if self.activity.is_empty() {
+                    ui.label("No activity yet");
+                }
+                for entry in self
+                    .activity
+                    .filtered(self.activity_severity, &self.activity_search)
+                {
+                    let color = match entry.severity {
+                        Severity::Info => ui.visuals().text_color(),
+                        Severity::Success => colors.success,
+                        Severity::Error => colors.error,
+                    };
+                    ui.label(
+                        egui::RichText::new(entry.line())
+                            .monospace()
+                            .small()
+                            .color(color),
+                    );
+                }
+            });
+    }
 
-        if self.ip.is_empty() {
-            self.status = InjectionStatus::Error("IP address is required".to_string());
+    fn export_activity(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Log", &["log", "txt"])
+            .set_file_name("activity.log")
+            .save_file()
+        else {
             return;
+        };
+
+        let result = activity::export(
+            &path,
+            self.activity
+                .filtered(self.activity_severity, &self.activity_search),
+        );
+        match result {
+            Ok(()) => self.set_status(
+                Source::Manual,
+                InjectionStatus::ConfigSaved(format!(
+                    "Activity log exported to {}",
+                    path.display()
+                )),
+            ),
+            Err(e) => self.set_status(Source::Manual, InjectionStatus::Error(e)),
         }
+    }
 
-        if self.port.is_empty() {
-            self.status = InjectionStatus::Error("Port is required".to_string());
+    /// Start, restart or stop the watcher to match the watch toggle and the
+    /// current target and file
+    fn sync_watch(&mut self) {
+        if !self.watch_enabled || !self.is_input_valid() {
+            self.watch = None;
+            self.watch_receiver = None;
             return;
         }
 
-        if self.port.parse::<u16>().is_err() {
-            self.status = InjectionStatus::Error(format!("Invalid port number: {}", self.port));
+        let up_to_date = self
+            .watch
+            .as_ref()
+            .is_some_and(|watch| watch.is_watching(&self.ip, &self.port, &self.file_path));
+        if up_to_date {
             return;
         }
 
-        // Create a channel for communication
+        let options = WatchOptions {
+            require_valid_elf: self.watch_require_elf,
+            crc32: true, // As for manual injections
+            ..WatchOptions::default()
+        };
         let (sender, receiver) = mpsc::channel();
-        self.receiver = Some(receiver);
+        self.watch = Some(watch::start_watch(
+            &self.ip,
+            &self.port,
+            &self.file_path,
+            options,
+            sender,
+        ));
+        self.watch_receiver = Some(receiver);
+    }
 
-        // Clone the necessary data for the async task
-        let ip = self.ip.clone();
-        let port = self.port.clone();
-        let file_path = self.file_path.clone();
+    /// Start hashing the selected file when it is a different file or has
+    /// changed since it was last hashed, and pick up finished results
+    fn sync_file_checksums(&mut self) {
+        if let Some(receiver) = &self.checksum_receiver {
+            if let Ok(result) = receiver.try_recv() {
+                self.file_checksums = Some(result);
+                self.checksum_receiver = None;
+            }
+        }
 
-        // Call the injection function with the sender
-        (self.inject_fn)(&ip, &port, &file_path, sender);
-    }
+        let Some(stamp) = FileStamp::of(&self.file_path).filter(|_| !self.file_path.is_empty())
+        else {
+            self.hashed_file = None;
+            self.file_checksums = None;
+            self.checksum_receiver = None;
+            return;
+        };
 
-    fn save_config(&mut self) {
-        // Create a channel for communication
-        let (sender, receiver) = mpsc::channel();
-        self.receiver = Some(receiver);
+        let current = Some((self.file_path.clone(), stamp));
+        if self.hashed_file == current {
+            return;
+        }
 
-        // Clone the necessary data for the save config function
-        let ip = self.ip.clone();
-        let port = self.port.clone();
+        // Hash off the UI thread, payloads can be large
+        let (sender, receiver) = mpsc::channel();
         let file_path = self.file_path.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(checksum::file_checksums(file_path, true));
+        });
+        self.hashed_file = current;
+        self.file_checksums = None;
+        self.checksum_receiver = Some(receiver);
+    }
 
-        // Call the save config function with the sender
-        (self.save_config_fn)(&ip, &port, &file_path, sender);
+    fn checksum_row(&self, ui: &mut egui::Ui) {
+        match &self.file_checksums {
+            Some(Ok(checksums)) => {
+                let crc32 = checksums.crc32_text().unwrap_or_default();
+                ui.label(
+                    egui::RichText::new(format!("{}…  CRC32 {}", checksums.short_sha256(), crc32))
+                        .monospace()
+                        .small(),
+                )
+                .on_hover_text(&checksums.sha256);
+                if ui.small_button("Copy").clicked() {
+                    ui.ctx().copy_text(checksums.sha256.clone());
+                }
+            }
+            Some(Err(e)) => {
+                ui.label(
+                    egui::RichText::new(e)
+                        .small()
+                        .color(self.status_colors().error),
+                );
+            }
+            None if self.checksum_receiver.is_some() => {
+                ui.label(egui::RichText::new("Hashing...").small());
+            }
+            None => {
+                ui.label(egui::RichText::new("No file").small());
+            }
+        }
     }
 
-    fn load_config(&mut self) {
-        // Create a channel for communication
-        let (sender, receiver) = mpsc::channel();
-        self.receiver = Some(receiver);
+    /// Use a file picked by the user. An archive with a single payload selects
+    /// that payload; otherwise its entries are offered in the Entry row.
+    fn select_file(&mut self, path: &str) {
+        self.file_path = archive::resolve_payload(path).unwrap_or_else(|_| path.to_string());
+        self.values_changed = true;
+        if archive::payload_exists(&self.file_path)
+            && !self.is_bare_archive()
+            && self.recent.add_file(&self.file_path)
+        {
+            self.save_recent();
+        }
+    }
 
-        // Call the load config function with the sender
-        (self.load_config_fn)(sender);
+    fn is_bare_archive(&self) -> bool {
+        archive::is_archive(&self.file_path) && ArchivePath::parse(&self.file_path).is_none()
     }
 
-    fn reset(&mut self) {
-        // Set default values directly
+    /// List the payloads of the archive the selected file is, or is in, when
+    /// it is a different archive or has changed since it was last listed
+    fn sync_archive_entries(&mut self) {
+        let archive = match ArchivePath::parse(&self.file_path) {
+            Some(archive_path) => archive_path.archive,
+            None if archive::is_archive(&self.file_path) => PathBuf::from(&self.file_path),
+            None => {
+                self.archive_entries = None;
+                return;
+            }
+        };
+
+        let stamp = FileStamp::of(&archive);
+        if let Some(listing) = &self.archive_entries {
+            if listing.archive == archive && listing.stamp == stamp {
+                return;
+            }
+        }
+
+        // Only the central directory is read, so this is quick even for large archives
+        let entries = archive::payload_candidates(&archive);
+        self.archive_entries = Some(ArchiveListing {
+            archive,
+            stamp,
+            entries,
+        });
+    }
+
+    fn archive_entry_row(&mut self, ui: &mut egui::Ui) {
+        let Some(listing) = &self.archive_entries else {
+            return;
+        };
+
+        let entries = match &listing.entries {
+            Ok(entries) if entries.is_empty() => {
+                ui.label(egui::RichText::new("No payload found in archive").small());
+                return;
+            }
+            Ok(entries) => entries,
+            Err(e) => {
+                ui.label(
+                    egui::RichText::new(e)
+                        .small()
+                        .color(self.status_colors().error),
+                );
+                return;
+            }
+        };
+
+        let selected = ArchivePath::parse(&self.file_path).map(|path| path.entry);
+        let mut chosen = None;
+        egui::ComboBox::from_id_salt("archive_entry")
+            .width(ui.available_width())
+            .selected_text(selected.as_deref().unwrap_or("Choose a payload..."))
+            .show_ui(ui, |ui| {
+                for entry in entries {
+                    let label = format!("{}  ({} bytes)", entry.name, entry.size);
+                    let is_selected = selected.as_deref() == Some(entry.name.as_str());
+                    if ui.selectable_label(is_selected, label).clicked() {
+                        chosen = Some(ArchivePath::new(&listing.archive, &entry.name));
+                    }
+                }
+            });
+
+        if let Some(path) = chosen {
+            self.file_path = path.to_string();
+            self.values_changed = true;
+        }
+    }
+
+    /// Read the selected file for the viewer when it is a different file or
+    /// has changed, pick up finished reads, and let go of it once the viewer
+    /// is closed
+    fn sync_payload_view(&mut self) {
+        if !self.viewer_open || self.file_path.trim().is_empty() || self.is_bare_archive() {
+            self.payload_view = None;
+            self.viewed_file = None;
+            self.payload_view_receiver = None;
+            return;
+        }
+
+        if let Some(receiver) = &self.payload_view_receiver {
+            if let Ok(view) = receiver.try_recv() {
+                let is_same_file = self
+                    .payload_view
+                    .as_ref()
+                    .is_some_and(|old| old.file_path == view.file_path);
+                if !is_same_file {
+                    self.hex_page = 0;
+                }
+                self.payload_view = Some(view);
+                self.payload_view_receiver = None;
+            }
+        }
+
+        let current = Some((self.file_path.clone(), FileStamp::of(&self.file_path)));
+        if self.viewed_file == current {
+            return;
+        }
+
+        // Read off the UI thread, payloads can be large or need inflating
+        let (sender, receiver) = mpsc::channel();
+        let file_path = self.file_path.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(PayloadView::read(&file_path));
+        });
+        // Another file is not shown meanwhile; a rebuilt one stays until reread
+        if self
+            .payload_view
+            .as_ref()
+            .is_some_and(|view| view.file_path != self.file_path)
+        {
+            self.payload_view = None;
+        }
+        self.viewed_file = current;
+        self.payload_view_receiver = Some(receiver);
+    }
+
+    /// Read the page of the hex dump being shown when it or the file changes
+    fn sync_hex_page(&mut self) {
+        let wanted = match (&self.payload_view, &self.viewed_file) {
+            (Some(view), Some(file)) if view.file_path == file.0 => match view.size {
+                Ok(size) => {
+                    let page = self.hex_page.min(inspect::page_count(size as usize) - 1);
+                    Some((file.clone(), page))
+                }
+                Err(_) => None,
+            },
+            _ => None,
+        };
+        let Some((file, page)) = wanted else {
+            self.hex_lines = None;
+            self.hex_page_receiver = None;
+            return;
+        };
+
+        if let Some(receiver) = &self.hex_page_receiver {
+            match receiver.try_recv() {
+                Ok(read) => self.hex_lines = Some(read),
+                Err(mpsc::TryRecvError::Empty) => return,
+                Err(mpsc::TryRecvError::Disconnected) => {}
+            }
+            self.hex_page_receiver = None;
+        }
+        if self
+            .hex_lines
+            .as_ref()
+            .is_some_and(|read| read.file == file && read.page == page)
+        {
+            return;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let _ = sender.send(HexPage::read(file, page));
+        });
+        self.hex_page_receiver = Some(receiver);
+    }
+
+    fn show_viewer(&mut self, ctx: &egui::Context) {
+        let mut open = self.viewer_open;
+        egui::Window::new("Payload Viewer")
+            .open(&mut open)
+            .default_size([640.0, 480.0])
+            .show(ctx, |ui| {
+                let Some(view) = &self.payload_view else {
+                    if self.payload_view_receiver.is_some() {
+                        ui.label("Reading...");
+                    } else {
+                        ui.label("Select a payload to view it");
+                    }
+                    return;
+                };
+                let size = match &view.size {
+                    Ok(size) => *size,
+                    Err(e) => {
+                        ui.label(egui::RichText::new(e).color(self.status_colors().error));
+                        return;
+                    }
+                };
+
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.viewer_tab, ViewerTab::Hex, "Hex");
+                    ui.selectable_value(&mut self.viewer_tab, ViewerTab::Strings, "Strings");
+                    let mut text = format!("{} bytes", size);
+                    if view.is_partly_scanned() {
+                        text.push_str(&format!(
+                            ", strings and sections from the first {} MiB",
+                            VIEWER_SCAN_BYTES / (1024 * 1024)
+                        ));
+                    }
+                    ui.label(egui::RichText::new(text).small());
+                });
+
+                match self.viewer_tab {
+                    ViewerTab::Hex => Self::hex_view(
+                        ui,
+                        size,
+                        self.hex_lines.as_ref(),
+                        &view.landmarks,
+                        &mut self.hex_page,
+                    ),
+                    ViewerTab::Strings => {
+                        let jump = Self::strings_view(ui, &view.strings, &mut self.strings_search);
+                        if let Some(offset) = jump {
+                            self.hex_page = inspect::page_of(offset);
+                            self.viewer_tab = ViewerTab::Hex;
+                        }
+                    }
+                }
+            });
+        self.viewer_open = open;
+    }
+
+    fn hex_view(
+        ui: &mut egui::Ui,
+        size: u64,
+        lines: Option<&HexPage>,
+        landmarks: &[Landmark],
+        page: &mut usize,
+    ) {
+        let pages = inspect::page_count(size as usize);
+        *page = (*page).min(pages - 1);
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(*page > 0, egui::Button::new("◀").small())
+                .clicked()
+            {
+                *page -= 1;
+            }
+            ui.label(format!("Page {} of {}", *page + 1, pages));
+            if ui
+                .add_enabled(*page + 1 < pages, egui::Button::new("▶").small())
+                .clicked()
+            {
+                *page += 1;
+            }
+
+            if !landmarks.is_empty() {
+                egui::ComboBox::from_id_salt("viewer_landmark")
+                    .selected_text("Jump to...")
+                    .width(ui.available_width())
+                    .show_ui(ui, |ui| {
+                        for landmark in landmarks {
+                            let label = format!("{:#010x}  {}", landmark.offset, landmark.label);
+                            if ui.selectable_label(false, label).clicked() {
+                                *page = inspect::page_of(landmark.offset);
+                            }
+                        }
+                    });
+            }
+        });
+
+        egui::ScrollArea::both()
+            .id_salt("viewer_hex")
+            .auto_shrink([false, false])
+            .show(ui, |ui| match lines.filter(|read| read.page == *page) {
+                Some(HexPage {
+                    lines: Ok(lines), ..
+                }) => {
+                    for line in lines {
+                        ui.label(egui::RichText::new(line).monospace().small());
+                    }
+                }
+                Some(HexPage { lines: Err(e), .. }) => {
+                    ui.label(e);
+                }
+                None => {
+                    ui.label("Reading...");
+                }
+            });
+    }
+
+    /// Returns the offset of a string that was clicked
+    fn strings_view(
+        ui: &mut egui::Ui,
+        strings: &[FoundString],
+        search: &mut String,
+    ) -> Option<usize> {
+        ui.add(
+            egui::TextEdit::singleline(search)
+                .hint_text("Search strings")
+                .desired_width(f32::INFINITY),
+        );
+
+        let matches: Vec<&FoundString> = inspect::search(strings, search).collect();
+        let row_height =
+            ui.text_style_height(&egui::TextStyle::Small) + ui.spacing().item_spacing.y;
+        let mut jump = None;
+        if matches.is_empty() {
+            ui.label(egui::RichText::new("No strings found").small());
+        }
+        egui::ScrollArea::both()
+            .id_salt("viewer_strings")
+            .auto_shrink([false, false])
+            .show_rows(ui, row_height, matches.len(), |ui, rows| {
+                for found in &matches[rows] {
+                    let line = format!("{:08x}  {}", found.offset, found.text);
+                    if ui
+                        .selectable_label(false, egui::RichText::new(line).monospace().small())
+                        .on_hover_text("Show in the hex view")
+                        .clicked()
+                    {
+                        jump = Some(found.offset);
+                    }
+                }
+            });
+        jump
+    }
+
+    /// Check the selected file against the manifest once it has been hashed
+    fn payload_check(&self) -> Option<PayloadCheck> {
+        let Some(Ok(checksums)) = &self.file_checksums else {
+            return None;
+        };
+        let file_name = Path::new(&self.file_path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        Some(self.manifest.check(file_name, &checksums.sha256))
+    }
+
+    fn payload_check_row(&mut self, ui: &mut egui::Ui) {
+        match self.payload_check() {
+            Some(PayloadCheck::Recognised(payload)) => {
+                let mut text = format!("Recognised: {}", payload.title());
+                if let Some(platform) = &payload.platform {
+                    text.push_str(&format!(" ({})", platform));
+                }
+                ui.label(
+                    egui::RichText::new(text)
+                        .small()
+                        .color(self.status_colors().success),
+                );
+
+                if let Some(port) = payload.default_port {
+                    if self.port.trim() != port.to_string()
+                        && ui.small_button(format!("Use port {}", port)).clicked()
+                    {
+                        self.port = port.to_string();
+                        self.values_changed = true;
+                    }
+                }
+            }
+            Some(PayloadCheck::HashMismatch(payload)) => {
+                ui.label(
+                    egui::RichText::new(format!("⚠ Hash mismatch for {}", payload.name))
+                        .small()
+                        .color(self.status_colors().error),
+                )
+                .on_hover_text(format!("Expected sha256 {}", payload.sha256));
+            }
+            Some(PayloadCheck::Unknown) if !self.manifest.payloads.is_empty() => {
+                ui.label(egui::RichText::new("Not in manifest").small());
+            }
+            _ => {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "{} known payload(s)",
+                        self.manifest.payloads.len()
+                    ))
+                    .small(),
+                );
+            }
+        }
+
+        if ui.small_button("Import Manifest...").clicked() {
+            self.pick_manifest();
+        }
+    }
+
+    fn pick_manifest(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Manifest", &["json", "toml"])
+            .pick_file()
+        {
+            self.import_manifest(&path);
+        }
+    }
+
+    fn import_manifest(&mut self, path: &Path) {
+        match Manifest::import(path) {
+            Ok(manifest) => {
+                self.set_status(
+                    Source::Manual,
+                    InjectionStatus::ConfigSaved(format!(
+                        "Imported {} known payload(s)",
+                        manifest.payloads.len()
+                    )),
+                );
+                self.manifest = manifest;
+            }
+            Err(e) => self.set_status(Source::Manual, InjectionStatus::Error(e)),
+        }
+    }
+
+    /// Pick up statuses of the manual injection and of config actions.
+    /// Returns whether there were any.
+    fn receive_statuses(&mut self) -> bool {
+        let new_status = match self.receiver.as_ref().map(mpsc::Receiver::try_recv) {
+            Some(Ok(status)) => Some(status),
+            // Otherwise no injection could start until it was cancelled
+            Some(Err(mpsc::TryRecvError::Disconnected)) if self.pending_injection.is_some() => {
+                Some(InjectionStatus::Error(
+                    "Injection ended without a result".to_string(),
+                ))
+            }
+            _ => None,
+        };
+        let received = new_status.is_some();
+        if let Some(new_status) = new_status {
+            if let Some(entry) = &self.pending_injection {
+                let target = format!("{}:{}", entry.ip, entry.port);
+                if let Some(notification) =
+                    Notification::injection(&target, &entry.file_path, &new_status)
+                {
+                    self.notify(Source::Manual, notification);
+                }
+            }
+            self.finish_pending_injection(&new_status);
+            self.set_status(Source::Manual, new_status);
+        }
+
+        let config_statuses: Vec<InjectionStatus> = self
+            .config_receiver
+            .as_ref()
+            .map(|receiver| receiver.try_iter().collect())
+            .unwrap_or_default();
+        for new_status in &config_statuses {
+            // Handle config loading to populate fields
+            if let InjectionStatus::ConfigLoaded(ip, port, file_path) = new_status {
+                self.ip = ip.clone();
+                self.port = port.clone();
+                self.file_path = file_path.clone();
+                self.values_changed = true; // Mark as changed for auto-save
+            }
+            self.set_status(Source::Manual, new_status.clone());
+        }
+
+        received || !config_statuses.is_empty()
+    }
+
+    /// Record the result of a manual injection in the history
+    fn finish_pending_injection(&mut self, status: &InjectionStatus) {
+        let entry = match status {
+            InjectionStatus::Success(bytes_sent, _) => self
+                .pending_injection
+                .take()
+                .map(|entry| entry.succeeded(*bytes_sent, status.sha256())),
+            InjectionStatus::Error(e) => self.pending_injection.take().map(|entry| entry.failed(e)),
+            _ => None,
+        };
+
+        if let Some(entry) = entry {
+            if let Err(e) = History::record(entry.clone()) {
+                self.log_error(Source::Manual, e);
+            }
+            self.history.push(entry);
+        }
+    }
+
+    /// Set up theme, text sizes and spacing when they were changed rather
+    /// than every frame
+    fn sync_appearance(&mut self, ctx: &egui::Context) {
+        let appearance = (self.preferences.theme, self.preferences.ui_scale());
+        if self.applied_appearance != Some(appearance) {
+            let (theme, scale) = appearance;
+            ctx.set_theme(match theme {
+                Theme::System => egui::ThemePreference::System,
+                Theme::Dark => egui::ThemePreference::Dark,
+                Theme::Light => egui::ThemePreference::Light,
+            });
+            ctx.all_styles_mut(|style| {
+                let sizes = [
+                    (egui::TextStyle::Body, BODY_TEXT_SIZE),
+                    (egui::TextStyle::Button, BODY_TEXT_SIZE),
+                    (egui::TextStyle::Heading, HEADING_TEXT_SIZE),
+                    (egui::TextStyle::Monospace, BODY_TEXT_SIZE),
+                    (egui::TextStyle::Small, SMALL_TEXT_SIZE),
+                ];
+                for (text_style, size) in sizes {
+                    if let Some(font) = style.text_styles.get_mut(&text_style) {
+                        font.size = size * scale;
+                    }
+                }
+
+                // Add padding to buttons and text inputs
+                style.spacing.button_padding = egui::Vec2::new(12.0, 5.0) * scale;
+                style.spacing.indent = 20.0 * scale;
+                style.spacing.item_spacing = egui::Vec2::new(8.0, 8.0) * scale;
+                style.spacing.interact_size.y = 30.0 * scale;
+            });
+            self.applied_appearance = Some(appearance);
+        }
+
+        // Follows the OS when the theme is System
+        self.dark_mode = ctx.style().visuals.dark_mode;
+    }
+
+    fn status_colors(&self) -> StatusColors {
+        StatusColors::new(self.preferences.high_contrast, self.dark_mode)
+    }
+
+    fn appearance_section(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Theme:");
+            for (theme, label) in [
+                (Theme::System, "System"),
+                (Theme::Dark, "Dark"),
+                (Theme::Light, "Light"),
+            ] {
+                changed |= ui
+                    .radio_value(&mut self.preferences.theme, theme, label)
+                    .changed();
+            }
+        });
+
+        let scale = ui.add(
+            egui::Slider::new(&mut self.preferences.ui_scale, MIN_UI_SCALE..=MAX_UI_SCALE)
+                .text("UI scale")
+                .step_by(0.05)
+                .custom_formatter(|value, _| format!("{:.0}%", value * 100.0)),
+        );
+        // Saved once the slider is let go
+        changed |= scale.drag_stopped() || (scale.changed() && !scale.dragged());
+
+        changed |= ui
+            .checkbox(
+                &mut self.preferences.high_contrast,
+                "High-contrast status colours",
+            )
+            .changed();
+
+        if changed {
+            if let Err(e) = self.preferences.save() {
+                self.set_status(Source::Manual, InjectionStatus::Error(e));
+            }
+        }
+    }
+
+    fn bundle_section(&mut self, ui: &mut egui::Ui) {
+        ui.label(
+            egui::RichText::new(
+                "Profiles, known payloads, history, preferences, recent lists, the queue and the target config in one file",
+            )
+            .small(),
+        );
+        ui.horizontal(|ui| {
+            if ui.button("Export...").clicked() {
+                self.pick_bundle_export();
+            }
+            if ui.button("Import...").clicked() {
+                self.pick_bundle_import();
+            }
+        });
+    }
+
+    fn pick_bundle_export(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Settings bundle", &["json"])
+            .set_file_name("ps-payload-injector-settings.json")
+            .save_file()
+        {
+            self.export_bundle(&path);
+        }
+    }
+
+    fn export_bundle(&mut self, path: &Path) {
+        let status = match Bundle::export(path) {
+            Ok((bundle, errors)) if errors.is_empty() => InjectionStatus::ConfigSaved(format!(
+                "Exported {} settings section(s)",
+                bundle.sections().len()
+            )),
+            Ok((bundle, errors)) => {
+                let failed: Vec<String> = errors
+                    .iter()
+                    .map(|(section, e)| format!("{}: {}", section.title(), e))
+                    .collect();
+                InjectionStatus::Error(format!(
+                    "Exported {} settings section(s), left out unreadable ones: {}",
+                    bundle.sections().len(),
+                    failed.join("; ")
+                ))
+            }
+            Err(e) => InjectionStatus::Error(e),
+        };
+        self.set_status(Source::Manual, status);
+    }
+
+    fn pick_bundle_import(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Settings bundle", &["json"])
+            .pick_file()
+        {
+            self.open_bundle_import(path);
+        }
+    }
+
+    /// Read a bundle and ask which of its sections to import
+    fn open_bundle_import(&mut self, path: PathBuf) {
+        match Bundle::load_from_file(&path) {
+            Ok(bundle) => {
+                self.bundle_import = Some(BundleImport {
+                    path,
+                    choices: bundle
+                        .sections()
+                        .into_iter()
+                        .map(|section| (section, Some(ImportMode::Merge)))
+                        .collect(),
+                    report: None,
+                });
+            }
+            Err(e) => self.set_status(Source::Manual, InjectionStatus::Error(e)),
+        }
+    }
+
+    fn show_bundle_import(&mut self, ctx: &egui::Context) {
+        let error_color = self.status_colors().error;
+        let Some(import) = &mut self.bundle_import else {
+            return;
+        };
+        let mut open = true;
+        let mut run_import = false;
+        let mut close = false;
+
+        egui::Window::new("Import Settings")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let file_name = import
+                    .path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                ui.label(format!("From '{}'", file_name));
+                ui.add_space(4.0);
+
+                if let Some(report) = &import.report {
+                    for line in &report.summary {
+                        ui.label(line);
+                    }
+                    for conflict in &report.conflicts {
+                        ui.label(egui::RichText::new(conflict).color(error_color));
+                    }
+                    close = ui.button("Close").clicked();
+                    return;
+                }
+
+                if import.choices.is_empty() {
+                    ui.label("The bundle has no settings");
+                }
+                egui::Grid::new("bundle_import_grid")
+                    .num_columns(4)
+                    .show(ui, |ui| {
+                        for (section, mode) in &mut import.choices {
+                            ui.label(section.title());
+                            ui.radio_value(mode, None, "Skip");
+                            ui.radio_value(mode, Some(ImportMode::Merge), "Merge")
+                                .on_hover_text(
+                                    "Add what is new and keep existing entries that differ",
+                                );
+                            ui.radio_value(mode, Some(ImportMode::Replace), "Replace");
+                            ui.end_row();
+                        }
+                    });
+
+                ui.add_space(4.0);
+                ui.horizontal(|ui| {
+                    let any_chosen = import.choices.iter().any(|(_, mode)| mode.is_some());
+                    run_import = ui
+                        .add_enabled(any_chosen, egui::Button::new("Import"))
+                        .clicked();
+                    close = ui.button("Cancel").clicked();
+                });
+            });
+
+        if run_import {
+            self.import_bundle();
+        }
+        if close || !open {
+            self.bundle_import = None;
+        }
+    }
+
+    /// Import the chosen sections and reload what they changed
+    fn import_bundle(&mut self) {
+        let Some(import) = &self.bundle_import else {
+            return;
+        };
+        let choices: Vec<(BundleSection, ImportMode)> = import
+            .choices
+            .iter()
+            .filter_map(|&(section, mode)| mode.map(|mode| (section, mode)))
+            .collect();
+        let imports_queue = choices
+            .iter()
+            .any(|&(section, _)| section == BundleSection::Queue);
+        if imports_queue && self.queue_job.is_some() {
+            self.set_status(
+                Source::Manual,
+                InjectionStatus::Error(
+                    "Wait for the queue's current job to finish before importing the queue"
+                        .to_string(),
+                ),
+            );
+            return;
+        }
+
+        let report = match Bundle::import(&import.path, &choices) {
+            Ok(report) => report,
+            Err(e) => {
+                self.set_status(Source::Manual, InjectionStatus::Error(e));
+                return;
+            }
+        };
+
+        for section in &report.changed {
+            match section {
+                BundleSection::Config => {
+                    // The user config is one layer; show what it resolves to
+                    let settings = Settings::load();
+                    self.ip = settings.ip.value;
+                    self.port = settings.port.value;
+                    self.file_path = settings.file_path.value;
+                    self.auto_save_enabled = settings.auto_save_enabled.value;
+                }
+                BundleSection::Profiles => self.profiles = Profiles::load_or_default(),
+                BundleSection::Manifest => self.manifest = Manifest::load_or_default(),
+                BundleSection::History => self.history = History::load_or_default(),
+                BundleSection::Preferences => {
+                    let window = self.preferences.window;
+                    self.preferences = Preferences {
+                        window,
+                        ..Preferences::load_or_default()
+                    };
+                }
+                BundleSection::Recent => self.recent = Recent::load_or_default(),
+                BundleSection::Queue => self.queue = Queue::load_or_default(),
+            }
+        }
+
+        let message = match report.conflicts.len() {
+            0 => "Imported settings".to_string(),
+            count => format!("Imported settings, {} conflict(s) kept as they were", count),
+        };
+        self.set_status(Source::Manual, InjectionStatus::ConfigSaved(message));
+        if let Some(import) = &mut self.bundle_import {
+            import.report = Some(report);
+        }
+    }
+
+    /// Remember where the window is, unless it is minimized or maximized so
+    /// that it reopens at its normal size
+    fn track_window_geometry(&mut self, ctx: &egui::Context) {
+        let viewport = ctx.input(|input| input.viewport().clone());
+        if viewport.minimized == Some(true) || viewport.maximized == Some(true) {
+            return;
+        }
+        if let Some(inner) = viewport.inner_rect {
+            self.preferences.window = Some(WindowGeometry {
+                position: viewport.outer_rect.map(|outer| [outer.min.x, outer.min.y]),
+                width: inner.width(),
+                height: inner.height(),
+            });
+        }
+    }
+
+    /// Command for a shortcut pressed this frame, if any
+    fn shortcut_command(&self, ctx: &egui::Context) -> Option<Command> {
+        ctx.input_mut(|input| {
+            if input.consume_shortcut(&PALETTE_SHORTCUT) {
+                return Some(Command::TogglePalette);
+            }
+            // The palette handles its own keys while open
+            if self.palette.is_some() {
+                return None;
+            }
+
+            for (key, profile) in PROFILE_KEYS.iter().zip(&self.profiles.profiles) {
+                if input.consume_key(egui::Modifiers::COMMAND, *key) {
+                    return Some(Command::ApplyProfile(profile.name.clone()));
+                }
+            }
+            PALETTE_ACTIONS
+                .iter()
+                .map(|(_, command)| command)
+                .find(|command| {
+                    command
+                        .shortcut()
+                        .is_some_and(|shortcut| input.consume_shortcut(&shortcut))
+                })
+                .cloned()
+        })
+    }
+
+    fn run_command(&mut self, command: Command) {
+        match command {
+            Command::Inject => {
+                if self.can_inject() {
+                    self.inject_payload();
+                }
+            }
+            Command::Browse => self.browse(),
+            Command::SaveConfig => {
+                if self.is_input_valid() {
+                    self.save_config();
+                }
+            }
+            Command::LoadConfig => self.load_config(),
+            Command::Cancel => self.cancel_injection(),
+            Command::Reset => self.reset(),
+            Command::ToggleWatch => self.watch_enabled = !self.watch_enabled,
+            Command::QueueCurrent => {
+                if self.is_input_valid() {
+                    self.queue_current();
+                }
+            }
+            Command::ToggleQueue => {
+                self.queue_running = !self.queue_running && self.queue.next_runnable().is_some()
+            }
+            Command::ImportManifest => self.pick_manifest(),
+            Command::ToggleViewer => self.viewer_open = !self.viewer_open,
+            Command::ApplyProfile(name) => self.apply_profile(&name),
+            Command::SelectPayload(path) => self.select_file(&path),
+            Command::TogglePalette => {
+                self.palette = match self.palette {
+                    Some(_) => None,
+                    None => Some(Palette::default()),
+                }
+            }
+        }
+    }
+
+    fn browse(&mut self) {
+        let mut dialog = rfd::FileDialog::new();
+        if let Some(directory) = self
+            .recent
+            .last_directory
+            .as_ref()
+            .filter(|dir| dir.is_dir())
+        {
+            dialog = dialog.set_directory(directory);
+        }
+        if let Some(path) = dialog.pick_file() {
+            if self.recent.set_directory_of(&path) {
+                self.save_recent();
+            }
+            self.select_file(&path.display().to_string());
+        }
+    }
+
+    fn recent_files_menu(&mut self, ui: &mut egui::Ui) {
+        let mut chosen = None;
+        ui.add_enabled_ui(!self.recent.files.is_empty(), |ui| {
+            egui::ComboBox::from_id_salt("recent_files")
+                .selected_text("Recent")
+                .width(70.0)
+                .show_ui(ui, |ui| {
+                    for file_path in &self.recent.files {
+                        let exists = archive::payload_exists(file_path);
+                        let response = ui.add_enabled(
+                            exists,
+                            egui::SelectableLabel::new(file_path == &self.file_path, file_path),
+                        );
+                        if response
+                            .on_disabled_hover_text("File no longer exists")
+                            .clicked()
+                        {
+                            chosen = Some(file_path.clone());
+                        }
+                    }
+                });
+        });
+        if let Some(file_path) = chosen {
+            self.select_file(&file_path);
+        }
+    }
+
+    fn recent_targets_menu(&mut self, ui: &mut egui::Ui) {
+        let mut chosen = None;
+        ui.add_enabled_ui(!self.recent.targets.is_empty(), |ui| {
+            egui::ComboBox::from_id_salt("recent_targets")
+                .selected_text("Recent")
+                .width(70.0)
+                .show_ui(ui, |ui| {
+                    for target in &self.recent.targets {
+                        let is_current = target.ip == self.ip && target.port == self.port;
+                        if ui
+                            .selectable_label(is_current, target.to_string())
+                            .clicked()
+                        {
+                            chosen = Some(target.clone());
+                        }
+                    }
+                });
+        });
+        if let Some(target) = chosen {
+            self.ip = target.ip;
+            self.port = target.port;
+            self.values_changed = true;
+        }
+    }
+
+    /// Show a desktop notification if they are turned on. A failure is
+    /// logged for `source` once the notification server has answered.
+    fn notify(&self, source: Source, notification: Notification) {
+        if !self.preferences.notifications {
+            return;
+        }
+        // The notification server may be slow to answer
+        let errors = self.notify_error_sender.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = notify::send(&notification) {
+                let _ = errors.send((source, e));
+            }
+        });
+    }
+
+    fn save_recent(&mut self) {
+        if let Err(e) = self.recent.save() {
+            self.log_error(Source::Manual, e);
+        }
+    }
+
+    /// Stop the manual injection and the running queue job, aborting their
+    /// transfers
+    fn cancel_injection(&mut self) {
+        let cancelled = InjectionStatus::Error("Injection cancelled".to_string());
+        if self.pending_injection.is_some() {
+            if let Some(cancel) = self.cancel.take() {
+                let _ = cancel.send(());
+            }
+            self.receiver = None;
+            self.finish_pending_injection(&cancelled);
+            self.set_status(Source::Manual, cancelled.clone());
+        }
+        if self.queue_job.is_some() {
+            if let Some(cancel) = self.queue_cancel.take() {
+                let _ = cancel.send(());
+            }
+            self.queue_running = false;
+            self.update_queue_job(cancelled);
+        }
+    }
+
+    /// Everything the command palette can search, as label and command
+    fn palette_items(&self) -> Vec<(String, Command)> {
+        let mut items: Vec<(String, Command)> = PALETTE_ACTIONS
+            .iter()
+            .map(|(label, command)| (label.to_string(), command.clone()))
+            .collect();
+
+        for profile in &self.profiles.profiles {
+            items.push((
+                format!("Profile: {}", profile.name),
+                Command::ApplyProfile(profile.name.clone()),
+            ));
+        }
+
+        // Payloads of the selected archive, then recently sent ones
+        let mut payloads = Vec::new();
+        if let Some(ArchiveListing {
+            archive,
+            entries: Ok(entries),
+            ..
+        }) = &self.archive_entries
+        {
+            for entry in entries {
+                payloads.push(ArchivePath::new(archive, &entry.name).to_string());
+            }
+        }
+        for entry in self.history.entries.iter().rev() {
+            if !payloads.contains(&entry.file_path) && archive::payload_exists(&entry.file_path) {
+                payloads.push(entry.file_path.clone());
+            }
+        }
+        for path in payloads {
+            items.push((format!("Payload: {}", path), Command::SelectPayload(path)));
+        }
+
+        items
+    }
+
+    fn show_palette(&mut self, ctx: &egui::Context) {
+        if self.palette.is_none() {
+            return;
+        }
+        let items = self.palette_items();
+        let Some(palette) = self.palette.as_mut() else {
+            return;
+        };
+
+        let matches: Vec<_> = palette::rank(&items, &palette.query, |(label, _)| label)
+            .into_iter()
+            .take(PALETTE_ROWS)
+            .collect();
+        let (up, down, enter, escape) = ctx.input_mut(|input| {
+            (
+                input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                input.consume_key(egui::Modifiers::NONE, egui::Key::Enter),
+                input.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            )
+        });
+        if up {
+            palette.selected = palette.selected.saturating_sub(1);
+        }
+        if down {
+            palette.selected += 1;
+        }
+        palette.selected = palette.selected.min(matches.len().saturating_sub(1));
+
+        let mut chosen = enter
+            .then(|| {
+                matches
+                    .get(palette.selected)
+                    .map(|(_, command)| command.clone())
+            })
+            .flatten();
+        egui::Window::new("Command Palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 40.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut palette.query)
+                        .hint_text("Search payloads, profiles and actions")
+                        .desired_width(400.0),
+                );
+                response.request_focus();
+                if response.changed() {
+                    palette.selected = 0;
+                }
+
+                if matches.is_empty() {
+                    ui.label(egui::RichText::new("No matches").small());
+                }
+                for (index, (label, command)) in matches.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui
+                            .selectable_label(index == palette.selected, label)
+                            .clicked()
+                        {
+                            chosen = Some(command.clone());
+                        }
+                        if let Some(shortcut) = command.shortcut() {
+                            ui.label(
+                                egui::RichText::new(ctx.format_shortcut(&shortcut))
+                                    .small()
+                                    .weak(),
+                            );
+                        }
+                    });
+                }
+            });
+
+        if escape || chosen.is_some() {
+            self.palette = None;
+        }
+        if let Some(command) = chosen {
+            self.run_command(command);
+        }
+    }
+
+    /// Collect statuses of the running queue job and start the next one
+    fn process_queue(&mut self) {
+        if let Some(receiver) = &self.queue_receiver {
+            let mut statuses = Vec::new();
+            let mut disconnected = false;
+            loop {
+                match receiver.try_recv() {
+                    Ok(status) => statuses.push(status),
+                    Err(mpsc::TryRecvError::Empty) => break,
+                    Err(mpsc::TryRecvError::Disconnected) => {
+                        disconnected = true;
+                        break;
+                    }
+                }
+            }
+            for status in statuses {
+                self.update_queue_job(status);
+            }
+            if disconnected && self.queue_job.is_some() {
+                self.update_queue_job(InjectionStatus::Error(
+                    "Injection ended without a result".to_string(),
+                ));
+            }
+        }
+
+        // Never send to the loader while a manual injection is still running
+        if self.queue_running && !self.is_injecting() {
+            self.start_next_queue_job();
+        }
+    }
+
+    fn update_queue_job(&mut self, status: InjectionStatus) {
+        let Some((id, _)) = &self.queue_job else {
+            return;
+        };
+        self.queue.set_status(*id, status.clone());
+
+        let entry = match &status {
+            InjectionStatus::Success(bytes_sent, _) => self
+                .queue_job
+                .take()
+                .map(|(_, entry)| entry.succeeded(*bytes_sent, status.sha256())),
+            InjectionStatus::Error(e) => self.queue_job.take().map(|(_, entry)| entry.failed(e)),
+            _ => None,
+        };
+        if let Some(entry) = entry {
+            match entry.bytes_sent {
+                Some(bytes_sent) if entry.error.is_none() => {
+                    self.queue_results.succeeded += 1;
+                    self.queue_results.bytes_sent += bytes_sent;
+                }
+                _ => self.queue_results.failed += 1,
+            }
+            if let Err(e) = History::record(entry.clone()) {
+                self.log_error(Source::Queue, e);
+            }
+            self.history.push(entry);
+            self.queue_receiver = None;
+            self.save_queue();
+        }
+        self.set_status(Source::Queue, status);
+    }
+
+    fn start_next_queue_job(&mut self) {
+        let Some(job) = self.queue.next_runnable().cloned() else {
+            // Every job has run or is paused
+            self.queue_running = false;
+            let results = std::mem::take(&mut self.queue_results);
+            if results != QueueResults::default() {
+                self.notify(
+                    Source::Queue,
+                    Notification::queue_finished(
+                        results.succeeded,
+                        results.failed,
+                        results.bytes_sent,
+                    ),
+                );
+            }
+            return;
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        self.queue.set_status(
+            job.id,
+            InjectionStatus::InProgress("Queued injection...".to_string()),
+        );
+        self.queue_receiver = Some(receiver);
+        self.queue_job = Some((
+            job.id,
+            HistoryEntry::new(Trigger::Queue, &job.ip, &job.port, &job.file_path),
+        ));
+        self.save_queue();
+
+        let (cancel, cancelled) = oneshot::channel();
+        self.queue_cancel = Some(cancel);
+        (self.inject_fn)(&job.ip, &job.port, &job.file_path, sender, cancelled);
+    }
+
+    fn apply_queue_action(&mut self, action: QueueAction) {
+        match action {
+            QueueAction::Move(id, offset) => self.queue.move_job(id, offset),
+            QueueAction::SetPaused(id, paused) => self.queue.set_paused(id, paused),
+            QueueAction::Retry(id) => self.queue.retry(id),
+            QueueAction::Remove(id) => self.queue.remove(id),
+        };
+        self.save_queue();
+    }
+
+    fn queue_current(&mut self) {
+        self.queue.add(&self.ip, &self.port, &self.file_path);
+        self.save_queue();
+    }
+
+    fn save_queue(&mut self) {
+        if let Err(e) = self.queue.save() {
+            self.log_error(Source::Queue, e);
+        }
+    }
+
+    fn queue_section(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.is_input_valid(), egui::Button::new("Add Current"))
+                .on_hover_text("Queue the selected payload for the target above")
+                .clicked()
+            {
+                self.queue_current();
+            }
+
+            let (label, enabled) = if self.queue_running {
+                ("Pause Queue", true)
+            } else {
+                ("Start Queue", self.queue.next_runnable().is_some())
+            };
+            if ui
+                .add_enabled(enabled, egui::Button::new(label))
+                .on_hover_text("A job that is already sending is finished first")
+                .clicked()
+            {
+                self.queue_running = !self.queue_running;
+            }
+
+            if ui.button("Clear Succeeded").clicked() {
+                self.queue.clear_succeeded();
+                self.save_queue();
+            }
+        });
+
+        if self.queue.jobs.is_empty() {
+            ui.label("No queued jobs");
+        }
+
+        let mut action = None;
+        let colors = self.status_colors();
+        let last = self.queue.jobs.len().saturating_sub(1);
+        for (index, job) in self.queue.jobs.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(index > 0, egui::Button::new("▲").small())
+                    .clicked()
+                {
+                    action = Some(QueueAction::Move(job.id, -1));
+                }
+                if ui
+                    .add_enabled(index < last, egui::Button::new("▼").small())
+                    .clicked()
+                {
+                    action = Some(QueueAction::Move(job.id, 1));
+                }
+
+                if job.is_finished() {
+                    if ui.small_button("Retry").clicked() {
+                        action = Some(QueueAction::Retry(job.id));
+                    }
+                } else {
+                    let label = if job.paused { "Resume" } else { "Pause" };
+                    if ui
+                        .add_enabled(!job.is_running(), egui::Button::new(label).small())
+                        .clicked()
+                    {
+                        action = Some(QueueAction::SetPaused(job.id, !job.paused));
+                    }
+                }
+                if ui
+                    .add_enabled(!job.is_running(), egui::Button::new("Remove").small())
+                    .clicked()
+                {
+                    action = Some(QueueAction::Remove(job.id));
+                }
+
+                ui.label(egui::RichText::new(job.title()).small());
+                let (text, color) = if job.paused {
+                    ("Paused".to_string(), colors.idle)
+                } else {
+                    (job.status.message(), colors.of(&job.status))
+                };
+                ui.label(egui::RichText::new(text).small().color(color));
+            });
+        }
+
+        if let Some(action) = action {
+            self.apply_queue_action(action);
+        }
+    }
+
+    fn host_section(&mut self, ui: &mut egui::Ui) {
+        let running = self.host.is_some();
+
+        ui.horizontal(|ui| {
+            ui.label("Root:");
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                if ui
+                    .add_enabled(!running, egui::Button::new("Browse..."))
+                    .clicked()
+                {
+                    if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                        self.host_root = path.display().to_string();
+                    }
+                }
+                ui.add_enabled(
+                    !running,
+                    egui::TextEdit::singleline(&mut self.host_root)
+                        .hint_text("Folder with exploit pages and payloads")
+                        .desired_width(f32::INFINITY),
+                );
+            });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Port:");
+            ui.add_enabled(
+                !running,
+                egui::TextEdit::singleline(&mut self.host_port).desired_width(50.0),
+            );
+            let label = if running { "Stop" } else { "Start" };
+            if ui.button(label).clicked() {
+                self.toggle_host();
+            }
+        });
+
+        if let Some(host) = &self.host {
+            ui.label(format!("Serving at {}", host.url()));
+        }
+
+        egui::ScrollArea::vertical()
+            .id_salt("host_log")
+            .max_height(120.0)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                if self.host_log.is_empty() {
+                    ui.label(egui::RichText::new("No requests yet").small());
+                }
+                for entry in &self.host_log {
+                    let color = if entry.status >= 400 {
+                        self.status_colors().error
+                    } else {
+                        ui.visuals().text_color()
+                    };
+                    ui.label(egui::RichText::new(entry.summary()).small().color(color));
+                }
+            });
+    }
+
+    /// Start the payload host with the current root and port, or stop it
+    fn toggle_host(&mut self) {
+        if self.host.take().is_some() {
+            self.host_receiver = None;
+            self.set_status(
+                Source::Host,
+                InjectionStatus::ConfigSaved("Payload host stopped".to_string()),
+            );
+            return;
+        }
+
+        let Ok(port) = self.host_port.trim().parse::<u16>() else {
+            self.set_status(
+                Source::Host,
+                InjectionStatus::Error(format!("Invalid port number: {}", self.host_port)),
+            );
+            return;
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        let options = HostOptions::new(self.host_root.trim().into(), port);
+        match host::start_host(options, sender) {
+            Ok(host) => {
+                self.set_status(
+                    Source::Host,
+                    InjectionStatus::ConfigSaved(format!("Payload host serving at {}", host.url())),
+                );
+                self.host = Some(host);
+                self.host_receiver = Some(receiver);
+            }
+            Err(e) => self.set_status(Source::Host, InjectionStatus::Error(e)),
+        }
+    }
+
+    /// Fill in the target and payload from a saved profile
+    fn apply_profile(&mut self, name: &str) {
+        let Some(profile) = self.profiles.find(name).cloned() else {
+            self.set_status(
+                Source::Profile,
+                InjectionStatus::Error(format!("No profile named '{}'", name)),
+            );
+            return;
+        };
+
+        self.ip = profile.ip;
+        self.port = profile.port;
+        self.file_path = profile.file_path;
+        self.profile_name = profile.name;
+        self.values_changed = true;
+        self.set_status(
+            Source::Profile,
+            InjectionStatus::ConfigSaved(format!("Applied profile '{}'", name)),
+        );
+    }
+
+    /// Save the current values under the name in the profile name field
+    fn save_profile(&mut self) {
+        let name = self.profile_name.trim().to_string();
+        self.profiles
+            .upsert(Profile::new(&name, &self.ip, &self.port, &self.file_path));
+        self.set_status(
+            Source::Profile,
+            match self.profiles.save() {
+                Ok(()) => InjectionStatus::ConfigSaved(format!("Saved profile '{}'", name)),
+                Err(e) => InjectionStatus::Error(e),
+            },
+        );
+    }
+
+    fn delete_profile(&mut self) {
+        let name = self.profile_name.trim().to_string();
+        if !self.profiles.remove(&name) {
+            return;
+        }
+        self.profile_name.clear();
+        self.set_status(
+            Source::Profile,
+            match self.profiles.save() {
+                Ok(()) => InjectionStatus::ConfigSaved(format!("Deleted profile '{}'", name)),
+                Err(e) => InjectionStatus::Error(e),
+            },
+        );
+    }
+
+    fn is_input_valid(&self) -> bool {
+        // Check if IP address is not empty and not just whitespace
+        if self.ip.trim().is_empty() {
+            return false;
+        }
+
+        // Check if port is not empty, not just whitespace, and is a valid u16
+        if self.port.trim().is_empty() || self.port.parse::<u16>().is_err() {
+            return false;
+        }
+
+        // Check if file path is not empty and file exists
+        if self.file_path.trim().is_empty() || !archive::payload_exists(&self.file_path) {
+            return false;
+        }
+
+        // An archive itself is never the payload, one of its entries is
+        if self.is_bare_archive() {
+            return false;
+        }
+
+        true
+    }
+
+    fn status_text(&self) -> String {
+        self.status.message()
+    }
+
+    fn status_color(&self) -> egui::Color32 {
+        self.status_colors().of(&self.status)
+    }
+
+    /// A manual injection may start: the input is valid and nothing is sending
+    fn can_inject(&self) -> bool {
+        self.is_input_valid() && !self.is_injecting()
+    }
+
+    /// A manual injection or a queued job is sending. Not taken from the
+    /// status line, which watch mode and the control API also update.
+    fn is_injecting(&self) -> bool {
+        self.pending_injection.is_some() || self.queue_job.is_some()
+    }
+
+    fn inject_payload(&mut self) {
+        self.set_status(
+            Source::Manual,
+            InjectionStatus::InProgress("Preparing injection...".to_string()),
+        );
+
+        if self.file_path.is_empty() {
+            self.set_status(
+                Source::Manual,
+                InjectionStatus::Error("No file selected".to_string()),
+            );
+            return;
+        }
+
+        if !archive::payload_exists(&self.file_path) {
+            self.set_status(
+                Source::Manual,
+                InjectionStatus::Error(format!("File does not exist: {}", self.file_path)),
+            );
+            return;
+        }
+
+        if self.is_bare_archive() {
+            self.set_status(
+                Source::Manual,
+                InjectionStatus::Error("Choose a payload inside the archive".to_string()),
+            );
+            return;
+        }
+
+        if self.ip.is_empty() {
+            self.set_status(
+                Source::Manual,
+                InjectionStatus::Error("IP address is required".to_string()),
+            );
+            return;
+        }
+
+        if self.port.is_empty() {
+            self.set_status(
+                Source::Manual,
+                InjectionStatus::Error("Port is required".to_string()),
+            );
+            return;
+        }
+
+        if self.port.parse::<u16>().is_err() {
+            self.set_status(
+                Source::Manual,
+                InjectionStatus::Error(format!("Invalid port number: {}", self.port)),
+            );
+            return;
+        }
+
+        // Create a channel for communication
+        let (sender, receiver) = mpsc::channel();
+        self.receiver = Some(receiver);
+
+        // Clone the necessary data for the async task
+        let ip = self.ip.clone();
+        let port = self.port.clone();
+        let file_path = self.file_path.clone();
+
+        self.pending_injection = Some(HistoryEntry::new(Trigger::Manual, &ip, &port, &file_path));
+
+        let files_changed = self.recent.add_file(&file_path);
+        if self.recent.add_target(&ip, &port) || files_changed {
+            self.save_recent();
+        }
+
+        // Call the injection function with the sender
+        let (cancel, cancelled) = oneshot::channel();
+        self.cancel = Some(cancel);
+        (self.inject_fn)(&ip, &port, &file_path, sender, cancelled);
+    }
+
+    fn save_config(&mut self) {
+        // Create a channel for communication
+        let (sender, receiver) = mpsc::channel();
+        self.config_receiver = Some(receiver);
+
+        // Clone the necessary data for the save config function
+        let ip = self.ip.clone();
+        let port = self.port.clone();
+        let file_path = self.file_path.clone();
+
+        // Call the save config function with the sender
+        (self.save_config_fn)(&ip, &port, &file_path, sender);
+    }
+
+    fn load_config(&mut self) {
+        // Create a channel for communication
+        let (sender, receiver) = mpsc::channel();
+        self.config_receiver = Some(receiver);
+
+        // Call the load config function with the sender
+        (self.load_config_fn)(sender);
+    }
+
+    fn reset(&mut self) {
+        // Set default values directly
         self.ip = DEFAULT_IP.to_string();
         self.port = DEFAULT_PORT.to_string();
         self.file_path = DEFAULT_FILE_PATH.to_string();
         self.values_changed = true;
 
-        // Create a channel for communication
+        // Create a channel for communication
+        let (sender, receiver) = mpsc::channel();
+        self.config_receiver = Some(receiver);
+
+        // Call the reset function with the sender
+        (self.reset_fn)(&self.ip, &self.port, &self.file_path, sender);
+    }
+}
+
+/// Tooltip saying which layer set a value the app started with
+fn source_hint(setting: &Setting<String>) -> String {
+    format!("Started as '{}' from {}", setting.value, setting.source)
+}
+
+/// `value` if it belongs in the user config, `None` while it is still what
+/// another layer started the app with
+fn user_value<'a>(value: &'a str, setting: &Setting<String>) -> Option<&'a str> {
+    let user_layer = matches!(
+        setting.source,
+        SettingSource::Default | SettingSource::UserFile(_)
+    );
+    (user_layer || value != setting.value).then_some(value)
+}
+
+/// Show the shortcut of a button in its tooltip, whether or not it is enabled
+fn with_shortcut_hint(
+    response: egui::Response,
+    shortcut: &egui::KeyboardShortcut,
+) -> egui::Response {
+    let hint = response.ctx.format_shortcut(shortcut);
+    response
+        .on_hover_text(hint.clone())
+        .on_disabled_hover_text(hint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{DEFAULT_FILE_PATH, DEFAULT_IP, DEFAULT_PORT};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tempfile::NamedTempFile;
+
+    // Mock functions for testing
+    fn mock_inject_fn(
+        _ip: &str,
+        _port: &str,
+        _file_path: &str,
+        _sender: mpsc::Sender<InjectionStatus>,
+        _cancel: oneshot::Receiver<()>,
+    ) {
+        // Does nothing for testing
+    }
+
+    fn mock_save_config_fn(
+        _ip: &str,
+        _port: &str,
+        _file_path: &str,
+        _sender: mpsc::Sender<InjectionStatus>,
+    ) {
+        // Does nothing for testing
+    }
+
+    fn mock_load_config_fn(_sender: mpsc::Sender<InjectionStatus>) {
+        // Does nothing for testing
+    }
+
+    #[test]
+    fn test_app_new() {
+        let app = App::new(
+            mock_inject_fn,
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "/test/path", true),
+        );
+
+        // Values should now be loaded from config (or defaults if no config exists)
+        assert!(!app.ip.is_empty());
+        assert!(!app.port.is_empty());
+        assert!(matches!(app.status, InjectionStatus::Idle));
+        assert!(!app.values_changed); // Should start as unchanged
+    }
+
+    #[test]
+    fn test_injection_status_debug() {
+        let idle = InjectionStatus::Idle;
+        let in_progress = InjectionStatus::InProgress("Testing".to_string());
+        let success = InjectionStatus::Success(1024, None);
+        let error = InjectionStatus::Error("Test error".to_string());
+        let config_loaded = InjectionStatus::ConfigLoaded(
+            "192.168.1.1".to_string(),
+            "8080".to_string(),
+            "/test/path".to_string(),
+        );
+
+        // Test that Debug trait works
+        assert!(format!("{:?}", idle).contains("Idle"));
+        assert!(format!("{:?}", in_progress).contains("InProgress"));
+        assert!(format!("{:?}", success).contains("Success"));
+        assert!(format!("{:?}", error).contains("Error"));
+        assert!(format!("{:?}", config_loaded).contains("ConfigLoaded"));
+    }
+
+    #[test]
+    fn test_is_input_valid() {
+        let mut app = App::new(
+            mock_inject_fn,
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "/test/path", true),
+        );
+
+        // Test invalid cases
+        assert!(!app.is_input_valid()); // Empty file path
+
+        app.file_path = "/nonexistent/file.txt".to_string();
+        assert!(!app.is_input_valid()); // File doesn't exist
+
+        // Create a temp file for valid file path
+        let temp_file = NamedTempFile::new().expect("Failed to create temp file");
+        app.file_path = temp_file.path().to_str().unwrap().to_string();
+
+        app.ip = "".to_string();
+        assert!(!app.is_input_valid()); // Empty IP
+
+        app.ip = "   ".to_string();
+        assert!(!app.is_input_valid()); // Whitespace only IP
+
+        app.ip = "192.168.1.1".to_string();
+        app.port = "".to_string();
+        assert!(!app.is_input_valid()); // Empty port
+
+        app.port = "invalid".to_string();
+        assert!(!app.is_input_valid()); // Invalid port
+
+        app.port = "65536".to_string();
+        assert!(!app.is_input_valid()); // Port out of range
+
+        app.port = "8080".to_string();
+        assert!(app.is_input_valid()); // All valid
+    }
+
+    #[test]
+    fn test_status_text() {
+        let app = App::new(
+            mock_inject_fn,
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "/test/path", true),
+        );
+
+        let mut test_app = app;
+
+        test_app.status = InjectionStatus::Idle;
+        assert_eq!(test_app.status_text(), "Idle");
+
+        test_app.status = InjectionStatus::InProgress("Testing...".to_string());
+        assert_eq!(test_app.status_text(), "Testing...");
+
+        test_app.status = InjectionStatus::Success(1024, None);
+        assert_eq!(test_app.status_text(), "Success! Sent 1024 bytes");
+
+        test_app.status = InjectionStatus::Error("Test error".to_string());
+        assert_eq!(test_app.status_text(), "Error: Test error");
+
+        test_app.status = InjectionStatus::ConfigLoaded(
+            "192.168.1.1".to_string(),
+            "8080".to_string(),
+            "/test/path".to_string(),
+        );
+        assert_eq!(test_app.status_text(), "Config loaded successfully");
+    }
+
+    #[test]
+    fn test_status_color() {
+        let app = App::new(
+            mock_inject_fn,
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "/test/path", true),
+        );
+        let mut test_app = app;
+
+        test_app.status = InjectionStatus::Idle;
+        assert_eq!(
+            test_app.status_color(),
+            egui::Color32::from_rgb(120, 120, 120)
+        );
+
+        test_app.status = InjectionStatus::InProgress("Testing".to_string());
+        assert_eq!(
+            test_app.status_color(),
+            egui::Color32::from_rgb(255, 165, 0)
+        );
+
+        test_app.status = InjectionStatus::Success(1024, None);
+        assert_eq!(
+            test_app.status_color(),
+            egui::Color32::from_rgb(80, 180, 80)
+        );
+
+        test_app.status = InjectionStatus::Error("Error".to_string());
+        assert_eq!(
+            test_app.status_color(),
+            egui::Color32::from_rgb(220, 80, 80)
+        );
+
+        test_app.status =
+            InjectionStatus::ConfigLoaded("ip".to_string(), "port".to_string(), "path".to_string());
+        assert_eq!(
+            test_app.status_color(),
+            egui::Color32::from_rgb(80, 180, 80)
+        );
+    }
+
+    #[test]
+    fn test_appearance_applied_when_changed() {
+        let mut app = App::new(
+            mock_inject_fn,
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "/test/path", false),
+        );
+        app.preferences = Preferences::default();
+        let ctx = egui::Context::default();
+        let body_size = |ctx: &egui::Context| ctx.style().text_styles[&egui::TextStyle::Body].size;
+
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.sync_appearance(ctx));
+        assert_eq!(body_size(&ctx), BODY_TEXT_SIZE);
+
+        // The style is not rebuilt every frame
+        ctx.all_styles_mut(|style| {
+            style
+                .text_styles
+                .get_mut(&egui::TextStyle::Body)
+                .unwrap()
+                .size = 10.0
+        });
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.sync_appearance(ctx));
+        assert_eq!(body_size(&ctx), 10.0);
+
+        app.preferences.theme = Theme::Light;
+        app.preferences.ui_scale = 1.5;
+        let _ = ctx.run(egui::RawInput::default(), |ctx| app.sync_appearance(ctx));
+        assert_eq!(body_size(&ctx), BODY_TEXT_SIZE * 1.5);
+        assert!(!app.dark_mode);
+
+        app.status = InjectionStatus::Error("Error".to_string());
+        assert_eq!(app.status_color(), StatusColors::STANDARD.error);
+        app.preferences.high_contrast = true;
+        assert_eq!(app.status_color(), StatusColors::HIGH_CONTRAST_LIGHT.error);
+        app.dark_mode = true;
+        assert_eq!(app.status_color(), StatusColors::HIGH_CONTRAST_DARK.error);
+    }
+
+    #[test]
+    fn test_inject_payload_validation() {
+        let mut app = App::new(
+            mock_inject_fn,
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "", true),
+        );
+
+        // Test empty file path
+        app.inject_payload();
+        assert!(matches!(app.status, InjectionStatus::Error(_)));
+        if let InjectionStatus::Error(msg) = &app.status {
+            assert!(msg.contains("No file selected"));
+        }
+
+        // Test nonexistent file
+        app.file_path = "/nonexistent/file.txt".to_string();
+        app.inject_payload();
+        assert!(matches!(app.status, InjectionStatus::Error(_)));
+        if let InjectionStatus::Error(msg) = &app.status {
+            assert!(msg.contains("File does not exist"));
+        }
+
+        // Create a temp file
+        let temp_file = NamedTempFile::new().expect("Failed to create temp file");
+        app.file_path = temp_file.path().to_str().unwrap().to_string();
+
+        // Test empty IP
+        app.ip = "".to_string();
+        app.inject_payload();
+        assert!(matches!(app.status, InjectionStatus::Error(_)));
+        if let InjectionStatus::Error(msg) = &app.status {
+            assert!(msg.contains("IP address is required"));
+        }
+
+        app.ip = "192.168.1.1".to_string();
+
+        // Test empty port
+        app.port = "".to_string();
+        app.inject_payload();
+        assert!(matches!(app.status, InjectionStatus::Error(_)));
+        if let InjectionStatus::Error(msg) = &app.status {
+            assert!(msg.contains("Port is required"));
+        }
+
+        // Test invalid port
+        app.port = "invalid".to_string();
+        app.inject_payload();
+        assert!(matches!(app.status, InjectionStatus::Error(_)));
+        if let InjectionStatus::Error(msg) = &app.status {
+            assert!(msg.contains("Invalid port number"));
+        }
+    }
+
+    #[test]
+    fn test_statuses_recorded_in_activity() {
+        let mut app = App::new(
+            mock_inject_fn,
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "", true),
+        );
+        assert!(app.activity.is_empty());
+
+        // The progress message is kept even though the error replaced it at once
+        app.inject_payload();
+        app.apply_profile("missing");
+
+        let entries: Vec<(Source, Severity, String)> = app
+            .activity
+            .entries()
+            .map(|entry| (entry.source, entry.severity, entry.message.clone()))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                (
+                    Source::Manual,
+                    Severity::Info,
+                    "Preparing injection...".to_string()
+                ),
+                (
+                    Source::Manual,
+                    Severity::Error,
+                    "Error: No file selected".to_string()
+                ),
+                (
+                    Source::Profile,
+                    Severity::Error,
+                    "Error: No profile named 'missing'".to_string()
+                ),
+            ]
+        );
+        assert_eq!(app.activity.filtered(Some(Severity::Error), "").count(), 2);
+    }
+
+    #[test]
+    fn test_edge_cases() {
+        let mut app = App::new(
+            mock_inject_fn,
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "/test/path", true),
+        );
+
+        // Test with whitespace in IP
+        app.ip = "  192.168.1.1  ".to_string();
+        app.port = "8080".to_string();
+        let temp_file = NamedTempFile::new().expect("Failed to create temp file");
+        app.file_path = temp_file.path().to_str().unwrap().to_string();
+
+        assert!(app.is_input_valid()); // Should handle whitespace
+
+        // Test port edge cases
+        app.port = "1".to_string();
+        assert!(app.is_input_valid()); // Port 1 is valid
+
+        app.port = "65535".to_string();
+        assert!(app.is_input_valid()); // Port 65535 is valid
+
+        app.port = "0".to_string();
+        assert!(app.is_input_valid()); // Port 0 is technically valid for u16
+    }
+
+    #[test]
+    fn test_config_loaded_populates_fields() {
+        let mut app = App::new(
+            mock_inject_fn,
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "/test/path", true),
+        );
+
+        // Simulate what happens when ConfigLoaded is received
         let (sender, receiver) = mpsc::channel();
-        self.receiver = Some(receiver);
+        app.config_receiver = Some(receiver);
 
-        // Call the reset function with the sender
-        (self.reset_fn)(&self.ip, &self.port, &self.file_path, sender);
-    }
-}
+        // Send a ConfigLoaded status
+        sender
+            .send(InjectionStatus::ConfigLoaded(
+                "10.0.0.1".to_string(),
+                "9000".to_string(),
+                "/new/path.txt".to_string(),
+            ))
+            .unwrap();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::config::{DEFAULT_FILE_PATH, DEFAULT_IP, DEFAULT_PORT};
-    use std::sync::atomic::{AtomicBool, Ordering};
-    use std::sync::Arc;
-    use tempfile::NamedTempFile;
+        // Handled like this in the update method
+        assert!(app.receive_statuses());
 
-    // Mock functions for testing
-    fn mock_inject_fn(
-        _ip: &str,
-        _port: &str,
-        _file_path: &str,
-        _sender: mpsc::Sender<InjectionStatus>,
-    ) {
-        // Does nothing for testing
+        assert_eq!(app.ip, "10.0.0.1");
+        assert_eq!(app.port, "9000");
+        assert_eq!(app.file_path, "/new/path.txt");
+        assert!(matches!(app.status, InjectionStatus::ConfigLoaded(_, _, _)));
     }
 
-    fn mock_save_config_fn(
-        _ip: &str,
-        _port: &str,
-        _file_path: &str,
-        _sender: mpsc::Sender<InjectionStatus>,
-    ) {
-        // Does nothing for testing
-    }
+    #[test]
+    fn test_save_config_status_transitions() {
+        let mut app = App::new(
+            mock_inject_fn,
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "/test/path", true),
+        );
 
-    fn mock_load_config_fn(_sender: mpsc::Sender<InjectionStatus>) {
-        // Does nothing for testing
+        // Create a channel for communication
+        let (sender, receiver) = mpsc::channel();
+        app.config_receiver = Some(receiver);
+
+        // Simulate save config operation
+        (app.save_config_fn)("192.168.1.1", "8080", "/test/path", sender.clone());
+
+        // Verify initial InProgress status
+        if let Some(receiver) = &app.config_receiver {
+            if let Ok(new_status) = receiver.try_recv() {
+                assert!(matches!(new_status, InjectionStatus::InProgress(_)));
+                app.status = new_status;
+            }
+        }
+
+        // Simulate successful save completion
+        let _ = sender.send(InjectionStatus::InProgress(
+            "Config saved to 'test.json'".to_string(),
+        ));
+        let _ = sender.send(InjectionStatus::Idle);
+
+        // Verify final Idle status
+        if let Some(receiver) = &app.config_receiver {
+            if let Ok(new_status) = receiver.try_recv() {
+                assert!(matches!(new_status, InjectionStatus::InProgress(_)));
+                app.status = new_status;
+            }
+            if let Ok(new_status) = receiver.try_recv() {
+                assert!(matches!(new_status, InjectionStatus::Idle));
+                app.status = new_status;
+            }
+        }
+
+        // Verify the app is in Idle state
+        assert!(matches!(app.status, InjectionStatus::Idle));
     }
 
     #[test]
-    fn test_app_new() {
-        let app = App::new(
+    fn test_reset_with_auto_save_enabled() {
+        let auto_save_called = Arc::new(AtomicBool::new(false));
+        let auto_save_called_clone = auto_save_called.clone();
+        let mut app = App::new(
             mock_inject_fn,
             mock_save_config_fn,
             mock_load_config_fn,
-            |_, _, _| {},
+            move |ip, port, file_path| {
+                auto_save_called_clone.store(true, Ordering::SeqCst);
+                assert_eq!(ip, Some(DEFAULT_IP));
+                assert_eq!(port, Some(DEFAULT_PORT));
+                assert_eq!(file_path, Some(DEFAULT_FILE_PATH));
+            },
             |_| {},
             |_, _, _, _| {},
-            (
-                "192.168.1.1".to_string(),
-                "8080".to_string(),
-                "/test/path".to_string(),
-                true,
-            ),
+            Settings::with_values("custom.ip", "1234", "/custom/path", true), // Enable auto-save
         );
 
-        // Values should now be loaded from config (or defaults if no config exists)
-        assert!(!app.ip.is_empty());
-        assert!(!app.port.is_empty());
+        // Verify initial state
+        assert_eq!(app.ip, "custom.ip");
+        assert_eq!(app.port, "1234");
+        assert_eq!(app.file_path, "/custom/path");
+        assert!(app.auto_save_enabled);
+
+        // Simulate reset button click
+        app.ip = DEFAULT_IP.to_string();
+        app.port = DEFAULT_PORT.to_string();
+        app.file_path = DEFAULT_FILE_PATH.to_string();
+        app.status = InjectionStatus::Idle;
+        app.values_changed = true;
+
+        // Trigger auto-save
+        if app.auto_save_enabled {
+            app.auto_save();
+        }
+
+        // Verify reset state
+        assert_eq!(app.ip, DEFAULT_IP);
+        assert_eq!(app.port, DEFAULT_PORT);
+        assert_eq!(app.file_path, DEFAULT_FILE_PATH);
         assert!(matches!(app.status, InjectionStatus::Idle));
-        assert!(!app.values_changed); // Should start as unchanged
+        assert!(app.values_changed);
+        assert!(auto_save_called.load(Ordering::SeqCst)); // Verify auto-save was called
     }
 
     #[test]
-    fn test_injection_status_debug() {
-        let idle = InjectionStatus::Idle;
-        let in_progress = InjectionStatus::InProgress("Testing".to_string());
-        let success = InjectionStatus::Success(1024);
-        let error = InjectionStatus::Error("Test error".to_string());
-        let config_loaded = InjectionStatus::ConfigLoaded(
-            "192.168.1.1".to_string(),
-            "8080".to_string(),
-            "/test/path".to_string(),
+    fn test_auto_save_skips_values_from_other_layers() {
+        type Saved = Vec<(Option<String>, Option<String>, Option<String>)>;
+        let saved = Arc::new(Mutex::new(Saved::new()));
+        let saved_clone = saved.clone();
+        let mut settings = Settings::with_values("10.0.0.9", "9021", "/team/payload.elf", true);
+        settings.ip.source = SettingSource::Cli("--ip");
+        settings.port.source = SettingSource::Env(crate::settings::ENV_PORT);
+        settings.file_path.source = SettingSource::SharedFile(PathBuf::from("shared.json"));
+        let mut app = App::new(
+            mock_inject_fn,
+            mock_save_config_fn,
+            mock_load_config_fn,
+            move |ip: Option<&str>, port: Option<&str>, file_path: Option<&str>| {
+                saved_clone.lock().unwrap().push((
+                    ip.map(str::to_string),
+                    port.map(str::to_string),
+                    file_path.map(str::to_string),
+                ));
+            },
+            |_| {},
+            |_, _, _, _| {},
+            settings,
         );
 
-        // Test that Debug trait works
-        assert!(format!("{:?}", idle).contains("Idle"));
-        assert!(format!("{:?}", in_progress).contains("InProgress"));
-        assert!(format!("{:?}", success).contains("Success"));
-        assert!(format!("{:?}", error).contains("Error"));
-        assert!(format!("{:?}", config_loaded).contains("ConfigLoaded"));
+        // Nothing was edited, so nothing is copied into the user config
+        app.auto_save();
+        // Only the edited value is
+        app.port = "9020".to_string();
+        app.auto_save();
+
+        assert_eq!(
+            *saved.lock().unwrap(),
+            vec![(None, None, None), (None, Some("9020".to_string()), None)]
+        );
     }
 
     #[test]
-    fn test_is_input_valid() {
+    fn test_auto_save_is_debounced() {
+        let save_count = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let save_count_clone = save_count.clone();
         let mut app = App::new(
             mock_inject_fn,
             mock_save_config_fn,
             mock_load_config_fn,
-            |_, _, _| {},
+            move |_, _, _| {
+                save_count_clone.fetch_add(1, Ordering::SeqCst);
+            },
             |_| {},
             |_, _, _, _| {},
-            (
-                "192.168.1.1".to_string(),
-                "8080".to_string(),
-                "/test/path".to_string(),
-                true,
-            ),
+            Settings::with_values("192.168.1.1", "8080", "/test/path", true),
         );
 
-        // Test invalid cases
-        assert!(!app.is_input_valid()); // Empty file path
+        // Nothing pending, nothing saved
+        let start = Instant::now();
+        assert_eq!(app.process_auto_save(start), None);
 
-        app.file_path = "/nonexistent/file.txt".to_string();
-        assert!(!app.is_input_valid()); // File doesn't exist
+        // A burst of edits only schedules a save
+        for i in 0..5 {
+            app.ip.push('1');
+            app.values_changed = true;
+            let now = start + Duration::from_millis(100 * i);
+            assert_eq!(app.process_auto_save(now), Some(AUTO_SAVE_DEBOUNCE));
+        }
+        assert_eq!(save_count.load(Ordering::SeqCst), 0);
 
-        // Create a temp file for valid file path
+        // Still waiting shortly before the deadline
+        let last_edit = start + Duration::from_millis(400);
+        let almost = last_edit + AUTO_SAVE_DEBOUNCE - Duration::from_millis(1);
+        assert_eq!(
+            app.process_auto_save(almost),
+            Some(Duration::from_millis(1))
+        );
+        assert_eq!(save_count.load(Ordering::SeqCst), 0);
+
+        // Saved exactly once after the edits settle
+        assert_eq!(app.process_auto_save(last_edit + AUTO_SAVE_DEBOUNCE), None);
+        assert_eq!(
+            app.process_auto_save(last_edit + AUTO_SAVE_DEBOUNCE * 2),
+            None
+        );
+        assert_eq!(save_count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_manual_injection_recorded_in_history() {
+        let mut app = App::new(
+            mock_inject_fn,
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "/test/path", false),
+        );
         let temp_file = NamedTempFile::new().expect("Failed to create temp file");
         app.file_path = temp_file.path().to_str().unwrap().to_string();
+        let history_before = app.history.entries.len();
 
-        app.ip = "".to_string();
-        assert!(!app.is_input_valid()); // Empty IP
+        // Unrelated statuses do not complete the injection
+        app.inject_payload();
+        assert!(app.pending_injection.is_some());
+        app.finish_pending_injection(&InjectionStatus::InProgress("Sending...".to_string()));
+        assert!(app.pending_injection.is_some());
+
+        app.finish_pending_injection(&InjectionStatus::Success(
+            512,
+            Some(Checksums {
+                sha256: "ab12".to_string(),
+                crc32: None,
+            }),
+        ));
+        assert!(app.pending_injection.is_none());
+        assert_eq!(app.history.entries.len(), history_before + 1);
+        let entry = app.history.entries.last().unwrap();
+        assert_eq!(entry.trigger, Trigger::Manual);
+        assert_eq!(entry.bytes_sent, Some(512));
+        assert_eq!(entry.sha256.as_deref(), Some("ab12"));
+        assert_eq!(entry.file_path, app.file_path);
+
+        let saved = History::load_from_file(History::default_path()).expect("Not saved");
+        assert_eq!(saved.entries.last(), Some(entry));
+        let _ = std::fs::remove_file(History::default_path());
+        let _ = std::fs::remove_file(Recent::default_path());
+    }
 
-        app.ip = "   ".to_string();
-        assert!(!app.is_input_valid()); // Whitespace only IP
+    #[test]
+    fn test_recent_files_and_targets() {
+        let mut app = App::new(
+            mock_inject_fn,
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "", false),
+        );
+        let _ = std::fs::remove_file(Recent::default_path());
+        app.recent = Recent::default();
+
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let first = temp_dir.path().join("first.elf");
+        let second = temp_dir.path().join("second.elf");
+        std::fs::write(&first, b"1").expect("Failed to write test data");
+        std::fs::write(&second, b"2").expect("Failed to write test data");
+        let first = first.display().to_string();
+        let second = second.display().to_string();
+
+        // Picking a file that does not exist does not add it
+        app.select_file("/nonexistent/file.elf");
+        assert!(app.recent.files.is_empty());
+
+        app.select_file(&first);
+        app.inject_payload();
+        app.ip = "10.0.0.2".to_string();
+        app.select_file(&second);
+        app.inject_payload();
 
-        app.ip = "192.168.1.1".to_string();
-        app.port = "".to_string();
-        assert!(!app.is_input_valid()); // Empty port
+        assert_eq!(app.recent.files, [second.clone(), first.clone()]);
+        let targets: Vec<String> = app.recent.targets.iter().map(|t| t.to_string()).collect();
+        assert_eq!(targets, ["10.0.0.2:8080", "192.168.1.1:8080"]);
 
-        app.port = "invalid".to_string();
-        assert!(!app.is_input_valid()); // Invalid port
+        let saved = Recent::load_from_file(Recent::default_path()).expect("Not saved");
+        assert_eq!(saved, app.recent);
+        let _ = std::fs::remove_file(Recent::default_path());
+    }
 
-        app.port = "65536".to_string();
-        assert!(!app.is_input_valid()); // Port out of range
+    #[test]
+    fn test_queue_runs_jobs_in_order() {
+        let mut app = App::new(
+            |_: &str, _: &str, file_path: &str, sender: mpsc::Sender<InjectionStatus>, _| {
+                let status = if file_path.ends_with("bad.elf") {
+                    InjectionStatus::Error("Connection refused".to_string())
+                } else {
+                    InjectionStatus::Success(3, None)
+                };
+                let _ = sender.send(status);
+            },
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "/test/path", false),
+        );
+        app.queue = Queue::default();
+        let first = app.queue.add("10.0.0.2", "9021", "/good.elf");
+        let paused = app.queue.add("10.0.0.2", "9021", "/later.elf");
+        let failing = app.queue.add("10.0.0.2", "9021", "/bad.elf");
+        app.apply_queue_action(QueueAction::SetPaused(paused, true));
+        app.apply_queue_action(QueueAction::Move(failing, -1));
+        let history_before = app.history.entries.len();
+
+        // Nothing runs until the queue is started
+        app.process_queue();
+        assert!(app.queue.get(first).unwrap().is_runnable());
+
+        app.queue_running = true;
+        app.process_queue();
+        app.process_queue();
+        assert_eq!(
+            app.queue_results,
+            QueueResults {
+                succeeded: 1,
+                failed: 0,
+                bytes_sent: 3,
+            }
+        );
+        app.process_queue();
+        app.process_queue();
+        assert!(!app.queue_running);
+        // Counted afresh for the next run once the queue has run out
+        assert_eq!(app.queue_results, QueueResults::default());
+        assert!(matches!(
+            app.queue.get(first).unwrap().status,
+            InjectionStatus::Success(3, None)
+        ));
+        assert!(matches!(
+            app.queue.get(failing).unwrap().status,
+            InjectionStatus::Error(_)
+        ));
+        assert!(app.queue.get(paused).unwrap().paused);
+        assert_eq!(app.history.entries.len(), history_before + 2);
+        assert_eq!(app.history.entries.last().unwrap().trigger, Trigger::Queue);
+
+        // The queue is saved as it runs
+        let saved = Queue::load_or_default();
+        assert!(matches!(
+            saved.get(failing).unwrap().status,
+            InjectionStatus::Error(_)
+        ));
 
-        app.port = "8080".to_string();
-        assert!(app.is_input_valid()); // All valid
+        // Retrying and resuming runs the remaining jobs
+        app.apply_queue_action(QueueAction::Retry(failing));
+        app.apply_queue_action(QueueAction::SetPaused(paused, false));
+        app.queue_running = true;
+        for _ in 0..4 {
+            app.process_queue();
+        }
+        assert!(matches!(
+            app.queue.get(paused).unwrap().status,
+            InjectionStatus::Success(3, None)
+        ));
+        assert_eq!(app.history.entries.len(), history_before + 4);
+
+        app.apply_queue_action(QueueAction::Remove(failing));
+        assert_eq!(app.queue.jobs.len(), 2);
+
+        let _ = std::fs::remove_file(Queue::default_path());
+        let _ = std::fs::remove_file(History::default_path());
+    }
+
+    #[test]
+    fn test_queue_and_manual_injection_take_turns() {
+        // Neither injection reports back, so both stay running
+        let mut app = App::new(
+            |_: &str, _: &str, _: &str, _: mpsc::Sender<InjectionStatus>, _| {},
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "/test/path", false),
+        );
+        let temp_file = NamedTempFile::new().expect("Failed to create temp file");
+        app.file_path = temp_file.path().to_str().unwrap().to_string();
+        app.queue = Queue::default();
+        let job = app.queue.add("10.0.0.2", "9021", &app.file_path.clone());
+
+        app.run_command(Command::Inject);
+        assert!(app.pending_injection.is_some());
+
+        // A status from watch mode does not end the manual injection
+        app.set_status(Source::Watch, InjectionStatus::Success(5, None));
+        assert!(!app.can_inject());
+
+        // The queue waits for the manual injection
+        app.queue_running = true;
+        app.process_queue();
+        assert!(app.queue_job.is_none());
+        assert!(app.queue.get(job).unwrap().is_runnable());
+
+        app.finish_pending_injection(&InjectionStatus::Success(3, None));
+        app.process_queue();
+        assert!(app.queue_job.is_some());
+
+        // And a manual injection waits for the queued job
+        assert!(!app.can_inject());
+        app.run_command(Command::Inject);
+        assert!(app.pending_injection.is_none());
+
+        let _ = std::fs::remove_file(Queue::default_path());
+        let _ = std::fs::remove_file(History::default_path());
+        let _ = std::fs::remove_file(Recent::default_path());
+    }
+
+    #[test]
+    fn test_config_actions_keep_pending_injection() {
+        let injection_sender = Arc::new(std::sync::Mutex::new(None));
+        let injection_sender_clone = injection_sender.clone();
+        let mut app = App::new(
+            move |_: &str, _: &str, _: &str, sender: mpsc::Sender<InjectionStatus>, _| {
+                *injection_sender_clone.lock().unwrap() = Some(sender);
+            },
+            |_: &str, _: &str, _: &str, sender: mpsc::Sender<InjectionStatus>| {
+                let _ = sender.send(InjectionStatus::ConfigSaved("Config saved".to_string()));
+            },
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "/test/path", false),
+        );
+        let temp_file = NamedTempFile::new().expect("Failed to create temp file");
+        app.file_path = temp_file.path().to_str().unwrap().to_string();
+        app.history = History::default();
+
+        app.run_command(Command::Inject);
+        assert!(app.pending_injection.is_some());
+
+        // Saving, loading and resetting the config leave the injection running
+        app.reset();
+        app.load_config();
+        app.save_config();
+        assert!(app.receive_statuses());
+        assert_eq!(app.status_text(), "Config saved");
+        assert!(app.receiver.is_some());
+        assert!(app.pending_injection.is_some());
+        assert!(!app.can_inject());
+
+        // And its result still reaches the history
+        let sender = injection_sender.lock().unwrap().take().unwrap();
+        sender
+            .send(InjectionStatus::Success(3, None))
+            .expect("Receiver was dropped");
+        assert!(app.receive_statuses());
+        assert!(app.pending_injection.is_none());
+        assert_eq!(app.history.entries.len(), 1);
+        assert_eq!(app.history.entries[0].bytes_sent, Some(3));
+
+        let _ = std::fs::remove_file(History::default_path());
+        let _ = std::fs::remove_file(Recent::default_path());
     }
 
     #[test]
-    fn test_status_text() {
-        let app = App::new(
+    fn test_commands_and_palette() {
+        let mut app = App::new(
             mock_inject_fn,
             mock_save_config_fn,
             mock_load_config_fn,
             |_, _, _| {},
             |_| {},
             |_, _, _, _| {},
-            (
-                "192.168.1.1".to_string(),
-                "8080".to_string(),
-                "/test/path".to_string(),
-                true,
-            ),
+            Settings::with_values("192.168.1.1", "8080", "/test/path", false),
         );
+        let temp_file = NamedTempFile::new().expect("Failed to create temp file");
+        let payload = temp_file.path().to_str().unwrap().to_string();
+        app.profiles = Profiles::default();
+        app.profiles
+            .upsert(Profile::new("ps5-lab", "10.0.0.5", "9021", &payload));
+        app.history = History::default();
+        app.history.push(HistoryEntry::new(
+            Trigger::Manual,
+            "10.0.0.5",
+            "9021",
+            &payload,
+        ));
+        app.history.push(HistoryEntry::new(
+            Trigger::Manual,
+            "10.0.0.5",
+            "9021",
+            "/gone/payload.elf",
+        ));
 
-        let mut test_app = app;
-
-        test_app.status = InjectionStatus::Idle;
-        assert_eq!(test_app.status_text(), "Idle");
-
-        test_app.status = InjectionStatus::InProgress("Testing...".to_string());
-        assert_eq!(test_app.status_text(), "Testing...");
+        // Profiles and payloads that still exist are offered next to the actions
+        let items = app.palette_items();
+        let labels: Vec<&str> = items.iter().map(|(label, _)| label.as_str()).collect();
+        assert!(labels.contains(&"Inject Payload"));
+        assert!(labels.contains(&"Profile: ps5-lab"));
+        assert!(labels.contains(&format!("Payload: {}", payload).as_str()));
+        assert!(!labels.iter().any(|label| label.contains("/gone/")));
+        let ranked = palette::rank(&items, "lab", |(label, _)| label);
+        assert_eq!(
+            ranked.first().map(|(_, command)| command),
+            Some(&Command::ApplyProfile("ps5-lab".to_string()))
+        );
 
-        test_app.status = InjectionStatus::Success(1024);
-        assert_eq!(test_app.status_text(), "Success! Sent 1024 bytes");
+        app.run_command(Command::TogglePalette);
+        assert!(app.palette.is_some());
+        app.run_command(Command::TogglePalette);
+        assert!(app.palette.is_none());
+
+        app.run_command(Command::ApplyProfile("ps5-lab".to_string()));
+        assert_eq!(app.ip, "10.0.0.5");
+        assert_eq!(app.file_path, payload);
+
+        // Escape cancels the injection in progress and records it
+        app.run_command(Command::Inject);
+        assert!(app.pending_injection.is_some());
+        app.run_command(Command::Cancel);
+        assert!(app.receiver.is_none());
+        assert!(app.pending_injection.is_none());
+        assert_eq!(app.status_text(), "Error: Injection cancelled");
+        assert_eq!(
+            app.history.entries.last().unwrap().error.as_deref(),
+            Some("Injection cancelled")
+        );
 
-        test_app.status = InjectionStatus::Error("Test error".to_string());
-        assert_eq!(test_app.status_text(), "Error: Test error");
+        // Invalid input is not injected
+        app.port = "not a port".to_string();
+        app.run_command(Command::Inject);
+        assert!(app.pending_injection.is_none());
 
-        test_app.status = InjectionStatus::ConfigLoaded(
-            "192.168.1.1".to_string(),
-            "8080".to_string(),
-            "/test/path".to_string(),
-        );
-        assert_eq!(test_app.status_text(), "Config loaded successfully");
+        let _ = std::fs::remove_file(History::default_path());
+        let _ = std::fs::remove_file(Recent::default_path());
     }
 
     #[test]
-    fn test_status_color() {
-        let app = App::new(
+    fn test_window_geometry_tracked() {
+        let mut app = App::new(
             mock_inject_fn,
             mock_save_config_fn,
             mock_load_config_fn,
             |_, _, _| {},
             |_| {},
             |_, _, _, _| {},
-            (
-                "192.168.1.1".to_string(),
-                "8080".to_string(),
-                "/test/path".to_string(),
-                true,
-            ),
+            Settings::with_values("192.168.1.1", "8080", "/test/path", false),
         );
-        let mut test_app = app;
+        let ctx = egui::Context::default();
+        let frame_with = |info: egui::ViewportInfo| {
+            let mut input = egui::RawInput::default();
+            input.viewports.insert(egui::ViewportId::ROOT, info);
+            input
+        };
+
+        let normal = egui::ViewportInfo {
+            inner_rect: Some(egui::Rect::from_min_size(
+                egui::pos2(110.0, 230.0),
+                egui::vec2(800.0, 600.0),
+            )),
+            outer_rect: Some(egui::Rect::from_min_size(
+                egui::pos2(100.0, 200.0),
+                egui::vec2(820.0, 640.0),
+            )),
+            ..Default::default()
+        };
+        let _ = ctx.run(frame_with(normal.clone()), |ctx| {
+            app.track_window_geometry(ctx)
+        });
+        let expected = WindowGeometry {
+            position: Some([100.0, 200.0]),
+            width: 800.0,
+            height: 600.0,
+        };
+        assert_eq!(app.preferences.window, Some(expected));
+
+        // A maximized window keeps the size to restore to
+        let maximized = egui::ViewportInfo {
+            maximized: Some(true),
+            inner_rect: Some(egui::Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(1920.0, 1080.0),
+            )),
+            ..normal
+        };
+        let _ = ctx.run(frame_with(maximized), |ctx| app.track_window_geometry(ctx));
+        assert_eq!(app.preferences.window, Some(expected));
+    }
 
-        test_app.status = InjectionStatus::Idle;
-        assert_eq!(
-            test_app.status_color(),
-            egui::Color32::from_rgb(120, 120, 120)
+    #[test]
+    fn test_profiles_save_apply_delete() {
+        let mut app = App::new(
+            mock_inject_fn,
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "8080", "/test/path", false),
         );
+        app.profiles = Profiles::default();
 
-        test_app.status = InjectionStatus::InProgress("Testing".to_string());
+        app.profile_name = " Living room ".to_string();
+        app.save_profile();
+        assert!(matches!(app.status, InjectionStatus::ConfigSaved(_)));
+        let saved = Profiles::load_from_file(Profiles::default_path()).expect("Not saved");
         assert_eq!(
-            test_app.status_color(),
-            egui::Color32::from_rgb(255, 165, 0)
+            saved.find("Living room"),
+            Some(&Profile::new(
+                "Living room",
+                "192.168.1.1",
+                "8080",
+                "/test/path"
+            ))
         );
 
-        test_app.status = InjectionStatus::Success(1024);
-        assert_eq!(
-            test_app.status_color(),
-            egui::Color32::from_rgb(80, 180, 80)
-        );
+        app.ip = "10.0.0.9".to_string();
+        app.port = "9021".to_string();
+        app.apply_profile("Living room");
+        assert_eq!(app.ip, "192.168.1.1");
+        assert_eq!(app.port, "8080");
+        assert!(app.values_changed);
 
-        test_app.status = InjectionStatus::Error("Error".to_string());
-        assert_eq!(
-            test_app.status_color(),
-            egui::Color32::from_rgb(220, 80, 80)
-        );
+        app.apply_profile("Nope");
+        assert!(matches!(app.status, InjectionStatus::Error(_)));
 
-        test_app.status =
-            InjectionStatus::ConfigLoaded("ip".to_string(), "port".to_string(), "path".to_string());
-        assert_eq!(
-            test_app.status_color(),
-            egui::Color32::from_rgb(80, 180, 80)
-        );
+        app.profile_name = "Living room".to_string();
+        app.delete_profile();
+        assert!(app.profiles.profiles.is_empty());
+        assert!(app.profile_name.is_empty());
+        let _ = std::fs::remove_file(Profiles::default_path());
     }
 
     #[test]
-    fn test_inject_payload_validation() {
+    fn test_file_checksums_follow_selected_file() {
         let mut app = App::new(
             mock_inject_fn,
             mock_save_config_fn,
@@ -631,62 +3824,43 @@ mod tests {
             |_, _, _| {},
             |_| {},
             |_, _, _, _| {},
-            (
-                "192.168.1.1".to_string(),
-                "8080".to_string(),
-                "".to_string(), // Start with empty file path
-                true,
-            ),
+            Settings::with_values("192.168.1.1", "8080", "/path/that/does/not/exist", false),
         );
 
-        // Test empty file path
-        app.inject_payload();
-        assert!(matches!(app.status, InjectionStatus::Error(_)));
-        if let InjectionStatus::Error(msg) = &app.status {
-            assert!(msg.contains("No file selected"));
-        }
-
-        // Test nonexistent file
-        app.file_path = "/nonexistent/file.txt".to_string();
-        app.inject_payload();
-        assert!(matches!(app.status, InjectionStatus::Error(_)));
-        if let InjectionStatus::Error(msg) = &app.status {
-            assert!(msg.contains("File does not exist"));
-        }
+        app.sync_file_checksums();
+        assert!(app.checksum_receiver.is_none());
+        assert!(app.file_checksums.is_none());
 
-        // Create a temp file
         let temp_file = NamedTempFile::new().expect("Failed to create temp file");
+        std::fs::write(&temp_file, b"abc").expect("Failed to write test data");
         app.file_path = temp_file.path().to_str().unwrap().to_string();
 
-        // Test empty IP
-        app.ip = "".to_string();
-        app.inject_payload();
-        assert!(matches!(app.status, InjectionStatus::Error(_)));
-        if let InjectionStatus::Error(msg) = &app.status {
-            assert!(msg.contains("IP address is required"));
-        }
-
-        app.ip = "192.168.1.1".to_string();
-
-        // Test empty port
-        app.port = "".to_string();
-        app.inject_payload();
-        assert!(matches!(app.status, InjectionStatus::Error(_)));
-        if let InjectionStatus::Error(msg) = &app.status {
-            assert!(msg.contains("Port is required"));
-        }
-
-        // Test invalid port
-        app.port = "invalid".to_string();
-        app.inject_payload();
-        assert!(matches!(app.status, InjectionStatus::Error(_)));
-        if let InjectionStatus::Error(msg) = &app.status {
-            assert!(msg.contains("Invalid port number"));
-        }
+        let wait_for_checksums = |app: &mut App<_, _, _, _, _, _>| {
+            app.sync_file_checksums();
+            let receiver = app.checksum_receiver.take().expect("Not hashing");
+            let result = receiver
+                .recv_timeout(Duration::from_secs(5))
+                .expect("Hashing did not finish");
+            app.file_checksums = Some(result);
+            app.file_checksums.clone().unwrap().expect("Hashing failed")
+        };
+
+        let checksums = wait_for_checksums(&mut app);
+        assert_eq!(checksums.sha256, checksum::sha256_hex(b"abc"));
+        assert_eq!(checksums.crc32, Some(0x352441c2));
+
+        // Unchanged file is not hashed again
+        app.sync_file_checksums();
+        assert!(app.checksum_receiver.is_none());
+
+        // A rebuilt file is
+        std::fs::write(&temp_file, b"abcd").expect("Failed to write test data");
+        let checksums = wait_for_checksums(&mut app);
+        assert_eq!(checksums.sha256, checksum::sha256_hex(b"abcd"));
     }
 
     #[test]
-    fn test_edge_cases() {
+    fn test_payload_view_follows_selected_file() {
         let mut app = App::new(
             mock_inject_fn,
             mock_save_config_fn,
@@ -694,37 +3868,79 @@ mod tests {
             |_, _, _| {},
             |_| {},
             |_, _, _, _| {},
-            (
-                "192.168.1.1".to_string(),
-                "8080".to_string(),
-                "/test/path".to_string(),
-                true,
-            ),
+            Settings::with_values("192.168.1.1", "8080", "", false),
         );
 
-        // Test with whitespace in IP
-        app.ip = "  192.168.1.1  ".to_string();
-        app.port = "8080".to_string();
-        let temp_file = NamedTempFile::new().expect("Failed to create temp file");
-        app.file_path = temp_file.path().to_str().unwrap().to_string();
-
-        assert!(app.is_input_valid()); // Should handle whitespace
-
-        // Test port edge cases
-        app.port = "1".to_string();
-        assert!(app.is_input_valid()); // Port 1 is valid
-
-        app.port = "65535".to_string();
-        assert!(app.is_input_valid()); // Port 65535 is valid
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let first = temp_dir.path().join("first.bin");
+        let mut bytes = vec![0u8; inspect::HEX_PAGE_BYTES * 3];
+        bytes.extend(b"payload v1.02");
+        std::fs::write(&first, &bytes).expect("Failed to write test data");
+        app.file_path = first.display().to_string();
+
+        // Nothing is read while the viewer is closed
+        app.sync_payload_view();
+        assert!(app.payload_view.is_none());
+
+        let wait_for_view = |app: &mut App<_, _, _, _, _, _>| {
+            let deadline = Instant::now() + Duration::from_secs(5);
+            loop {
+                app.sync_payload_view();
+                app.sync_hex_page();
+                if app.payload_view_receiver.is_none() && app.hex_page_receiver.is_none() {
+                    break;
+                }
+                assert!(Instant::now() < deadline, "Reading did not finish");
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        };
+
+        app.run_command(Command::ToggleViewer);
+        wait_for_view(&mut app);
+        let view = app.payload_view.as_ref().expect("File not read");
+        assert_eq!(view.size, Ok(bytes.len() as u64));
+        assert_eq!(view.strings.len(), 1);
+        assert_eq!(view.strings[0].text, "payload v1.02");
+        assert!(view.landmarks.is_empty());
+        let hex_lines = app.hex_lines.as_ref().expect("Page not read");
+        assert_eq!(hex_lines.page, 0);
+        assert_eq!(
+            hex_lines.lines.as_ref().unwrap().len(),
+            inspect::HEX_PAGE_ROWS
+        );
 
-        app.port = "0".to_string();
-        assert!(app.is_input_valid()); // Port 0 is technically valid for u16
+        // Only the page shown is read
+        app.hex_page = 3;
+        wait_for_view(&mut app);
+        let lines = app.hex_lines.as_ref().unwrap().lines.as_ref().unwrap();
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with(&format!("{:08x}", inspect::HEX_PAGE_BYTES * 3)));
+
+        // The page is kept while the file is rebuilt, and reset for another file
+        app.hex_page = 3;
+        bytes.extend(b" rebuilt");
+        std::fs::write(&first, &bytes).expect("Failed to write test data");
+        wait_for_view(&mut app);
+        let view = app.payload_view.as_ref().unwrap();
+        assert_eq!(view.strings[0].text, "payload v1.02 rebuilt");
+        assert_eq!(app.hex_page, 3);
+
+        let second = temp_dir.path().join("second.bin");
+        std::fs::write(&second, b"tiny").expect("Failed to write test data");
+        app.file_path = second.display().to_string();
+        wait_for_view(&mut app);
+        assert_eq!(app.hex_page, 0);
+        assert_eq!(app.payload_view.as_ref().unwrap().strings[0].text, "tiny");
+
+        app.run_command(Command::ToggleViewer);
+        app.sync_payload_view();
+        app.sync_hex_page();
+        assert!(app.payload_view.is_none());
+        assert!(app.hex_lines.is_none());
     }
 
     #[test]
-    fn test_config_loaded_populates_fields() {
-        // This test would require setting up a receiver, which is complex in unit tests
-        // In a real scenario, you'd test the field population logic
+    fn test_import_settings_bundle() {
         let mut app = App::new(
             mock_inject_fn,
             mock_save_config_fn,
@@ -732,48 +3948,60 @@ mod tests {
             |_, _, _| {},
             |_| {},
             |_, _, _, _| {},
-            (
-                "192.168.1.1".to_string(),
-                "8080".to_string(),
-                "/test/path".to_string(),
-                true,
-            ),
+            Settings::with_values("192.168.1.1", "9021", "", false),
         );
-
-        // Simulate what happens when ConfigLoaded is received
-        let (sender, receiver) = mpsc::channel();
-        app.receiver = Some(receiver);
-
-        // Send a ConfigLoaded status
-        sender
-            .send(InjectionStatus::ConfigLoaded(
-                "10.0.0.1".to_string(),
-                "9000".to_string(),
-                "/new/path.txt".to_string(),
-            ))
-            .unwrap();
-
-        // In the real app, this would be handled in the update method
-        // For testing purposes, we simulate the behavior
-        if let Some(receiver) = &app.receiver {
-            if let Ok(new_status) = receiver.try_recv() {
-                if let InjectionStatus::ConfigLoaded(ip, port, file_path) = &new_status {
-                    app.ip = ip.clone();
-                    app.port = port.clone();
-                    app.file_path = file_path.clone();
-                }
-                app.status = new_status;
-            }
+        app.profiles = Profiles::default();
+        app.profiles
+            .upsert(Profile::new("PS5", "10.0.0.5", "9021", "/b.elf"));
+        app.profiles.save().expect("Failed to save profiles");
+
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let path = temp_dir.path().join("settings.json");
+        let mut incoming = Profiles::default();
+        incoming.upsert(Profile::new("PS5", "10.0.0.55", "9021", "/b.elf"));
+        incoming.upsert(Profile::new("Lab", "10.0.0.9", "9021", "/c.elf"));
+        Bundle {
+            profiles: Some(incoming),
+            history: Some(History::default()),
+            ..Bundle::default()
         }
+        .save_to_file(&path)
+        .expect("Failed to save bundle");
 
-        assert_eq!(app.ip, "10.0.0.1");
-        assert_eq!(app.port, "9000");
-        assert_eq!(app.file_path, "/new/path.txt");
-        assert!(matches!(app.status, InjectionStatus::ConfigLoaded(_, _, _)));
+        app.open_bundle_import(path);
+        let import = app.bundle_import.as_mut().expect("Import not opened");
+        assert_eq!(
+            import.choices,
+            [
+                (BundleSection::Profiles, Some(ImportMode::Merge)),
+                (BundleSection::History, Some(ImportMode::Merge)),
+            ]
+        );
+        import.choices[1].1 = None; // Skip the history
+
+        app.import_bundle();
+        let report = app.bundle_import.as_ref().unwrap().report.as_ref().unwrap();
+        assert_eq!(report.summary, ["Profiles: 1 added, 0 unchanged"]);
+        assert_eq!(report.conflicts.len(), 1);
+        assert!(
+            matches!(&app.status, InjectionStatus::ConfigSaved(msg) if msg.contains("1 conflict"))
+        );
+        assert_eq!(app.profiles.profiles.len(), 2);
+        assert_eq!(app.profiles.find("PS5").unwrap().ip, "10.0.0.5");
+
+        // A file that is not a bundle is reported without opening the import
+        app.bundle_import = None;
+        let broken = temp_dir.path().join("broken.json");
+        std::fs::write(&broken, "[]").expect("Failed to write");
+        app.open_bundle_import(broken);
+        assert!(app.bundle_import.is_none());
+        assert!(matches!(&app.status, InjectionStatus::Error(e) if e.contains("settings bundle")));
+
+        let _ = std::fs::remove_file(Profiles::default_path());
     }
 
     #[test]
-    fn test_save_config_status_transitions() {
+    fn test_payload_check_and_import_manifest() {
         let mut app = App::new(
             mock_inject_fn,
             mock_save_config_fn,
@@ -781,100 +4009,143 @@ mod tests {
             |_, _, _| {},
             |_| {},
             |_, _, _, _| {},
-            (
-                "192.168.1.1".to_string(),
-                "8080".to_string(),
-                "/test/path".to_string(),
-                true,
-            ),
+            Settings::with_values("192.168.1.1", "9021", "/payloads/goldhen.bin", false),
         );
+        assert_eq!(app.payload_check(), None);
+
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let manifest_path = temp_dir.path().join("team.toml");
+        std::fs::write(
+            &manifest_path,
+            format!(
+                "[[payloads]]\nname = \"GoldHEN\"\nversion = \"2.4b18\"\nsha256 = \"{}\"\n\
+                 default_port = 9090\nfile_name = \"goldhen.bin\"\n",
+                checksum::sha256_hex(b"abc")
+            ),
+        )
+        .expect("Failed to write manifest");
 
-        // Create a channel for communication
-        let (sender, receiver) = mpsc::channel();
-        app.receiver = Some(receiver);
-
-        // Simulate save config operation
-        (app.save_config_fn)("192.168.1.1", "8080", "/test/path", sender.clone());
+        app.import_manifest(&temp_dir.path().join("missing.json"));
+        assert!(matches!(app.status, InjectionStatus::Error(_)));
+        app.import_manifest(&manifest_path);
+        assert!(matches!(app.status, InjectionStatus::ConfigSaved(_)));
+        assert_eq!(app.manifest.payloads.len(), 1);
+        let _ = std::fs::remove_file(Manifest::default_path());
+
+        app.file_checksums = Some(Ok(Checksums {
+            sha256: checksum::sha256_hex(b"abc"),
+            crc32: None,
+        }));
+        assert!(matches!(
+            app.payload_check(),
+            Some(PayloadCheck::Recognised(_))
+        ));
 
-        // Verify initial InProgress status
-        if let Some(receiver) = &app.receiver {
-            if let Ok(new_status) = receiver.try_recv() {
-                assert!(matches!(new_status, InjectionStatus::InProgress(_)));
-                app.status = new_status;
-            }
-        }
+        app.file_checksums = Some(Ok(Checksums {
+            sha256: checksum::sha256_hex(b"corrupted"),
+            crc32: None,
+        }));
+        assert_eq!(
+            app.payload_check().and_then(|check| check.message()),
+            Some("Hash mismatch for GoldHEN".to_string())
+        );
+    }
 
-        // Simulate successful save completion
-        let _ = sender.send(InjectionStatus::InProgress(
-            "Config saved to 'test.json'".to_string(),
-        ));
-        let _ = sender.send(InjectionStatus::Idle);
+    #[test]
+    fn test_select_file_from_archive() {
+        use std::io::Write;
 
-        // Verify final Idle status
-        if let Some(receiver) = &app.receiver {
-            if let Ok(new_status) = receiver.try_recv() {
-                assert!(matches!(new_status, InjectionStatus::InProgress(_)));
-                app.status = new_status;
-            }
-            if let Ok(new_status) = receiver.try_recv() {
-                assert!(matches!(new_status, InjectionStatus::Idle));
-                app.status = new_status;
+        let mut app = App::new(
+            mock_inject_fn,
+            mock_save_config_fn,
+            mock_load_config_fn,
+            |_, _, _| {},
+            |_| {},
+            |_, _, _, _| {},
+            Settings::with_values("192.168.1.1", "9021", "/test/path", false),
+        );
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let write_zip = |name: &str, entries: &[&str]| {
+            let path = temp_dir.path().join(name);
+            let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+            for entry in entries {
+                zip.start_file(*entry, zip::write::SimpleFileOptions::default())
+                    .unwrap();
+                zip.write_all(b"payload").unwrap();
             }
-        }
-
-        // Verify the app is in Idle state
-        assert!(matches!(app.status, InjectionStatus::Idle));
+            zip.finish().unwrap();
+            path.to_str().unwrap().to_string()
+        };
+
+        // A single payload is selected straight away
+        let single = write_zip("single.zip", &["README.md", "payload.elf"]);
+        app.select_file(&single);
+        assert_eq!(app.file_path, format!("{}!/payload.elf", single));
+        assert!(app.is_input_valid());
+        app.sync_archive_entries();
+        let listing = app.archive_entries.as_ref().expect("Not listed");
+        assert_eq!(listing.entries.as_ref().unwrap().len(), 1);
+
+        // With several the user has to choose
+        let several = write_zip("several.zip", &["ps4.bin", "ps5.elf"]);
+        app.select_file(&several);
+        assert_eq!(app.file_path, several);
+        assert!(!app.is_input_valid());
+        app.inject_payload();
+        assert!(
+            matches!(&app.status, InjectionStatus::Error(e) if e.contains("inside the archive"))
+        );
+        app.sync_archive_entries();
+        let names: Vec<_> = app
+            .archive_entries
+            .as_ref()
+            .unwrap()
+            .entries
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect();
+        assert_eq!(names, ["ps4.bin", "ps5.elf"]);
+
+        app.file_path = "/test/payload.bin".to_string();
+        app.sync_archive_entries();
+        assert!(app.archive_entries.is_none());
+        let _ = std::fs::remove_file(Recent::default_path());
     }
 
     #[test]
-    fn test_reset_with_auto_save_enabled() {
-        let auto_save_called = Arc::new(AtomicBool::new(false));
-        let auto_save_called_clone = auto_save_called.clone();
+    fn test_toggle_host() {
         let mut app = App::new(
             mock_inject_fn,
             mock_save_config_fn,
             mock_load_config_fn,
-            move |ip, port, file_path| {
-                auto_save_called_clone.store(true, Ordering::SeqCst);
-                assert_eq!(ip, DEFAULT_IP);
-                assert_eq!(port, DEFAULT_PORT);
-                assert_eq!(file_path, DEFAULT_FILE_PATH);
-            },
+            |_, _, _| {},
             |_| {},
             |_, _, _, _| {},
-            (
-                "custom.ip".to_string(),
-                "1234".to_string(),
-                "/custom/path".to_string(),
-                true, // Enable auto-save
-            ),
+            Settings::with_values("192.168.1.1", "8080", "/test/path", false),
         );
+        let root = tempfile::tempdir().expect("Failed to create temp dir");
 
-        // Verify initial state
-        assert_eq!(app.ip, "custom.ip");
-        assert_eq!(app.port, "1234");
-        assert_eq!(app.file_path, "/custom/path");
-        assert!(app.auto_save_enabled);
+        app.host_port = "web".to_string();
+        app.toggle_host();
+        assert!(app.host.is_none());
+        assert!(matches!(app.status, InjectionStatus::Error(_)));
 
-        // Simulate reset button click
-        app.ip = DEFAULT_IP.to_string();
-        app.port = DEFAULT_PORT.to_string();
-        app.file_path = DEFAULT_FILE_PATH.to_string();
-        app.status = InjectionStatus::Idle;
-        app.values_changed = true;
+        app.host_port = "0".to_string();
+        app.host_root = "/path/that/does/not/exist".to_string();
+        app.toggle_host();
+        assert!(app.host.is_none());
+        assert!(matches!(app.status, InjectionStatus::Error(_)));
 
-        // Trigger auto-save
-        if app.auto_save_enabled {
-            (app.auto_save_fn)(&app.ip, &app.port, &app.file_path);
-        }
+        app.host_root = root.path().to_str().unwrap().to_string();
+        app.toggle_host();
+        assert!(app.host.is_some());
+        assert!(app.host_receiver.is_some());
 
-        // Verify reset state
-        assert_eq!(app.ip, DEFAULT_IP);
-        assert_eq!(app.port, DEFAULT_PORT);
-        assert_eq!(app.file_path, DEFAULT_FILE_PATH);
-        assert!(matches!(app.status, InjectionStatus::Idle));
-        assert!(app.values_changed);
-        assert!(auto_save_called.load(Ordering::SeqCst)); // Verify auto-save was called
+        app.toggle_host();
+        assert!(app.host.is_none());
+        assert!(app.host_receiver.is_none());
     }
 
     #[test]
@@ -890,12 +4161,7 @@ mod tests {
             },
             |_| {},
             |_, _, _, _| {},
-            (
-                "custom.ip".to_string(),
-                "1234".to_string(),
-                "/custom/path".to_string(),
-                false, // Disable auto-save
-            ),
+            Settings::with_values("custom.ip", "1234", "/custom/path", false), // Disable auto-save
         );
 
         // Verify initial state
@@ -929,12 +4195,7 @@ mod tests {
             |_, _, _| {},
             |_| {},
             |_, _, _, _| {},
-            (
-                "custom.ip".to_string(),
-                "1234".to_string(),
-                "/custom/path".to_string(),
-                true, // Enable auto-save
-            ),
+            Settings::with_values("custom.ip", "1234", "/custom/path", true), // Enable auto-save
         );
 
         // Verify initial auto-save state
@@ -960,12 +4221,7 @@ mod tests {
             |_, _, _| {},
             |_| {},
             |_, _, _, _| {},
-            (
-                "custom.ip".to_string(),
-                "1234".to_string(),
-                "/custom/path".to_string(),
-                false,
-            ),
+            Settings::with_values("custom.ip", "1234", "/custom/path", false),
         );
 
         // Set error state
@@ -992,17 +4248,12 @@ mod tests {
             |_, _, _| {},
             |_| {},
             |_, _, _, _| {},
-            (
-                "custom.ip".to_string(),
-                "1234".to_string(),
-                "/custom/path".to_string(),
-                false,
-            ),
+            Settings::with_values("custom.ip", "1234", "/custom/path", false),
         );
 
         // Set success state
-        app.status = InjectionStatus::Success(100);
-        assert!(matches!(app.status, InjectionStatus::Success(_)));
+        app.status = InjectionStatus::Success(100, None);
+        assert!(matches!(app.status, InjectionStatus::Success(_, _)));
 
         // Simulate reset button click
         app.ip = DEFAULT_IP.to_string();
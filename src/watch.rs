@@ -0,0 +1,393 @@
+use crate::archive;
+use crate::elf;
+use crate::history::{History, HistoryEntry, Trigger};
+use crate::network::{FileTransfer, TransferReport};
+use crate::status::InjectionStatus;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant, SystemTime};
+
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+pub const DEFAULT_SETTLE_TIME: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchOptions {
+    pub poll_interval: Duration,
+    /// The file must stay unchanged this long before it is sent, so a build
+    /// that is still writing it is not picked up half-way
+    pub settle_time: Duration,
+    /// Skip sends of files that are not valid payload ELFs
+    pub require_valid_elf: bool,
+    /// Also compute a CRC32 of each send
+    pub crc32: bool,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            settle_time: DEFAULT_SETTLE_TIME,
+            require_valid_elf: false,
+            crc32: false,
+        }
+    }
+}
+
+/// Size and modification time of a file, used to notice rewrites
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    pub modified: Option<SystemTime>,
+    pub len: u64,
+}
+
+impl FileStamp {
+    /// Current stamp of `path`, or `None` if it does not exist (e.g. mid-rebuild).
+    /// For a file inside an archive this is the stamp of the archive.
+    pub fn of<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let path = path.as_ref();
+        let metadata = match path.to_str() {
+            Some(path) => std::fs::metadata(archive::backing_file(path)).ok()?,
+            None => std::fs::metadata(path).ok()?,
+        };
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// Decides when a watched file has changed and settled
+pub struct ChangeDetector {
+    settle_time: Duration,
+    last_sent: Option<FileStamp>,
+    pending: Option<(FileStamp, Instant)>,
+}
+
+impl ChangeDetector {
+    /// `baseline` is the file's state when watching starts; it is not sent
+    pub fn new(baseline: Option<FileStamp>, settle_time: Duration) -> Self {
+        Self {
+            settle_time,
+            last_sent: baseline,
+            pending: None,
+        }
+    }
+
+    /// Feed the file's current stamp. Returns true once a change has been
+    /// stable for the settle time; each change is reported only once.
+    pub fn observe(&mut self, stamp: Option<FileStamp>, now: Instant) -> bool {
+        let Some(stamp) = stamp else {
+            self.pending = None;
+            return false;
+        };
+
+        if Some(stamp) == self.last_sent {
+            self.pending = None;
+            return false;
+        }
+
+        match self.pending {
+            Some((pending, since)) if pending == stamp => {
+                if now.duration_since(since) >= self.settle_time {
+                    self.last_sent = Some(stamp);
+                    self.pending = None;
+                    true
+                } else {
+                    false
+                }
+            }
+            // New or still-changing content restarts the settle timer
+            _ => {
+                self.pending = Some((stamp, now));
+                false
+            }
+        }
+    }
+}
+
+/// A running watcher; dropping it stops the watch
+pub struct WatchHandle {
+    pub ip: String,
+    pub port: String,
+    pub file_path: String,
+    stop: Arc<AtomicBool>,
+}
+
+impl WatchHandle {
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_watching(&self, ip: &str, port: &str, file_path: &str) -> bool {
+        self.ip == ip && self.port == port && self.file_path == file_path
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Watch `file_path` on a background thread and send it to `ip:port` each time it
+/// changes. Statuses of each send are reported through `sender`.
+pub fn start_watch(
+    ip: &str,
+    port: &str,
+    file_path: &str,
+    options: WatchOptions,
+    sender: mpsc::Sender<InjectionStatus>,
+) -> WatchHandle {
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = WatchHandle {
+        ip: ip.to_string(),
+        port: port.to_string(),
+        file_path: file_path.to_string(),
+        stop: stop.clone(),
+    };
+
+    let (ip, port, file_path) = (ip.to_string(), port.to_string(), file_path.to_string());
+    std::thread::spawn(move || run_watch(&ip, &port, &file_path, &options, &sender, &stop));
+
+    handle
+}
+
+/// Blocking watch loop, returns once `stop` is set
+pub fn run_watch(
+    ip: &str,
+    port: &str,
+    file_path: &str,
+    options: &WatchOptions,
+    sender: &mpsc::Sender<InjectionStatus>,
+    stop: &AtomicBool,
+) {
+    let rt = match tokio::runtime::Runtime::new() {
+        Ok(rt) => rt,
+        Err(e) => {
+            let _ = sender.send(InjectionStatus::Error(format!(
+                "Failed to create async runtime: {}",
+                e
+            )));
+            return;
+        }
+    };
+
+    let mut detector = ChangeDetector::new(FileStamp::of(file_path), options.settle_time);
+
+    while !stop.load(Ordering::SeqCst) {
+        if detector.observe(FileStamp::of(file_path), Instant::now()) {
+            let entry = HistoryEntry::new(Trigger::Watch, ip, port, file_path);
+            let result = rt.block_on(send_changed_file(ip, port, file_path, options, sender));
+            let entry = match &result {
+                Ok(report) => entry.succeeded(report.bytes_sent, Some(&report.checksums.sha256)),
+                Err(e) => entry.failed(e),
+            };
+
+            // Record before reporting, so the history is up to date for listeners.
+            // A failure is reported first so the result stays the latest status.
+            if let Err(e) = History::record(entry) {
+                let _ = sender.send(InjectionStatus::Error(e));
+            }
+
+            let _ = sender.send(match result {
+                Ok(report) => InjectionStatus::Success(report.bytes_sent, Some(report.checksums)),
+                Err(e) => InjectionStatus::Error(e),
+            });
+        }
+
+        std::thread::sleep(options.poll_interval);
+    }
+}
+
+async fn send_changed_file(
+    ip: &str,
+    port: &str,
+    file_path: &str,
+    options: &WatchOptions,
+    sender: &mpsc::Sender<InjectionStatus>,
+) -> Result<TransferReport, String> {
+    let filename = Path::new(file_path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("unknown");
+
+    if options.require_valid_elf {
+        let bytes = archive::read_payload(file_path)?;
+        elf::validate_payload(&bytes).map_err(|e| format!("Not sending '{}': {}", filename, e))?;
+    }
+
+    let _ = sender.send(InjectionStatus::InProgress(format!(
+        "'{}' changed, sending to {}:{}...",
+        filename, ip, port
+    )));
+
+    FileTransfer::new(ip.to_string(), port.to_string(), file_path.to_string())
+        .with_crc32(options.crc32)
+        .send_file_with_checksums()
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DataFile;
+    use crate::history::HISTORY_FILE_NAME;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    fn stamp(len: u64, secs: u64) -> Option<FileStamp> {
+        Some(FileStamp {
+            modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+            len,
+        })
+    }
+
+    #[test]
+    fn test_detector_ignores_baseline() {
+        let start = Instant::now();
+        let mut detector = ChangeDetector::new(stamp(10, 1), Duration::from_millis(500));
+
+        assert!(!detector.observe(stamp(10, 1), start));
+        assert!(!detector.observe(stamp(10, 1), start + Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_detector_waits_for_settle() {
+        let start = Instant::now();
+        let settle = Duration::from_millis(500);
+        let mut detector = ChangeDetector::new(stamp(10, 1), settle);
+
+        // The file keeps growing while it is being written
+        assert!(!detector.observe(stamp(20, 2), start));
+        assert!(!detector.observe(stamp(30, 2), start + Duration::from_millis(300)));
+        assert!(!detector.observe(stamp(30, 2), start + Duration::from_millis(700)));
+
+        // Unchanged for the full settle time since the last change
+        assert!(detector.observe(stamp(30, 2), start + Duration::from_millis(800)));
+
+        // Reported only once
+        assert!(!detector.observe(stamp(30, 2), start + Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn test_detector_handles_missing_file() {
+        let start = Instant::now();
+        let settle = Duration::from_millis(100);
+        let mut detector = ChangeDetector::new(None, settle);
+
+        assert!(!detector.observe(None, start));
+
+        // A file appearing counts as a change
+        assert!(!detector.observe(stamp(5, 1), start));
+        // Deleted again before settling
+        assert!(!detector.observe(None, start + Duration::from_millis(50)));
+        assert!(!detector.observe(stamp(5, 1), start + Duration::from_millis(200)));
+        assert!(detector.observe(stamp(5, 1), start + Duration::from_millis(300)));
+    }
+
+    #[tokio::test]
+    async fn test_send_changed_file_requires_valid_elf() {
+        let temp_file = tempfile::NamedTempFile::new().expect("Failed to create temp file");
+        std::fs::write(&temp_file, b"raw payload").expect("Failed to write test data");
+        let (sender, receiver) = mpsc::channel();
+
+        let options = WatchOptions {
+            require_valid_elf: true,
+            ..WatchOptions::default()
+        };
+        let result = send_changed_file(
+            "127.0.0.1",
+            "9",
+            temp_file.path().to_str().unwrap(),
+            &options,
+            &sender,
+        )
+        .await;
+
+        assert!(result.unwrap_err().contains("Not an ELF file"));
+        // Nothing was attempted
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_watch_sends_on_change() {
+        let temp_dir = tempfile::tempdir().expect("Failed to create temp dir");
+        let payload_path = temp_dir.path().join("payload.bin");
+        std::fs::write(&payload_path, b"first build").expect("Failed to write payload");
+
+        let rt = tokio::runtime::Runtime::new().expect("Failed to create runtime");
+        let listener = rt
+            .block_on(TcpListener::bind("127.0.0.1:0"))
+            .expect("Failed to bind listener");
+        let local_addr = listener.local_addr().expect("Failed to get local address");
+        let server_handle = rt.spawn(async move {
+            let (mut socket, _) = listener.accept().await.expect("Failed to accept");
+            let mut buffer = Vec::new();
+            socket
+                .read_to_end(&mut buffer)
+                .await
+                .expect("Failed to read");
+            buffer
+        });
+
+        let options = WatchOptions {
+            poll_interval: Duration::from_millis(20),
+            settle_time: Duration::from_millis(60),
+            require_valid_elf: false,
+            crc32: true,
+        };
+        let (sender, receiver) = mpsc::channel();
+        let handle = start_watch(
+            &local_addr.ip().to_string(),
+            &local_addr.port().to_string(),
+            payload_path.to_str().unwrap(),
+            options,
+            sender,
+        );
+
+        // The initial file is not sent, a rebuild is
+        std::thread::sleep(Duration::from_millis(100));
+        std::fs::write(&payload_path, b"second build, a bit longer").expect("Failed to write");
+
+        let mut sent = None;
+        while let Ok(status) = receiver.recv_timeout(Duration::from_secs(5)) {
+            if let InjectionStatus::Success(bytes, checksums) = status {
+                sent = Some((bytes, checksums.and_then(|checksums| checksums.crc32)));
+                break;
+            }
+        }
+        handle.stop();
+
+        let second_build = b"second build, a bit longer";
+        assert_eq!(sent, Some((26, Some(crc32fast::hash(second_build)))));
+        let received = rt.block_on(server_handle).expect("Server task failed");
+        assert_eq!(received, b"second build, a bit longer");
+
+        // The send was recorded in the watch thread's history file; remove it
+        let payload = payload_path.to_str().unwrap().to_string();
+        let mut recorded = false;
+        for entry in std::fs::read_dir(".")
+            .expect("Failed to list dir")
+            .flatten()
+        {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with("test-app_config") || !name.ends_with(HISTORY_FILE_NAME) {
+                continue;
+            }
+            if let Ok(history) = History::load_from_file(entry.path()) {
+                if history.entries.iter().any(|e| e.file_path == payload) {
+                    let last = history.entries.last().unwrap();
+                    assert_eq!(last.bytes_sent, Some(26));
+                    assert_eq!(
+                        last.sha256.as_deref(),
+                        Some(crate::checksum::sha256_hex(b"second build, a bit longer").as_str())
+                    );
+                    recorded = true;
+                    let _ = std::fs::remove_file(entry.path());
+                }
+            }
+        }
+        assert!(recorded);
+    }
+}